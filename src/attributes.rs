@@ -0,0 +1,381 @@
+// Attributes modify the role of a player instead of replacing it, see roles.md
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::components::*;
+use crate::events::*;
+use crate::ruleset::*;
+use crate::user::*;
+use crate::GameContext;
+use crate::GameContextHistory;
+use crate::GameState;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum RoleAttribute {
+    // the role is never revealed on death
+    BlackHat,
+    // the role is always revealed on death
+    WhiteHat,
+    // takes a player of their choice along when they die
+    Brutal,
+    // checks of the player give the opposite answer
+    Tinker,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttributeInfo {
+    pub attribute: RoleAttribute,
+    pub name: &'static str,
+    pub icon: &'static str,
+    pub description: &'static str,
+}
+
+pub const ATTRIBUTES: [AttributeInfo; 4] = [
+    AttributeInfo {
+        attribute: RoleAttribute::BlackHat,
+        name: "Black Hat",
+        icon: "🎩",
+        description: "Роль не раскрывается после смерти.",
+    },
+    AttributeInfo {
+        attribute: RoleAttribute::WhiteHat,
+        name: "White Hat",
+        icon: "🤍",
+        description: "Роль всегда раскрывается после смерти.",
+    },
+    AttributeInfo {
+        attribute: RoleAttribute::Brutal,
+        name: "Brutal",
+        icon: "🪓",
+        description: "Погибая, забирает с собой выбранного игрока.",
+    },
+    AttributeInfo {
+        attribute: RoleAttribute::Tinker,
+        name: "Tinker",
+        icon: "🔧",
+        description: "Проверки игрока дают обратный ответ.",
+    },
+];
+
+impl RoleAttribute {
+    pub fn info(&self) -> &'static AttributeInfo {
+        ATTRIBUTES
+            .iter()
+            .find(|info| info.attribute == *self)
+            .expect("every attribute is listed in ATTRIBUTES")
+    }
+}
+
+// Attributes of the player in the ATTRIBUTES order
+pub fn player_attributes(user: &Player) -> Vec<&'static AttributeInfo> {
+    ATTRIBUTES
+        .iter()
+        .filter(|info| user.attributes.contains(&info.attribute))
+        .collect()
+}
+
+// The game doesn't reveal roles, a White Hat is the exception unless it also has a Black Hat
+pub fn is_role_revealed(user: &Player) -> bool {
+    user.attributes.contains(&RoleAttribute::WhiteHat) && !user.attributes.contains(&RoleAttribute::BlackHat)
+}
+
+// A Brutal player who has just died, at night or by the day vote, and has not picked the victim yet
+pub fn pending_revenge(users: &[Player]) -> Option<&Player> {
+    users.iter().find(|u| {
+        u.attributes.contains(&RoleAttribute::Brutal)
+            && !u.used_attributes.contains(&RoleAttribute::Brutal)
+            && u.was_killed
+            && !u.is_alive
+    })
+}
+
+// The dying Brutal player takes the chosen player along in the phase, None when they spare everybody
+pub fn take_revenge(users: &mut [Player], events: &mut Vec<GameEvent>, phase: usize, brutal_id: &str, target: Option<&str>) {
+    if let Some(brutal) = users.iter_mut().find(|u| u.id == brutal_id) {
+        brutal.used_attributes.insert(RoleAttribute::Brutal);
+    }
+
+    let Some(victim) = users.iter_mut().find(|u| Some(u.id.as_str()) == target && u.is_alive) else {
+        return;
+    };
+
+    victim.is_alive = false;
+    victim.was_killed = true;
+    events.push(GameEvent::Revenge {
+        phase,
+        actor: brutal_id.to_string(),
+        target: victim.id.clone(),
+    });
+    events.push(GameEvent::Killed {
+        phase,
+        target: victim.id.clone(),
+        cause: None,
+    });
+}
+
+// Morning reminders about the dead players whose attributes change the reveal or who were taken along
+pub fn attribute_hints(users: &[Player], events: &[GameEvent]) -> Vec<HintLine> {
+    users
+        .iter()
+        .filter(|u| u.was_killed && !u.is_alive)
+        .flat_map(|u| {
+            let reveal = if u.attributes.contains(&RoleAttribute::BlackHat) {
+                Some(HintLine::Player("🎩", u.clone(), "— роль не раскрывается."))
+            } else if is_role_revealed(u) {
+                Some(HintLine::Player("🤍", u.clone(), "— объявите роль."))
+            } else {
+                None
+            };
+            let revenge = revenge_actor(events, &u.id)
+                .and_then(|actor| users.iter().find(|brutal| brutal.id == actor))
+                .map(|brutal| HintLine::Players("🪓", vec![brutal.clone(), u.clone()], "— Brutal забрал с собой."));
+
+            reveal.into_iter().chain(revenge)
+        })
+        .collect()
+}
+
+#[component]
+pub fn UserAttributes(attributes: HashSet<RoleAttribute>) -> impl IntoView {
+    view! {
+        {
+            ATTRIBUTES.iter().filter(|info| attributes.contains(&info.attribute)).map(|info| {
+                view!{
+                    <span class="text-xs" title=info.name>{info.icon}</span>
+                }
+            }).collect::<Vec<_>>().into_view()
+        }
+    }
+}
+
+// Setup step after the roles, any player can get any attributes on top of the role
+#[component]
+pub fn SetupAttributes(on_next: impl Fn() + Copy + 'static, on_close: impl Fn() + Copy + 'static) -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+    let current = create_rw_signal(RoleAttribute::BlackHat);
+    let ids_sorted = move || users_sorted(game_ctx.users.ids());
+
+    view! {
+        <div class="fixed inset-0 z-40 bg-white flex flex-col gap-3 p-3">
+            <h2>"Атрибуты игроков"</h2>
+            <div class="grid grid-cols-2 gap-2">
+                {ATTRIBUTES.iter().map(|info| {
+                    let attribute = info.attribute;
+                    let class = move || if current.get() == attribute {
+                        "px-4 py-2 text-sm bg-blue-300 rounded-full"
+                    } else {
+                        "px-4 py-2 text-sm bg-gray-200 rounded-full"
+                    };
+
+                    view! {
+                        <button class=class on:click=move |_| current.set(attribute)>
+                            {info.icon}" "{info.name}
+                        </button>
+                    }
+                }).collect::<Vec<_>>()}
+            </div>
+            <div class="text-xs text-gray-500">{move || current.get().info().description}</div>
+            <div class="flex-1 overflow-auto">
+                <div class="grid grid-cols-2 gap-y-1 gap-x-3">
+                    <For
+                        each=ids_sorted
+                        key=|id| id.clone()
+                        children=move |id| {
+                            let Some(user) = game_ctx.users.player(&id) else {
+                                return ().into_view();
+                            };
+
+                            view!{
+                                <UserSelectRole
+                                    user
+                                    disabled=false
+                                    highlighted=false
+                                    killed=false
+                                    is_selected=move |u| u.attributes.contains(&current.get())
+                                    on:click=move |_| {
+                                        let attribute = current.get();
+                                        game_ctx.users.update_player(&id, |user| {
+                                            if !user.attributes.remove(&attribute) {
+                                                user.attributes.insert(attribute);
+                                            }
+                                        });
+                                    }
+                                />
+                            }.into_view()
+                        }
+                    />
+                </div>
+            </div>
+            <div class="flex gap-2 w-full items-center">
+                <button
+                    class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full flex items-center justify-center"
+                    on:click=move |_| on_close()
+                >
+                    "←"
+                </button>
+                <button
+                    class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                    on:click=move |_| on_next()
+                >
+                    "Начать игру"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+// A Brutal player who died at night or by the day vote picks the player to take along
+#[component]
+pub fn BrutalRevenge() -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+    let (selected_users, set_selected_users) = create_signal::<HashSet<String>>(HashSet::new());
+
+    let brutal = move || game_ctx.users.with(|users| pending_revenge(users).cloned());
+
+    let take = move |target: Option<String>| {
+        let Some(brutal) = brutal() else {
+            return;
+        };
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
+
+        set_context_history.update(|history| history.push(game_ctx.get_history()));
+        let game_state = game_ctx.game_state.get();
+        let phase = phase_round(game_state, game_ctx.round.get());
+        game_ctx.update_game(|users, events| {
+            take_revenge(users, events, phase, &brutal.id, target.as_deref());
+
+            if let GameState::Game(ruleset, _) = game_state {
+                ruleset.after_kills(users, events, phase);
+            }
+        });
+        set_selected_users.set(HashSet::new());
+    };
+
+    view! {
+        {move || brutal().map(|brutal| view! {
+            <div class="fixed inset-0 z-40 bg-white flex flex-col gap-3 p-3">
+                <h2>{format!("🪓 {} погиб. Кого он заберёт с собой?", brutal.name)}</h2>
+                <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+                    <div class="flex-1"></div>
+                    <div class="flex flex-col gap-1 w-full pb-0.5">
+                        <SelectUsersForVote
+                            selected_users
+                            set_selected_users
+                            is_killed=move |user: &Player| !user.is_alive
+                            is_disabled=move |user| !user.is_alive
+                            is_highlighted=move |_| false
+                            is_single_select=true
+                        />
+                    </div>
+                </div>
+                <div class="flex gap-2 w-full items-center">
+                    <button
+                        class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                        on:click=move |_| take(None)
+                    >
+                        "Никого"
+                    </button>
+                    <button
+                        class="flex-grow-[2] px-4 py-2 text-sm bg-gray-200 rounded-full"
+                        on:click=move |_| {
+                            if let Some(target) = selected_users.get().into_iter().next() {
+                                take(Some(target));
+                            }
+                        }
+                    >
+                        "Забрать"
+                    </button>
+                </div>
+            </div>
+        })}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roles::*;
+    use crate::werewolf::*;
+    use crate::MafiaRole;
+    use crate::MafiaRuleset;
+
+    fn brutal_kills(events: &[GameEvent]) -> Vec<(usize, &str, &str)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::Revenge { phase, actor, target } => Some((*phase, actor.as_str(), target.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_brutal_picks_the_victim_at_night() {
+        let mut users = test_players(&[
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::None,
+            Role::None,
+        ]);
+        let mut events = vec![];
+        users[2].attributes.insert(RoleAttribute::Brutal);
+        users[2].attributes.insert(RoleAttribute::BlackHat);
+
+        test_choice(&users, &mut events, 0, Role::Werewolf(WerewolfRole::Werewolf), &["003"]);
+        resolve_night(&mut users, &mut events, 0);
+
+        // nobody is taken along until the moderator picks the victim
+        assert!(users[0].is_alive && users[1].is_alive);
+        assert_eq!(pending_revenge(&users).map(|u| u.id.as_str()), Some("003"));
+        assert!(!is_role_revealed(&users[2]));
+        assert_eq!(attribute_hints(&users, &events).len(), 1);
+
+        // the victim is picked in the morning
+        take_revenge(&mut users, &mut events, 1, "003", Some("002"));
+        assert!(!users[1].is_alive);
+        assert!(pending_revenge(&users).is_none());
+        assert_eq!(brutal_kills(&events), [(1, "003", "002")]);
+        assert_eq!(kill_causes(&events, "002"), HashSet::new());
+        assert!(attribute_hints(&users, &events).iter().any(|hint| matches!(
+            hint,
+            HintLine::Players("🪓", players, _) if players[0].id == "003" && players[1].id == "002"
+        )));
+    }
+
+    #[test]
+    fn test_brutal_lynched_at_day_takes_the_victim() {
+        let mut users = test_players(&[Role::Werewolf(WerewolfRole::Werewolf), Role::None, Role::None]);
+        let mut events = vec![];
+        users[1].attributes.insert(RoleAttribute::Brutal);
+
+        apply_day_vote(&mut users, &mut events, 0, &HashSet::from(["002".to_string()]));
+        assert_eq!(pending_revenge(&users).map(|u| u.id.as_str()), Some("002"));
+
+        // the vote has moved the game to the next night
+        take_revenge(&mut users, &mut events, 2, "002", Some("001"));
+        assert!(!users[0].is_alive);
+        assert_eq!(brutal_kills(&events), [(2, "002", "001")]);
+        assert!(pending_revenge(&users).is_none());
+    }
+
+    #[test]
+    fn test_brutal_killed_by_mafia_takes_the_victim() {
+        let mafia = Role::Mafia(MafiaRole::Mafia);
+        let mut users = test_players(&[mafia, Role::None, Role::None]);
+        let mut events = vec![];
+        users[1].attributes.insert(RoleAttribute::Brutal);
+
+        test_choice(&users, &mut events, 0, mafia, &["002"]);
+        crate::mafia::calculate_night_kills(&mut users, &mut events, 0);
+        assert_eq!(pending_revenge(&users).map(|u| u.id.as_str()), Some("002"));
+
+        take_revenge(&mut users, &mut events, 1, "002", Some("001"));
+        MafiaRuleset.after_kills(&mut users, &mut events, 1);
+        assert!(!users[0].is_alive && users[2].is_alive);
+        assert_eq!(brutal_kills(&events), [(1, "002", "001")]);
+    }
+}
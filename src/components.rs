@@ -0,0 +1,1312 @@
+use itertools::Itertools;
+use leptos::*;
+use leptos_use::utils::*;
+use leptos_use::*;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::attributes::*;
+use crate::effects::*;
+use crate::events::*;
+use crate::investigation::*;
+use crate::narration::*;
+use crate::notes::*;
+use crate::print::*;
+use crate::roles::*;
+use crate::ruleset::*;
+use crate::rust_create_new_game_log;
+use crate::saves::*;
+use crate::user::*;
+use crate::voting::*;
+use crate::GameContext;
+use crate::GameContextHistory;
+use crate::GameState;
+use crate::GlobalInfo;
+
+#[derive(Debug, Clone)]
+pub struct OpenFinishGameDialogue(pub bool);
+
+pub fn use_ruleset() -> &'static dyn Ruleset {
+    use_context::<&'static dyn Ruleset>().expect("Ruleset not found")
+}
+
+#[component]
+pub fn GameView(ruleset: &'static dyn Ruleset) -> impl IntoView {
+    let open_finish_game_dialogue = create_rw_signal(OpenFinishGameDialogue(false));
+    let is_substituting = create_rw_signal(false);
+    let is_writing_notes = create_rw_signal(false);
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+    provide_context(ruleset);
+    provide_context(open_finish_game_dialogue);
+    ruleset.provide_game_context();
+
+    let game_state_view = move || {
+        if open_finish_game_dialogue.get().0 {
+            return view! {
+                <SelectWinners
+                    on_close=move || open_finish_game_dialogue.set(OpenFinishGameDialogue(false))
+                    on_finish=move |winners, best_players, best_player_votes, best_player_revotes| {
+                        finish_game(&game_ctx, winners, best_players, best_player_votes, best_player_revotes)
+                    }
+                />
+            }
+            .into_view();
+        }
+
+        if is_writing_notes.get() {
+            return view! {
+                <PlayerNotes on_close=move || is_writing_notes.set(false) />
+            }
+            .into_view();
+        }
+
+        if is_substituting.get() {
+            return view! {
+                <SubstitutePlayer on_close=move || is_substituting.set(false) />
+            }
+            .into_view();
+        }
+
+        match game_ctx.game_state.get() {
+            GameState::Game(_, phase) => ruleset.phase_view(phase),
+            _ => view! {
+                <div>"Ошибка"</div>
+            }
+            .into_view(),
+        }
+    };
+
+    view! {
+        <div class="relative flex flex-col gap-4 w-full h-full">
+            <h1 class="text-lg relative w-full text-left">
+                {ruleset.title()}
+                <div class="absolute right-0 top-0 flex gap-1">
+                    <button
+                        class=move || format!("text-sm px-2 py-1 rounded-full {}", if is_substituting.get() { "bg-blue-300" } else { "bg-gray-200" })
+                        on:click=move |_| is_substituting.update(|is_substituting| *is_substituting = !*is_substituting)
+                    >
+                        "Замена"
+                    </button>
+                    <button
+                        class=move || format!("text-sm px-2 py-1 rounded-full {}", if is_writing_notes.get() { "bg-blue-300" } else { "bg-gray-200" })
+                        on:click=move |_| is_writing_notes.update(|is_writing_notes| *is_writing_notes = !*is_writing_notes)
+                    >
+                        "📝"
+                    </button>
+                    <PrintSheetButton />
+                    <PauseGameButton />
+                    {move || if open_finish_game_dialogue.get().0 {
+                        view!{
+                            <button
+                                class="text-sm px-2 py-1 bg-gray-200 rounded-full"
+                                on:click=move |_| {
+                                    open_finish_game_dialogue.set(OpenFinishGameDialogue(false));
+                                }>
+                                "Отмена"
+                            </button>
+                        }
+                    }else{
+                        view!{
+                            <button
+                                class="text-sm px-2 py-1 bg-gray-200 rounded-full"
+                                on:click=move |_| {
+                                    open_finish_game_dialogue.set(OpenFinishGameDialogue(true));
+                                }>
+                                "Завершить игру"
+                            </button>
+                        }
+                    }}
+                </div>
+            </h1>
+            {game_state_view}
+        </div>
+    }
+}
+
+// Index of the current night or day in the players history, days are stored after their night
+pub fn phase_round(game_state: GameState, round: usize) -> usize {
+    match game_state {
+        GameState::Game(_, GamePhase::Night(_) | GamePhase::FirstNight(_)) => round,
+        _ => round + 1,
+    }
+}
+
+#[component]
+fn SubstitutePlayer(on_close: impl Fn() + Copy + 'static) -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+    let global_info = use_context::<GlobalInfo>().expect("GlobalInfo not found");
+
+    let (selected_seat, set_selected_seat) = create_signal::<HashSet<String>>(HashSet::new());
+    let replacement = create_rw_signal::<Option<UserSheetInfo>>(None);
+    let filter_name = create_rw_signal("".to_string());
+
+    let filtered_users = move || {
+        let filter = filter_name.get().to_lowercase().replace("#", "");
+        global_info
+            .users
+            .get()
+            .into_iter()
+            .filter(|u| game_ctx.users.with(|users| !users.iter().any(|user| *u == *user)))
+            .filter(|u| u.id().contains(&filter) || u.name().to_lowercase().starts_with(&filter))
+            .sorted_by(|a, b| a.name().to_lowercase().cmp(&b.name().to_lowercase()))
+            .collect::<Vec<_>>()
+    };
+
+    let onclick_substitute = move |_| {
+        let (Some(seat), Some(replacement)) =
+            (selected_seat.get().into_iter().next(), replacement.get())
+        else {
+            return;
+        };
+
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
+
+        set_context_history.update(|history| history.push(game_ctx.get_history()));
+
+        let first_round = phase_round(game_ctx.game_state.get(), game_ctx.round.get());
+        game_ctx.update_game(|users, events| {
+            if let Some(user) = users.iter_mut().find(|u| u.id == seat) {
+                substitute_player(user, &replacement, first_round);
+                rename_player(events, &seat, &user.id);
+            }
+        });
+
+        on_close();
+    };
+
+    view! {
+        <h2>"Кто уходит из игры?"</h2>
+        <div class="flex flex-col gap-1 w-full">
+            <SelectUsersForVote
+                selected_users=selected_seat
+                set_selected_users=set_selected_seat
+                is_killed=move |user: &Player| !user.is_alive
+                is_disabled=move |user| !user.is_alive
+                is_highlighted=move |_| false
+                is_single_select=true
+            />
+        </div>
+        <h2>"Кто занимает место?"</h2>
+        <input
+            class="px-3 text-sm py-1 border-gray-200 border rounded-full"
+            placeholder="Имя игрока или номер"
+            on:input=move |ev| filter_name.set(event_target_value(&ev))
+            prop:value=filter_name
+        />
+        <div class="flex-1 flex flex-col gap-1 overflow-auto -mx-4 px-4">
+            <For
+                each=filtered_users
+                key=|user| user.id()
+                children=move |user| {
+                    let user_id = user.id();
+                    let is_selected = move || replacement.get().is_some_and(|r| r.id() == user_id);
+
+                    view! {
+                        <button
+                            class=move || format!("flex gap-1 items-baseline justify-start px-3 py-1 text-base rounded-full {}",
+                                if is_selected() { "bg-blue-300" } else { "bg-gray-200" }
+                            )
+                            on:click={
+                                let user = user.clone();
+                                move |_| replacement.set(Some(user.clone()))
+                            }
+                        >
+                            <span class="opacity-70 text-sm w-9">{format!("#{}", user.id())}</span>
+                            {user.name()}
+                            <span class="opacity-50 text-sm">{user.comment()}</span>
+                        </button>
+                    }
+                }
+            />
+        </div>
+        <div class="flex gap-2 w-full items-center">
+            <button
+                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                on:click=move |_| on_close()
+            >
+                "Отмена"
+            </button>
+            <button
+                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                on:click=onclick_substitute
+            >
+                "Заменить"
+            </button>
+        </div>
+    }
+}
+
+#[component]
+fn SelectWinners(
+    on_close: impl Fn() + Clone + 'static,
+    on_finish: impl Fn(HashSet<Role>, HashSet<String>, HashMap<String, String>, HashMap<String, String>)
+        + Clone
+        + 'static,
+) -> impl IntoView {
+    let ruleset = use_ruleset();
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+    let selected_winners = create_rw_signal(HashSet::<Role>::new());
+    let selected_users = create_rw_signal(HashSet::<String>::new());
+    // the picks of the players, voter -> best player
+    let best_player_votes = create_rw_signal(HashMap::<String, String>::new());
+    // the re-vote among the leaders of a tied vote
+    let best_player_revotes = create_rw_signal(HashMap::<String, String>::new());
+    let tied_players = create_rw_signal(HashSet::<String>::new());
+    let is_voting = create_rw_signal(false);
+    let is_revoting = create_rw_signal(false);
+
+    let roles = ruleset.winner_roles();
+    // the last row is right aligned when it has a free cell
+    let has_spacer = roles.len() % 3 == 2;
+
+    let is_selected = move |role: &Role| selected_winners.get().contains(role);
+
+    let calculate_user_logs_fn = move || {
+        calculate_user_logs(
+            ruleset,
+            &game_ctx.users.get(),
+            &game_ctx.events.get(),
+            &selected_users.get(),
+            &selected_winners.get(),
+        )
+    };
+
+    // the player with the most votes gets the star, a tie goes to the re-vote among the leaders,
+    // the moderator can still change the pick
+    let on_votes_counted = move |votes: HashMap<String, String>| {
+        let leaders = best_player_leaders(&game_ctx.users.get(), &votes, &HashMap::new());
+        best_player_votes.set(votes);
+        best_player_revotes.set(HashMap::new());
+        is_voting.set(false);
+
+        if leaders.len() > 1 {
+            tied_players.set(leaders);
+            is_revoting.set(true);
+        } else {
+            selected_users.set(leaders);
+        }
+    };
+
+    let on_revotes_counted = move |revotes: HashMap<String, String>| {
+        selected_users.set(best_player_leaders(&game_ctx.users.get(), &best_player_votes.get(), &revotes));
+        best_player_revotes.set(revotes);
+        is_revoting.set(false);
+    };
+
+    // the re-vote was closed, the moderator picks among the leaders
+    let on_revote_closed = move || {
+        selected_users.set(tied_players.get());
+        is_revoting.set(false);
+    };
+
+    let format_tally = move |votes: &HashMap<String, String>| {
+        tally_votes(&game_ctx.users.get(), votes, |_| 1)
+            .into_iter()
+            .map(|(user, count)| format!("{} — {}", user.name, count))
+            .join(", ")
+    };
+    let votes_tally = move || format_tally(&best_player_votes.get());
+    let revotes_tally = move || format_tally(&best_player_revotes.get());
+
+    view! {
+        <Show when=move || is_voting.get()>
+            <div class="fixed inset-0 z-40 bg-white flex flex-col gap-3 p-3">
+                <VoteCount
+                    title="⭐ Кто лучший игрок?"
+                    voters=game_ctx.users.get_untracked().into_iter().map(|u| (u, 1)).collect()
+                    is_disabled=|voter, user| voter.id == user.id
+                    on_done=on_votes_counted
+                    on_close=move || is_voting.set(false)
+                />
+            </div>
+        </Show>
+        <Show when=move || is_revoting.get()>
+            <div class="fixed inset-0 z-40 bg-white flex flex-col gap-3 p-3">
+                <VoteCount
+                    title="⭐ Переголосование между лидерами"
+                    voters=game_ctx.users.get_untracked().into_iter().map(|u| (u, 1)).collect()
+                    is_disabled=move |voter, user| {
+                        voter.id == user.id || !tied_players.get_untracked().contains(&user.id)
+                    }
+                    on_done=on_revotes_counted
+                    on_close=on_revote_closed
+                />
+            </div>
+        </Show>
+        <div class="flex-1 flex flex-col gap-1 relative overflow-auto px-4 -mx-4">
+            <div class="flex gap-2 items-center justify-between">
+                <h2>"⭐ Выберите лучших игроков"</h2>
+                <button
+                    class="text-sm px-2 py-1 bg-gray-200 rounded-full"
+                    on:click=move |_| is_voting.set(true)
+                >
+                    "🗳️ Голосование"
+                </button>
+            </div>
+            <Show when=move || !best_player_votes.get().is_empty()>
+                <div class="text-xs text-gray-500">"Голоса: "{votes_tally}</div>
+            </Show>
+            <Show when=move || !best_player_revotes.get().is_empty()>
+                <div class="text-xs text-gray-500">"Переголосование: "{revotes_tally}</div>
+            </Show>
+            <div class="grid grid-cols-3 gap-1">
+                <For
+                    each=move || game_ctx.users.ids()
+                    key=|id| id.clone()
+                    children=move |id| {
+                        let Some(user) = game_ctx.users.player(&id) else {
+                            return ().into_view();
+                        };
+
+                        view!{
+                            <UserSelectRole
+                                user
+                                disabled=false
+                                is_selected=move |u| selected_users.get().contains(&u.id)
+                                highlighted=false
+                                killed=false
+                                on:click=move |_| {
+                                    selected_users.update(|users| {
+                                        if users.contains(&id) {
+                                            users.remove(&id);
+                                        } else {
+                                            users.insert(id.clone());
+                                        }
+                                    });
+                                }
+                            />
+                        }.into_view()
+                    }
+                />
+            </div>
+        </div>
+        <div class="flex flex-col gap-1 relative px-4 -mx-4">
+            <h2>"🏆 Выберите кто победил"</h2>
+            <button class=move ||
+                format!("rounded-xl px-3 py-2 text-sm {}", if selected_winners.get().is_empty() {
+                    "text-white bg-red-800/80".to_string()
+                } else {
+                    "bg-gray-200".to_string()
+                })
+                on:click=move|_|{
+                    selected_winners.update(|selected_winners|{
+                        selected_winners.clear();
+                    });
+                }
+            >
+                "Не сохранять результаты"
+            </button>
+            <div class="grid grid-cols-3 gap-1 justify-stretch w-full">
+            {has_spacer.then(|| view! { <div></div> })}
+            {roles.iter().map(|role| {
+                let role_clone = role.get_role();
+                view!{
+                    <button class=move ||
+                        format!("flex-1 rounded-xl px-3 py-5 text-sm {}", if is_selected(
+                            &role.get_role()
+                        ) {
+                            format!("text-white {}", role.get_role_bg_color())
+                        } else {
+                            "bg-gray-200".to_string()
+                        })
+                        on:click=move|_|{
+                            selected_winners.update(|selected_winners|{
+                                if selected_winners.contains(&role_clone) {
+                                    selected_winners.remove(&role_clone);
+                                }else{
+                                    selected_winners.insert(role_clone);
+                                }
+                            });
+                        }
+                    >
+                        {role.get_role_name()}
+                    </button>
+                }
+            }).collect::<Vec<_>>()}
+            </div>
+        </div>
+        <div class="flex gap-2 w-full items-center">
+            <button
+                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                on:click={
+                    move |_| on_close()
+                }
+            >
+                "Назад"
+            </button>
+            <button
+                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                on:click={
+                    move |_| {
+                        if selected_winners.get().is_empty() {
+                            if window().confirm_with_message("Вернуться в главное меню без победителей?").expect("REASON") {
+                                on_finish(HashSet::new(), HashSet::new(), HashMap::new(), HashMap::new());
+                            }
+                        }else{
+                            rust_create_new_game_log(calculate_user_logs_fn(), ruleset.is_mafia_log());
+
+                            on_finish(
+                                selected_winners.get(),
+                                selected_users.get(),
+                                best_player_votes.get(),
+                                best_player_revotes.get(),
+                            );
+                        }
+                    }
+                }
+            >
+                "Закончить игру"
+            </button>
+        </div>
+    }
+}
+
+#[component]
+pub fn UserSelectRole(
+    #[prop(into)] user: Signal<Player>,
+    is_selected: impl Fn(&Player) -> bool + 'static,
+    #[prop(into)] disabled: MaybeSignal<bool>,
+    #[prop(into)] killed: MaybeSignal<bool>,
+    #[prop(into)] highlighted: MaybeSignal<bool>,
+    #[prop(default = "ring-red-600/50".to_string())] highlight_color: String,
+) -> impl IntoView {
+    let ruleset = use_ruleset();
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+    view! {
+        <button
+            disabled=disabled
+            class=move || {
+                let is_selected = user.with(|user| is_selected(user));
+
+                format!("relative overflow-hidden
+                    flex-1 px-3 py-1 text-sm rounded-2xl
+                    flex gap-1.5 items-center justify-center
+                    min-h-8 {} {}",
+                if killed.get() {
+                    "opacity-20 bg-white hover:opacity-90"
+                } else if disabled.get() {
+                    "opacity-60 bg-gray-100 hover:opacity-90"
+                } else if is_selected {
+                    "bg-blue-300"
+                } else {
+                    user.with(|user| ruleset.role_color(user))
+                },
+                if highlighted.get() {
+                    format!("ring-1 {}", highlight_color)
+                } else {
+                    "".to_string()
+                })}
+        >
+            {move || user.with(|user| view! {
+                <div class="flex-grow min-w-0">
+                    <div class="text-left">{user.name.clone()} <UserAdditionalRoles roles=user.additional_role.clone() /><UserAttributes attributes=user.attributes.clone() /><UserEffects effects=user.effects.clone() /></div>
+                    <UserRoleNames role=user.role.clone() />
+                    {user.notes.last().map(|note| view! {
+                        <div class="text-[0.6rem] text-left opacity-60 truncate">"📝 "{note.text.clone()}</div>
+                    })}
+                </div>
+                <UserHistory current=game_ctx.events.with(|events| round_marks(events, game_ctx.round.get(), &user.id)) />
+                {if user.was_killed && !user.is_alive {
+                    view! {
+                        <div class="text-[0.5rem]">"❌"</div>
+                    }.into_view()
+                }else if user.was_killed && user.is_alive {
+                    view! {
+                        <div class="relative text-[0.5rem]">"💛"
+                            <div class="absolute text-[0.3rem] left-[0.15rem] top-[0.04rem]">"❌"</div>
+                        </div>
+                    }.into_view()
+                }else{
+                    "".into_view()
+                }}
+            })}
+        </button>
+    }
+}
+
+#[component]
+fn UserRoleNames(role: HashSet<Role>) -> impl IntoView {
+    view! {
+        <div class="flex flex-wrap gap-x-1 items-start justify-start">
+            {move || {
+                if role.is_empty() {
+                    view!{
+                        <UserRoleName role=Role::None />
+                    }.into_view()
+                }else{
+                    role.iter().map(|role| {
+                        let role = *role;
+
+                        view!{
+                            <UserRoleName role=role />
+                        }
+                    }).intersperse_with(|| {
+                        // Add separator between the roles
+                        view! { <Separator /> }
+                    })
+                    .collect::<Vec<_>>().into_view()
+                }
+            }}
+        </div>
+    }
+}
+
+#[component]
+fn UserKilledBy(killed_by: HashSet<Role>) -> impl IntoView {
+    let killer_roles = use_ruleset().killer_roles();
+
+    view! {
+        {move || {
+            let killed_by = killed_by
+                .clone()
+                .into_iter()
+                .filter(|role| killer_roles.contains(role))
+                .collect::<Vec<_>>();
+
+            if killed_by.is_empty() {
+                view!{
+                    ""
+                }.into_view()
+            }else{
+                view!{
+                    " (by "
+                    {move || killed_by.iter().map(|role| {
+                        let role = *role;
+
+                        view!{
+                            <UserRoleName role=role />
+                        }.into_view()
+                    }).intersperse_with(|| {
+                        view! { ", " }.into_view()
+                    })
+                    .collect::<Vec<_>>().into_view()}
+                    ")"
+                }.into_view()
+            }
+        }}
+    }
+}
+
+#[component]
+fn Separator() -> impl IntoView {
+    view! {
+        <div class="text-xs opacity-50">"•"</div>
+    }
+}
+
+#[component]
+fn UserHistory(current: HashSet<Role>) -> impl IntoView {
+    view! {
+        <div class="flex gap-0.5 flex-wrap min-h-4">
+            {
+                current.iter().map(|role| {
+                    let role = *role;
+
+                    if role != Role::WasKilled{
+                        view!{
+                            <UserRoleIcon role=role is_history=UserRoleIconType::Current />
+                        }
+                    }else{
+                        "".into_view()
+                    }
+                }).collect::<Vec<_>>().into_view()
+            }
+        </div>
+    }
+}
+
+enum UserRoleIconType {
+    History,
+    Current,
+    Additional,
+}
+
+#[component]
+fn UserRoleIcon(role: Role, is_history: UserRoleIconType) -> impl IntoView {
+    use_ruleset()
+        .roles()
+        .iter()
+        .find(|r| r.get_role() == role)
+        .map(|role_info| match is_history {
+            UserRoleIconType::Additional => view! {
+                " "{role_info.get_role_icon()}
+            }
+            .into_view(),
+            _ => view! {
+                <div
+                    class=move || match is_history {
+                        UserRoleIconType::History => "text-xs opacity-80 w-4 h-4",
+                        UserRoleIconType::Current => "text-xs rounded-md bg-white w-4 h-4",
+                        UserRoleIconType::Additional => "text-xs w-4 h-4",
+                    }
+                >
+                    {role_info.get_role_icon()}
+                </div>
+            }
+            .into_view(),
+        })
+}
+
+#[component]
+fn UserRoleName(role: Role) -> impl IntoView {
+    use_ruleset()
+        .roles()
+        .iter()
+        .find(|r| r.get_role() == role)
+        .map_or_else(
+            move || {
+                view! {
+                    <div
+                        class="text-xs opacity-20"
+                    >
+                        "Не выбрано"
+                    </div>
+                }
+            },
+            move |role_info| {
+                view! {
+                    <div
+                        class=move || format!("text-xs opacity-50 {}", role_info.get_role_name_color())
+                    >
+                        {role_info.get_role_name()}
+                    </div>
+                }
+            },
+        )
+        .into_view()
+}
+
+#[component]
+fn UserAdditionalRoles(roles: HashSet<Role>) -> impl IntoView {
+    view! {
+        {
+            roles.iter().map(|role| {
+                view!{
+                    <UserRoleIcon role=*role is_history=UserRoleIconType::Additional />
+                }
+            }).collect::<Vec<_>>().into_view()
+        }
+    }
+}
+
+#[component]
+fn UserEffects(effects: Vec<StatusEffect>) -> impl IntoView {
+    view! {
+        {
+            effects.iter().map(|effect| {
+                view!{
+                    <span class="text-xs" title=effect.kind.description()>{effect.kind.icon()}</span>
+                }
+            }).collect::<Vec<_>>().into_view()
+        }
+    }
+}
+
+pub fn users_sorted<T: Clone>(users: Vec<T>) -> Vec<T> {
+    // Clone and sort the users by a desired attribute if needed. Here, sorting by ID as an example.
+    let len = users.len();
+    let mut rearranged_users = Vec::with_capacity(len);
+
+    let is_odd = len % 2 != 0;
+    // If the length is odd, add the last middle element.
+    if is_odd {
+        rearranged_users.push(users[len / 2].clone());
+    }
+
+    for i in 0..len / 2 {
+        let i = len / 2 - i - 1;
+        // Add the i-th from the end and the i-th from the start in pairs.
+        if is_odd {
+            rearranged_users.push(users[len - i - 1].clone());
+            rearranged_users.push(users[i].clone());
+        } else {
+            rearranged_users.push(users[i].clone());
+            rearranged_users.push(users[len - i - 1].clone());
+        }
+    }
+
+    rearranged_users
+}
+
+#[component]
+pub fn SelectUsersForVote(
+    selected_users: ReadSignal<HashSet<String>>,
+    set_selected_users: WriteSignal<HashSet<String>>,
+    is_killed: impl Fn(&Player) -> bool + 'static,
+    is_disabled: impl Fn(&Player) -> bool + 'static,
+    is_highlighted: impl Fn(&Player) -> bool + 'static,
+    #[prop(optional)] is_single_select: bool,
+    #[prop(default = "ring-red-600/50".to_string())] highlight_color: String,
+) -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+    let alive_counters = use_ruleset().alive_counters();
+
+    let users_alive_len = move || game_ctx.users.with(|users| users.iter().filter(|u| u.is_alive).count());
+    let alive_counters_text = move || {
+        game_ctx.users.with(|users| {
+            alive_counters
+                .iter()
+                .map(|(label, role)| {
+                    let count = users
+                        .iter()
+                        .filter(|u| u.is_alive && u.role.contains(role))
+                        .count();
+
+                    format!(", {}: {}", label, count)
+                })
+                .collect::<String>()
+        })
+    };
+    let is_selected = move |user: &Player| selected_users.get().contains(&user.id);
+    // the rows follow the player and whatever else the checks read, like the current selection
+    let is_killed: Rc<dyn Fn(&Player) -> bool> = Rc::new(is_killed);
+    let is_disabled: Rc<dyn Fn(&Player) -> bool> = Rc::new(is_disabled);
+    let is_highlighted: Rc<dyn Fn(&Player) -> bool> = Rc::new(is_highlighted);
+    let ids_sorted = move || users_sorted(game_ctx.users.ids());
+
+    view! {
+        <div class="text-sm">"Осталось игроков: "{users_alive_len}{alive_counters_text}</div>
+        <div class="grid grid-cols-2 gap-y-1 gap-x-3">
+            <For
+                each=ids_sorted
+                key=|id| id.clone()
+                children=move |id| {
+                    let Some(user) = game_ctx.users.player(&id) else {
+                        return ().into_view();
+                    };
+                    let row_signal = |check: &Rc<dyn Fn(&Player) -> bool>| {
+                        let check = check.clone();
+                        Signal::derive(move || user.with(|user| check(user)))
+                    };
+                    let (disabled, highlighted, killed) =
+                        (row_signal(&is_disabled), row_signal(&is_highlighted), row_signal(&is_killed));
+
+                    view!{
+                        <UserSelectRole
+                            user
+                            disabled=disabled
+                            highlighted=highlighted
+                            highlight_color=highlight_color.clone()
+                            killed=killed
+                            is_selected=is_selected
+                            on:click=move |_| {
+                                set_selected_users.update(|selected_users| {
+                                    if selected_users.contains(&id) {
+                                        selected_users.remove(&id);
+                                    } else {
+                                        if is_single_select {
+                                            selected_users.clear();
+                                        }
+                                        selected_users.insert(id.clone());
+                                    }
+                                });
+                            }
+                        />
+                    }.into_view()
+                }
+            />
+        </div>
+    }
+}
+
+#[component]
+pub fn DayVote() -> impl IntoView {
+    let clock_choose = create_rw_signal(true);
+    let kill_player_choose = create_rw_signal(false);
+    let start_player_choose = create_rw_signal(false);
+    let vote_choose = create_rw_signal(false);
+    let vote_leaders_log = create_rw_signal(Vec::<Player>::new());
+
+    let (highlighted_player, set_highlighted_player) =
+        create_signal::<HashSet<String>>(HashSet::new());
+
+    let ruleset = use_ruleset();
+    let open_dialogue =
+        use_context::<RwSignal<OpenFinishGameDialogue>>().expect("MafiaContext not found");
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+    let (selected_users, set_selected_users) = create_signal::<HashSet<String>>(HashSet::new());
+
+    let onclick_next_role = move || {
+        let selected_users = selected_users.get();
+        let round = game_ctx.round.get();
+
+        game_ctx.update_game(|users, events| ruleset.apply_day_vote(users, events, round, &selected_users));
+        game_ctx.round.set(round + 2);
+
+        match ruleset.next_night_role(None, &game_ctx.users.get()) {
+            Some(role) => game_ctx
+                .game_state
+                .set(GameState::Game(ruleset, GamePhase::Night(role))),
+            None => open_dialogue.set(OpenFinishGameDialogue(true)),
+        }
+    };
+
+    let onclick_kill_users = move || {
+        let selected_users = selected_users.get();
+        let round = game_ctx.round.get();
+
+        game_ctx.update_game(|users, events| ruleset.kill_at_day_start(users, events, round, &selected_users));
+
+        kill_player_choose.set(false);
+        clock_choose.set(true);
+    };
+
+    create_effect(move |_| {
+        kill_player_choose.get();
+        set_selected_users.update(|selected_users| {
+            selected_users.clear();
+        });
+    });
+
+    let onclick_start_player = move || {
+        if !start_player_choose.get() {
+            set_highlighted_player.update(|highlighted_player| {
+                highlighted_player.clear();
+            });
+            start_player_choose.set(true);
+        } else {
+            start_player_choose.set(false);
+        }
+    };
+
+    create_effect(move |_| {
+        if start_player_choose.get() && !highlighted_player.get().is_empty() {
+            start_player_choose.set(false);
+            clock_choose.set(true);
+        }
+    });
+
+    // the speakers go around the table from the player who started the round
+    let game_log = create_memo(move |_| {
+        let mut log = game_ctx.users.with(|users| {
+            let mut log = game_ctx.events.with(|events| {
+                let mut log = ruleset.day_hints(users, events);
+                log.extend(attribute_hints(users, events));
+                log
+            });
+
+            if let Some(first) = highlighted_player.get().iter().next() {
+                log.push(HintLine::Players("🗣️", speech_order(users, first), "говорят по очереди."));
+            }
+
+            log
+        });
+
+        match vote_leaders_log.get().as_slice() {
+            [] => {}
+            [leader] => log.push(HintLine::Player("🗳️", leader.clone(), "набрал больше всех голосов.")),
+            leaders => log.push(HintLine::Players("🗳️", leaders.to_vec(), "— ничья.")),
+        }
+
+        log
+    });
+
+    let narration = Signal::derive(move || {
+        game_ctx.users.with(|users| morning_script(users, game_ctx.round.get(), ruleset.roles()))
+    });
+
+    // the leaders of the count are preselected, the moderator still confirms the vote
+    let on_votes_counted = move |votes: HashMap<String, String>| {
+        let users = game_ctx.users.get();
+        let leaders = day_vote_leaders(ruleset, &users, &votes);
+
+        vote_leaders_log.set(users.into_iter().filter(|u| leaders.contains(&u.id)).collect());
+        set_selected_users.set(leaders);
+        vote_choose.set(false);
+        clock_choose.set(true);
+    };
+
+    let is_highlighted = move |user: &Player| highlighted_player.get().contains(&user.id);
+
+    view! {
+        {move ||
+            if vote_choose.get() {
+                view!{
+                    <VoteCount
+                        title="Кто за кого голосует?"
+                        voters=day_voters(ruleset, game_ctx.users.get_untracked())
+                        is_disabled=|_, user| !user.is_alive || user.has_effect(StatusKind::Banished)
+                        on_done=on_votes_counted
+                        on_close=move || {
+                            vote_choose.set(false);
+                            clock_choose.set(true);
+                        }
+                    />
+                }.into_view()
+            } else if start_player_choose.get() {
+                view!{
+                    <h2>"Кто начал этот раунд?"</h2>
+                    <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+                        <div class="flex-1"></div>
+                        <div class="flex flex-col gap-1 w-full pb-0.5">
+                            <SelectUsersForVote
+                                selected_users=highlighted_player
+                                set_selected_users=set_highlighted_player
+                                is_killed=move |user: &Player| !user.is_alive && !user.was_killed
+                                is_disabled=move |user| !user.is_alive
+                                is_highlighted
+                                highlight_color="ring-blue-600/80".to_string()
+                                is_single_select=true
+                            />
+                        </div>
+                    </div>
+                    <DayTurnButtons onclick_next_role clock_choose start_player_choose kill_player_choose vote_choose onclick_start_player />
+                }.into_view()
+            }else if kill_player_choose.get() {
+                view!{
+                    <h2>"Выберите убитых в начале дня:"</h2>
+                    <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+                        <div class="flex-1"></div>
+                        <div class="flex flex-col gap-1 w-full pb-0.5">
+                            <SelectUsersForVote
+                                selected_users
+                                set_selected_users
+                                is_killed=move |user: &Player| !user.is_alive && !user.was_killed
+                                is_disabled=move |user| !user.is_alive
+                                is_highlighted
+                                highlight_color="ring-blue-600/80".to_string()
+                            />
+                        </div>
+                    </div>
+                    <DayTurnButtons onclick_next_role=onclick_kill_users clock_choose start_player_choose kill_player_choose vote_choose onclick_start_player />
+                }.into_view()
+            }else{
+                view! {
+                    <h2>"Кого мирные жители убъют этим Днем?"</h2>
+                    <NarrationScript lines=narration />
+                    <DisplayLogs logs=game_log />
+                    <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+                        <div class="flex-1"></div>
+                        <div class="flex flex-col gap-1 w-full pb-0.5">
+                            <SelectUsersForVote selected_users set_selected_users
+                                is_killed=move |user: &Player| !user.is_alive && !user.was_killed
+                                is_disabled=move |user| !user.is_alive || user.has_effect(StatusKind::Banished)
+                                is_highlighted
+                                highlight_color="ring-blue-600/80".to_string()
+                            />
+                        </div>
+                    </div>
+                    <Show when=move || clock_choose.get()>
+                        <Timer />
+                    </Show>
+                    <DayTurnButtons onclick_next_role clock_choose start_player_choose kill_player_choose vote_choose onclick_start_player />
+                }.into_view()
+            }
+        }
+    }
+}
+
+#[component]
+pub fn DisplayLogs(logs: Memo<Vec<HintLine>>) -> impl IntoView {
+    view! {
+        <div class="flex flex-col gap-1 relative text-xs">
+            {move || logs.get().iter().map(
+                |log| {
+                    match log {
+                        HintLine::Killed(user, killed_by) => {
+                            let user = user.clone();
+                            view!{
+                                <div class="w-full flex items-center justify-start gap-1 text-gray-500">
+                                    "❌"<span class="bg-gray-100 text-gray-900 px-1 rounded-md">{user.name}</span><UserRoleNames role=user.role />" убит"<UserKilledBy killed_by=killed_by.clone() />"."
+                                </div>
+                            }.into_view()
+                        },
+                        HintLine::Player(icon, user, text) => {
+                            let user = user.clone();
+                            view!{
+                                <div class="w-full flex items-center justify-start gap-1 text-gray-500">
+                                    {*icon}<span class="bg-gray-100 text-gray-900 px-1 rounded-md">{user.name}</span>{*text}
+                                </div>
+                            }.into_view()
+                        },
+                        HintLine::Players(icon, users, text) => {
+                            view!{
+                                <div class="w-full flex-wrap flex items-center justify-start gap-1.5 text-gray-500">
+                                    {*icon}{users.iter().map(|user| {
+                                        let user = user.clone();
+                                        view!{
+                                            <span class="bg-gray-100 text-gray-900 px-1 rounded-md whitespace-nowrap">{user.name}</span>
+                                        }.into_view()
+                                    }).collect::<Vec<_>>().into_view()}{*text}
+                                </div>
+                            }.into_view()
+                        }
+                    }
+                }
+            ).collect::<Vec<_>>().into_view()}
+        </div>
+    }
+}
+
+#[component]
+pub fn Timer() -> impl IntoView {
+    let (time, set_time) = create_signal(0);
+
+    let Pausable {
+        pause,
+        resume,
+        is_active,
+    } = use_interval_fn(
+        move || {
+            set_time.update(|time| {
+                if *time > 0 {
+                    *time -= 1;
+                }
+            });
+        },
+        1000,
+    );
+
+    let get_time = move || {
+        let time = time.get();
+        let minutes = time / 60;
+        let seconds = time % 60;
+
+        format!("{:01}:{:02}", minutes, seconds)
+    };
+
+    pause();
+
+    create_effect(move |_| {
+        if is_active.get() && time.get() == 0 {
+            pause();
+
+            // play alarm sound
+            let audio = web_sys::HtmlAudioElement::new_with_src("assets/alarm.mp3");
+            if let Ok(audio) = audio {
+                let _ = audio.play().unwrap();
+            }
+        }
+    });
+
+    let start_timer = move |start_time: i32| {
+        set_time.update(|time| {
+            *time = start_time;
+        });
+        resume();
+    };
+
+    view! {
+        <div class="flex items-stretch justify-center gap-1">
+            <div class="text-4xl">"⏰"</div>
+            <div class="text-4xl">{get_time}</div>
+            <button class="flex-1 px-1 py-1 text-sm bg-gray-200 rounded-full" on:click={
+                let start_timer = start_timer.clone();
+
+                move|_|{
+                    start_timer(60);
+                }
+            }>"1 мин"</button>
+            <button class="flex-1 px-1 py-1 text-sm bg-gray-200 rounded-full" on:click={
+                let start_timer = start_timer.clone();
+
+                move|_|{
+                    start_timer(30);
+                }
+            }>"30 сек"</button>
+            <button class="flex-1 px-1 py-1 text-sm bg-gray-200 rounded-full" on:click={
+                let start_timer = start_timer.clone();
+
+                move|_|{
+                    start_timer(15);
+                }
+            }>"15 сек"</button>
+        </div>
+    }
+}
+
+#[component]
+fn DayTurnButtons<F, F2>(
+    onclick_next_role: F,
+    onclick_start_player: F2,
+    clock_choose: RwSignal<bool>,
+    start_player_choose: RwSignal<bool>,
+    kill_player_choose: RwSignal<bool>,
+    vote_choose: RwSignal<bool>,
+) -> impl IntoView
+where
+    F: Fn() + 'static,
+    F2: Fn() + 'static,
+{
+    let onclick_prev_role = move |_| {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
+
+        set_context_history.update(|history| {
+            if let Some(prev_ctx) = history.pop() {
+                game_ctx.undo_to(prev_ctx);
+            }
+        });
+    };
+
+    let onclick_next = move |_| {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
+
+        set_context_history.update(|history| history.push(game_ctx.get_history()));
+
+        onclick_next_role();
+    };
+
+    view! {
+        <div class="flex gap-2 w-full items-center">
+        <button
+            class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full flex items-center justify-center"
+            on:click=onclick_prev_role
+        >
+            "←"
+        </button>
+        <button
+            class=move || {
+                format!("flex-1 px-2 py-2 text-sm rounded-full flex items-center justify-center {}", if kill_player_choose.get() {
+                    "bg-blue-500"
+                }else{
+                    "bg-gray-200"
+                })
+            }
+            on:click=move |_| {
+                kill_player_choose.update(|kill_player_choose| {
+                    *kill_player_choose = !*kill_player_choose;
+                });
+                clock_choose.set(false);
+                start_player_choose.set(false);
+            }
+        >
+            "❌"
+        </button>
+        <button
+            class=move || {
+                format!("flex-1 px-2 py-2 text-sm rounded-full flex items-center justify-center {}", if start_player_choose.get() {
+                    "bg-blue-500"
+                }else{
+                    "bg-gray-200"
+                })
+            }
+            on:click=move |_| {
+                onclick_start_player();
+                clock_choose.set(false);
+                kill_player_choose.set(false);
+            }
+        >
+            "🏁"
+        </button>
+        <button
+            class="flex-1 px-2 py-2 text-sm bg-gray-200 rounded-full flex items-center justify-center"
+            on:click=move |_| {
+                vote_choose.set(true);
+                clock_choose.set(false);
+                start_player_choose.set(false);
+                kill_player_choose.set(false);
+            }
+        >
+            "🗳️"
+        </button>
+        <button
+            class=move || {
+                format!("flex-1 px-2 py-2 text-sm rounded-full flex items-center justify-center {}", if clock_choose.get() {
+                    "bg-blue-500"
+                }else{
+                    "bg-gray-200"
+                })
+            }
+            on:click=move |_| {
+                start_player_choose.set(false);
+                clock_choose.update(|clock_choose| {
+                    *clock_choose = !*clock_choose;
+                });
+                kill_player_choose.set(false);
+            }
+        >
+            "⏰"
+        </button>
+        <button
+                class="flex-grow-[2] px-4 py-2 text-sm bg-gray-200 rounded-full"
+                on:click=onclick_next
+            >
+                "Далее"
+            </button>
+        </div>
+    }
+}
+
+// The checked players are shown to the awakened one before the next role.
+// Returns true when the card is opened, the night turn goes on after it is closed.
+pub fn open_check_card(
+    game_ctx: GameContext,
+    role: Role,
+    selected_users: &HashSet<String>,
+    check_card: RwSignal<Option<CheckResult>>,
+) -> bool {
+    if check_card.get().is_some() {
+        return false;
+    }
+
+    let investigation = Investigation::of(role);
+    let Some(result) = investigation.and_then(|i| i.check(&game_ctx.users.get(), selected_users)) else {
+        return false;
+    };
+
+    let targets = selected_users.iter().cloned().collect::<Vec<_>>();
+    let round = game_ctx.round.get();
+
+    game_ctx.users.update(|users| record_check(users, &targets, round, role, result));
+    check_card.set(Some(result));
+    true
+}
+
+// "Далее" stays disabled until the choice of the turn is complete
+#[component]
+pub fn NextTurnButtons<F>(
+    onclick_next_role: F,
+    #[prop(into, optional)] is_next_disabled: MaybeSignal<bool>,
+) -> impl IntoView
+where
+    F: Fn() + 'static,
+{
+    let onclick_prev_role = move |_| {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
+
+        set_context_history.update(|history| {
+            if let Some(prev_ctx) = history.pop() {
+                game_ctx.undo_to(prev_ctx);
+            }
+        });
+    };
+
+    let onclick_next = move |_| {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
+
+        set_context_history.update(|history| history.push(game_ctx.get_history()));
+
+        onclick_next_role();
+    };
+
+    view! {
+        <div class="flex gap-2 w-full items-center">
+            <button
+                class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full flex items-center justify-center"
+                on:click=onclick_prev_role
+            >
+                "←"
+            </button>
+            <button
+                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full disabled:opacity-40"
+                disabled=is_next_disabled
+                on:click=onclick_next
+            >
+                "Далее"
+            </button>
+        </div>
+    }
+}
@@ -0,0 +1,141 @@
+// Temporary states put on a player by a role, each one knows when it goes away
+use serde::{Deserialize, Serialize};
+
+use crate::roles::*;
+use crate::user::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StatusKind {
+    // can't speak during the day
+    Silenced,
+    // the next attack takes the blessing instead of the life
+    Blessed,
+    // sits out the discussion and can't be voted out
+    Banished,
+    // an extra life, lost to the next attack
+    Protected,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Expiry {
+    // removed when the day vote is over
+    EndOfDay,
+    // removed when it saves the player from an attack
+    Triggered,
+    Permanent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub source: Role,
+    // phase index the effect was put on, even rounds are nights
+    pub round: usize,
+    pub expiry: Expiry,
+}
+
+impl StatusKind {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            StatusKind::Silenced => "🤐",
+            StatusKind::Blessed => "✝️",
+            StatusKind::Banished => "🚷",
+            StatusKind::Protected => "🛡️",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            StatusKind::Silenced => "не может говорить.",
+            StatusKind::Blessed => "благословлён.",
+            StatusKind::Banished => "изгнан и не участвует в обсуждении.",
+            StatusKind::Protected => "переживёт следующее нападение.",
+        }
+    }
+}
+
+impl Player {
+    pub fn has_effect(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    // The same role doesn't stack the effect, the newer one replaces it
+    pub fn apply_effect(&mut self, kind: StatusKind, source: Role, round: usize, expiry: Expiry) {
+        self.effects
+            .retain(|effect| effect.kind != kind || effect.source != source);
+        self.effects.push(StatusEffect {
+            kind,
+            source,
+            round,
+            expiry,
+        });
+    }
+
+    // An attack uses up the first effect put on by `sources`, in the given order, returns its source
+    pub fn consume_effect(&mut self, sources: &[Role]) -> Option<Role> {
+        let position = sources.iter().find_map(|source| {
+            self.effects
+                .iter()
+                .position(|effect| effect.source == *source && effect.expiry == Expiry::Triggered)
+        });
+
+        position.map(|position| self.effects.remove(position).source)
+    }
+
+    pub fn can_speak(&self) -> bool {
+        self.is_alive && !self.has_effect(StatusKind::Silenced) && !self.has_effect(StatusKind::Banished)
+    }
+}
+
+// The day vote is over, the effects lasting for the day are gone
+pub fn expire_day_effects(users: &mut [Player]) {
+    for user in users.iter_mut() {
+        user.effects
+            .retain(|effect| effect.expiry != Expiry::EndOfDay);
+    }
+}
+
+// Speakers of the day around the table from `first`, the players who can't speak are skipped
+pub fn speech_order(users: &[Player], first: &str) -> Vec<Player> {
+    let start = users.iter().position(|u| u.id == first).unwrap_or(0);
+
+    users
+        .iter()
+        .cycle()
+        .skip(start)
+        .take(users.len())
+        .filter(|u| u.can_speak())
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WerewolfRole;
+
+    #[test]
+    fn test_effects_expire_and_skip_speakers() {
+        let mut users = test_players(&[Role::None; 4]);
+        let spellcaster = Role::Werewolf(WerewolfRole::Spellcaster);
+        let priest = Role::Werewolf(WerewolfRole::Priest);
+
+        users[1].apply_effect(StatusKind::Silenced, spellcaster, 2, Expiry::EndOfDay);
+        users[2].apply_effect(StatusKind::Blessed, priest, 2, Expiry::Triggered);
+        users[3].is_alive = false;
+
+        let speakers = speech_order(&users, "003");
+        assert_eq!(
+            speakers.iter().map(|u| u.id.as_str()).collect::<Vec<_>>(),
+            ["003", "001"]
+        );
+
+        expire_day_effects(&mut users);
+        assert!(users[1].can_speak());
+        assert!(users[2].has_effect(StatusKind::Blessed));
+
+        assert_eq!(users[2].consume_effect(&[spellcaster]), None);
+        assert_eq!(users[2].consume_effect(&[spellcaster, priest]), Some(priest));
+        assert!(users[2].effects.is_empty());
+    }
+}
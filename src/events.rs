@@ -0,0 +1,391 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::invariants::*;
+use crate::roles::*;
+use crate::user::*;
+
+// What happened in the game, appended as soon as the moderator confirms a choice or the phase is resolved.
+// Phase is the round index: even indexes are nights, odd ones are the days after them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    // `actors` are the alive holders of the role when the choice was confirmed
+    Targeted {
+        phase: usize,
+        actor_role: Role,
+        actors: Vec<String>,
+        target: String,
+    },
+    // the target was attacked and survived thanks to the role
+    Protected {
+        phase: usize,
+        actor_role: Role,
+        target: String,
+    },
+    Killed {
+        phase: usize,
+        target: String,
+        cause: Option<Role>,
+    },
+    Converted {
+        phase: usize,
+        target: String,
+        from: Alignment,
+        to: Alignment,
+        cause: Role,
+    },
+    // executed by the day vote, `by` is the role the moderator marks the execution with
+    Lynched {
+        phase: usize,
+        target: String,
+        by: Role,
+    },
+    // the dying Brutal player took the target along, the death is the Killed event without a cause after it
+    Revenge {
+        phase: usize,
+        actor: String,
+        target: String,
+    },
+    // the Mayor badge is given by the election or passed on by the dying Mayor, None when nobody gets it
+    MayorChosen {
+        phase: usize,
+        mayor: Option<String>,
+    },
+}
+
+impl GameEvent {
+    pub fn phase(&self) -> usize {
+        match self {
+            GameEvent::Targeted { phase, .. }
+            | GameEvent::Protected { phase, .. }
+            | GameEvent::Killed { phase, .. }
+            | GameEvent::Converted { phase, .. }
+            | GameEvent::Lynched { phase, .. }
+            | GameEvent::Revenge { phase, .. }
+            | GameEvent::MayorChosen { phase, .. } => *phase,
+        }
+    }
+
+    pub fn target(&self) -> &str {
+        match self {
+            GameEvent::Targeted { target, .. }
+            | GameEvent::Protected { target, .. }
+            | GameEvent::Killed { target, .. }
+            | GameEvent::Converted { target, .. }
+            | GameEvent::Lynched { target, .. }
+            | GameEvent::Revenge { target, .. } => target,
+            GameEvent::MayorChosen { mayor, .. } => mayor.as_deref().unwrap_or_default(),
+        }
+    }
+
+    // Role icon the event leaves on the target in the logs
+    fn marked_role(&self) -> Option<Role> {
+        match self {
+            GameEvent::Targeted { actor_role, .. } => Some(*actor_role),
+            GameEvent::Killed { .. } => Some(Role::WasKilled),
+            GameEvent::Lynched { by, .. } => Some(*by),
+            GameEvent::Protected { .. }
+            | GameEvent::Converted { .. }
+            | GameEvent::Revenge { .. }
+            | GameEvent::MayorChosen { .. } => None,
+        }
+    }
+}
+
+pub fn is_night_phase(phase: usize) -> bool {
+    phase.is_multiple_of(2)
+}
+
+// Alive players who make the choice of the role
+pub fn role_actors(users: &[Player], role: Role) -> Vec<String> {
+    users
+        .iter()
+        .filter(|u| u.is_alive && u.role.contains(&role))
+        .map(|u| u.id.clone())
+        .collect()
+}
+
+// The choice confirmed by the moderator, one event per target in the seat order
+pub fn record_choice(
+    users: &[Player],
+    events: &mut Vec<GameEvent>,
+    phase: usize,
+    actor_role: Role,
+    actors: &[String],
+    targets: &HashSet<String>,
+) {
+    events.extend(
+        users
+            .iter()
+            .filter(|u| targets.contains(&u.id))
+            .map(|u| GameEvent::Targeted {
+                phase,
+                actor_role,
+                actors: actors.to_vec(),
+                target: u.id.clone(),
+            }),
+    );
+}
+
+// Roles that chose the player in the phase
+pub fn phase_choices(events: &[GameEvent], phase: usize, id: &str) -> HashSet<Role> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            GameEvent::Targeted {
+                phase: event_phase,
+                actor_role,
+                target,
+                ..
+            } if *event_phase == phase && target == id => Some(*actor_role),
+            _ => None,
+        })
+        .collect()
+}
+
+// Marks the player got since the night of `round` started, shown next to the name until the day vote
+pub fn round_marks(events: &[GameEvent], round: usize, id: &str) -> HashSet<Role> {
+    events
+        .iter()
+        .filter(|event| event.phase() >= round && event.target() == id)
+        .filter_map(GameEvent::marked_role)
+        .collect()
+}
+
+pub fn kill_causes(events: &[GameEvent], id: &str) -> HashSet<Role> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            GameEvent::Killed {
+                target,
+                cause: Some(cause),
+                ..
+            } if target == id => Some(*cause),
+            _ => None,
+        })
+        .collect()
+}
+
+// The dying Brutal player who took the player along
+pub fn revenge_actor<'a>(events: &'a [GameEvent], id: &str) -> Option<&'a str> {
+    events.iter().find_map(|event| match event {
+        GameEvent::Revenge { actor, target, .. } if target == id => Some(actor.as_str()),
+        _ => None,
+    })
+}
+
+// One-shot roles like the Witch potions have been used in this or an earlier phase
+pub fn is_role_used(events: &[GameEvent], role: Role) -> bool {
+    events
+        .iter()
+        .any(|event| matches!(event, GameEvent::Targeted { actor_role, .. } if *actor_role == role))
+}
+
+pub fn conversion_event(user: &Player, change: &AlignmentChange) -> GameEvent {
+    GameEvent::Converted {
+        phase: change.round,
+        target: user.id.clone(),
+        from: change.from,
+        to: change.to,
+        cause: change.cause,
+    }
+}
+
+// Results of resolving a phase in the order they happen. A protection is logged only for
+// the players who live through the phase.
+pub struct PhaseOutcome {
+    phase: usize,
+    events: Vec<GameEvent>,
+    protections: Vec<GameEvent>,
+}
+
+impl PhaseOutcome {
+    pub fn new(phase: usize) -> Self {
+        Self {
+            phase,
+            events: vec![],
+            protections: vec![],
+        }
+    }
+
+    pub fn protect(&mut self, target: &Player, actor_role: Role) {
+        self.protections.push(GameEvent::Protected {
+            phase: self.phase,
+            actor_role,
+            target: target.id.clone(),
+        });
+    }
+
+    pub fn kill(&mut self, target: &mut Player, cause: Role) {
+        if !target.is_alive {
+            return;
+        }
+
+        target.is_alive = false;
+        target.was_killed = true;
+        self.events.push(GameEvent::Killed {
+            phase: self.phase,
+            target: target.id.clone(),
+            cause: Some(cause),
+        });
+    }
+
+    pub fn convert(&mut self, target: &mut Player, role: Role, cause: Role) {
+        let changes = target.alignment_changes.len();
+        target.convert(role, self.phase, cause);
+
+        self.events.extend(
+            target.alignment_changes[changes..]
+                .iter()
+                .map(|change| conversion_event(target, change)),
+        );
+    }
+
+    pub fn commit(self, users: &[Player], events: &mut Vec<GameEvent>) {
+        events.extend(self.events);
+
+        for protection in self.protections {
+            let survived = users.iter().any(|u| u.id == protection.target() && u.is_alive);
+
+            if survived && !events.contains(&protection) {
+                events.push(protection);
+            }
+        }
+
+        debug_check_event_invariants(events, "phase outcome");
+    }
+}
+
+// Roles that marked the player in every finished phase, the icons of the logs
+pub fn marked_roles(events: &[GameEvent], id: &str) -> Vec<(usize, HashSet<Role>)> {
+    let mut rounds = Vec::<(usize, HashSet<Role>)>::new();
+
+    for event in events.iter().filter(|event| event.target() == id) {
+        let Some(role) = event.marked_role() else {
+            continue;
+        };
+
+        match rounds.iter_mut().find(|(phase, _)| *phase == event.phase()) {
+            Some((_, roles)) => {
+                roles.insert(role);
+            }
+            None => rounds.push((event.phase(), HashSet::from([role]))),
+        }
+    }
+
+    rounds
+}
+
+// The log follows the seat, a substitute takes over the events of the player who left
+pub fn rename_player(events: &mut [GameEvent], from: &str, to: &str) {
+    for event in events.iter_mut() {
+        match event {
+            GameEvent::Targeted { actors, target, .. } => {
+                for actor in actors.iter_mut().filter(|actor| *actor == from) {
+                    *actor = to.to_string();
+                }
+                if target == from {
+                    *target = to.to_string();
+                }
+            }
+            GameEvent::Revenge { actor, target, .. } => {
+                if actor == from {
+                    *actor = to.to_string();
+                }
+                if target == from {
+                    *target = to.to_string();
+                }
+            }
+            GameEvent::Protected { target, .. }
+            | GameEvent::Killed { target, .. }
+            | GameEvent::Converted { target, .. }
+            | GameEvent::Lynched { target, .. } => {
+                if target == from {
+                    *target = to.to_string();
+                }
+            }
+            GameEvent::MayorChosen { mayor, .. } => {
+                if mayor.as_deref() == Some(from) {
+                    *mayor = Some(to.to_string());
+                }
+            }
+        }
+    }
+}
+
+pub fn was_targeted_by(events: &[GameEvent], id: &str, role: &Role) -> bool {
+    events.iter().any(|event| {
+        matches!(event, GameEvent::Targeted { actor_role, target, .. } if actor_role == role && target == id)
+    })
+}
+
+pub fn last_phase(events: &[GameEvent]) -> usize {
+    events.iter().map(GameEvent::phase).max().unwrap_or(0)
+}
+
+// The alive holders of the role choose the players with the ids in the phase
+#[cfg(test)]
+pub fn test_choice(users: &[Player], events: &mut Vec<GameEvent>, phase: usize, role: Role, targets: &[&str]) {
+    let targets = targets.iter().map(|id| id.to_string()).collect::<HashSet<_>>();
+
+    record_choice(users, events, phase, role, &role_actors(users, role), &targets);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mafia::*;
+    use crate::MafiaRole;
+
+    #[test]
+    fn test_events_follow_the_confirmed_choices() {
+        let mafia = Role::Mafia(MafiaRole::Mafia);
+        let doctor = Role::Mafia(MafiaRole::Doctor);
+        let maniac = Role::Mafia(MafiaRole::Maniac);
+        let mut users = test_players(&[mafia, doctor, maniac, Role::None]);
+
+        let mut events = vec![];
+        for (role, target) in [(mafia, "002"), (doctor, "002"), (maniac, "004")] {
+            test_choice(&users, &mut events, 2, role, &[target]);
+        }
+        calculate_night_kills(&mut users, &mut events, 2);
+
+        let targeted = |actor_role, actor: &str, target: &str| GameEvent::Targeted {
+            phase: 2,
+            actor_role,
+            actors: vec![actor.to_string()],
+            target: target.to_string(),
+        };
+        assert_eq!(
+            events,
+            vec![
+                targeted(mafia, "001", "002"),
+                targeted(doctor, "002", "002"),
+                targeted(maniac, "003", "004"),
+                GameEvent::Killed {
+                    phase: 2,
+                    target: "004".to_string(),
+                    cause: Some(maniac),
+                },
+                GameEvent::Protected {
+                    phase: 2,
+                    actor_role: doctor,
+                    target: "002".to_string(),
+                },
+            ]
+        );
+
+        // the icons of the logs and of the current round come from the same log
+        assert_eq!(marked_roles(&events, "004"), vec![(2, HashSet::from([maniac, Role::WasKilled]))]);
+        assert_eq!(round_marks(&events, 2, "002"), HashSet::from([mafia, doctor]));
+        assert!(round_marks(&events, 4, "002").is_empty());
+        assert_eq!(kill_causes(&events, "004"), HashSet::from([maniac]));
+        assert!(is_role_used(&events, doctor));
+        assert!(was_targeted_by(&events, "002", &doctor));
+        assert_eq!(last_phase(&events), 2);
+
+        rename_player(&mut events, "001", "005");
+        assert_eq!(events[0], targeted(mafia, "005", "002"));
+    }
+}
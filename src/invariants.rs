@@ -0,0 +1,242 @@
+use leptos::*;
+use std::collections::HashSet;
+
+use crate::events::*;
+use crate::user::*;
+
+// Rules every game state has to follow, returns the list of violations
+pub fn check_invariants(users: &[Player], events: &[GameEvent]) -> Result<(), Vec<String>> {
+    let mut violations = Vec::new();
+    let mut ids = HashSet::new();
+
+    for user in users {
+        if !ids.insert(&user.id) {
+            violations.push(format!("{}: duplicate player id", user.name));
+        }
+
+        let is_killed = events
+            .iter()
+            .any(|event| matches!(event, GameEvent::Killed { target, .. } if *target == user.id));
+        if is_killed && user.is_alive {
+            violations.push(format!("{}: killed but still alive", user.name));
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+// Rules of the event log, returns the list of violations
+pub fn check_event_invariants(events: &[GameEvent]) -> Result<(), Vec<String>> {
+    let mut violations = Vec::new();
+    let mut killed = HashSet::new();
+    let mut protected = HashSet::new();
+
+    for event in events {
+        match event {
+            GameEvent::Killed { target, .. } if !killed.insert(target) => {
+                violations.push(format!("{}: killed more than once", target));
+            }
+            GameEvent::Protected { phase, target, .. } => {
+                protected.insert((*phase, target));
+            }
+            _ => {}
+        }
+    }
+
+    for event in events {
+        if let GameEvent::Killed { phase, target, .. } = event {
+            if protected.contains(&(*phase, target)) {
+                violations.push(format!("{}: protected and killed in phase {}", target, phase));
+            }
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+fn report_violations(result: Result<(), Vec<String>>, transition: &str) {
+    if let Err(violations) = result {
+        let message = format!(
+            "invariants broken after {}: {}",
+            transition,
+            violations.join("; ")
+        );
+
+        if cfg!(test) {
+            panic!("{}", message);
+        }
+
+        logging::error!("{}", message);
+    }
+}
+
+// Called after every state transition, broken invariants are reported in debug builds only
+pub fn debug_check_invariants(users: &[Player], events: &[GameEvent], transition: &str) {
+    if cfg!(debug_assertions) {
+        report_violations(check_invariants(users, events).and(check_event_invariants(events)), transition);
+    }
+}
+
+pub fn debug_check_event_invariants(events: &[GameEvent], transition: &str) {
+    if cfg!(debug_assertions) {
+        report_violations(check_event_invariants(events), transition);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mafia;
+    use crate::roles::*;
+    use crate::simulator::SimRng;
+    use crate::werewolf;
+    use crate::MafiaRole;
+
+    const CASES: u64 = 300;
+    const PHASES: usize = 12;
+
+    fn random_players(rng: &mut SimRng) -> Vec<Player> {
+        (0..3 + rng.below(10))
+            .map(|i| Player::new_guest(format!("{:03}", i), format!("User{}", i)))
+            .collect()
+    }
+
+    fn random_selection(users: &[Player], rng: &mut SimRng) -> HashSet<String> {
+        users
+            .iter()
+            .filter(|_| rng.chance(0.2))
+            .map(|u| u.id.clone())
+            .collect()
+    }
+
+    fn assert_invariants(users: &[Player], events: &[GameEvent], seed: u64, phase: usize) {
+        if let Err(violations) = check_invariants(users, events).and(check_event_invariants(events)) {
+            panic!("seed {} phase {}: {}", seed, phase, violations.join("; "));
+        }
+    }
+
+    // Any roles, any targets including dead players and players chosen by several roles
+    #[test]
+    fn test_werewolf_transitions_keep_invariants() {
+        let roles = werewolf::WEREWOLF_ROLES
+            .iter()
+            .filter(|r| !matches!(r, RoleInfo::Icon(_)))
+            .collect::<Vec<_>>();
+
+        for seed in 0..CASES {
+            let mut rng = SimRng::new(seed);
+            let mut users = random_players(&mut rng);
+
+            for user in users.iter_mut() {
+                for _ in 0..rng.below(3) {
+                    let role_info = rng.pick(&roles).unwrap();
+                    match role_info {
+                        RoleInfo::Additional(_) => {
+                            user.additional_role.insert(role_info.get_role());
+                        }
+                        _ => werewolf::insert_user_role(user, role_info.get_role()),
+                    }
+                }
+            }
+            werewolf::initialize_user_roles(&mut users);
+
+            let mut events = vec![];
+            let mut round = 0;
+            for phase in 0..PHASES {
+                if phase % 2 == 0 {
+                    let selected = random_selection(&users, &mut rng);
+                    werewolf::apply_day_vote(&mut users, &mut events, round, &selected);
+                    round += 2;
+                } else {
+                    for role_info in roles.iter().filter(|r| matches!(r, RoleInfo::Night(_))) {
+                        let role = role_info.get_role();
+                        let targets = random_selection(&users, &mut rng);
+                        record_choice(&users, &mut events, round, role, &role_actors(&users, role), &targets);
+                    }
+                    werewolf::resolve_night(&mut users, &mut events, round);
+                }
+
+                assert_invariants(&users, &events, seed, phase);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mafia_transitions_keep_invariants() {
+        let roles = [
+            MafiaRole::Mafia,
+            MafiaRole::Doctor,
+            MafiaRole::Detective,
+            MafiaRole::Maniac,
+            MafiaRole::Prostitute,
+            MafiaRole::Priest,
+        ];
+
+        for seed in 0..CASES {
+            let mut rng = SimRng::new(seed);
+            let mut users = random_players(&mut rng);
+
+            for user in users.iter_mut() {
+                if let Some(role) = rng.pick(&roles).filter(|_| rng.chance(0.6)) {
+                    user.role.insert(Role::Mafia(role));
+                }
+            }
+
+            let mut events = vec![];
+            let mut round = 0;
+            for phase in 0..PHASES {
+                if phase % 2 == 0 {
+                    let selected = random_selection(&users, &mut rng);
+                    mafia::apply_day_vote(&mut users, &mut events, round, &selected);
+                    round += 2;
+                } else {
+                    for role in roles.map(Role::Mafia) {
+                        let targets = random_selection(&users, &mut rng);
+                        record_choice(&users, &mut events, round, role, &role_actors(&users, role), &targets);
+                    }
+                    mafia::calculate_night_kills(&mut users, &mut events, round);
+                }
+
+                assert_invariants(&users, &events, seed, phase);
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_invariants_reports_violations() {
+        let user = Player::new_guest("001".to_string(), "User1".to_string());
+        let killed = |phase| GameEvent::Killed {
+            phase,
+            target: "001".to_string(),
+            cause: None,
+        };
+
+        let violations = check_invariants(&[user], &[killed(1)]).unwrap_err();
+
+        assert_eq!(violations, vec!["User1: killed but still alive".to_string()]);
+
+        let protected = GameEvent::Protected {
+            phase: 2,
+            actor_role: Role::Mafia(MafiaRole::Doctor),
+            target: "001".to_string(),
+        };
+
+        let violations = check_event_invariants(&[killed(1), protected, killed(2)]).unwrap_err();
+
+        assert_eq!(
+            violations,
+            vec![
+                "001: killed more than once".to_string(),
+                "001: protected and killed in phase 2".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,378 @@
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::attributes::*;
+use crate::roles::*;
+use crate::user::*;
+use crate::MafiaRole;
+use crate::WerewolfRole;
+
+// Answer of a night check, shown to the awakened player
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CheckResult {
+    Yes,
+    No,
+    SameTeam,
+    DifferentTeam,
+    // the answer of a team check, shown with the icon of the team
+    InTeam(Alignment),
+    NotInTeam(Alignment),
+}
+
+fn team_icon(alignment: Alignment) -> &'static str {
+    match alignment {
+        Alignment::Village => "✋",
+        Alignment::Tanner => "🧵",
+        Alignment::Maniac => "🔪",
+        Alignment::Mafia => "🔫",
+        Alignment::Vampire => "🧛",
+        Alignment::Werewolf => "🐺",
+    }
+}
+
+impl CheckResult {
+    pub fn from_bool(value: bool) -> Self {
+        if value {
+            CheckResult::Yes
+        } else {
+            CheckResult::No
+        }
+    }
+
+    // What a Tinker makes the check say
+    pub fn inverted(&self) -> Self {
+        match self {
+            CheckResult::Yes => CheckResult::No,
+            CheckResult::No => CheckResult::Yes,
+            CheckResult::SameTeam => CheckResult::DifferentTeam,
+            CheckResult::DifferentTeam => CheckResult::SameTeam,
+            CheckResult::InTeam(alignment) => CheckResult::NotInTeam(*alignment),
+            CheckResult::NotInTeam(alignment) => CheckResult::InTeam(*alignment),
+        }
+    }
+
+    // The answer the checking role is looking for
+    pub fn is_positive(&self) -> bool {
+        matches!(self, CheckResult::Yes | CheckResult::InTeam(_))
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            CheckResult::Yes => "👍",
+            CheckResult::No => "👎",
+            CheckResult::SameTeam => "🤝",
+            CheckResult::DifferentTeam => "⚔️",
+            CheckResult::InTeam(alignment) => team_icon(*alignment),
+            CheckResult::NotInTeam(_) => "🙅",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CheckResult::Yes => "Да",
+            CheckResult::No => "Нет",
+            CheckResult::SameTeam => "Одна команда",
+            CheckResult::DifferentTeam => "Разные команды",
+            CheckResult::InTeam(alignment) => match alignment {
+                Alignment::Village => "Мирный",
+                Alignment::Tanner => "Tanner",
+                Alignment::Maniac => "Маньяк",
+                Alignment::Mafia => "Мафия",
+                Alignment::Vampire => "Вампир",
+                Alignment::Werewolf => "Оборотень",
+            },
+            CheckResult::NotInTeam(alignment) => match alignment {
+                Alignment::Village => "Не мирный",
+                Alignment::Tanner => "Не Tanner",
+                Alignment::Maniac => "Не маньяк",
+                Alignment::Mafia => "Не мафия",
+                Alignment::Vampire => "Не вампир",
+                Alignment::Werewolf => "Не оборотень",
+            },
+        }
+    }
+}
+
+// What a checking role looks at, see roles.md
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Investigation {
+    // one player, is the player in the team
+    Team(Alignment),
+    // one player, is the player a wolf; the Lycan looks like one
+    Wolf,
+    // one player with the nearest alive neighbours, is any of them a wolf
+    WolfNearby,
+    // two players, are they in the same team
+    SameTeam,
+}
+
+// a Wolf Cub is not a wolf for the checks until it is promoted to a Werewolf
+fn is_wolf(user: &Player) -> bool {
+    [WerewolfRole::Werewolf, WerewolfRole::Vampire]
+        .iter()
+        .any(|role| user.role.contains(&Role::Werewolf(*role)))
+}
+
+// Alive players next to the seat on both sides, the dead ones are skipped
+pub fn seat_neighbours<'a>(users: &'a [Player], id: &str) -> Vec<&'a Player> {
+    let Some(index) = users.iter().position(|u| u.id == id) else {
+        return vec![];
+    };
+
+    let left = users[..index]
+        .iter()
+        .rev()
+        .chain(users[index + 1..].iter().rev())
+        .find(|u| u.is_alive);
+    let right = users[index + 1..]
+        .iter()
+        .chain(users[..index].iter())
+        .find(|u| u.is_alive);
+
+    let mut neighbours = left.into_iter().chain(right).collect::<Vec<_>>();
+    neighbours.dedup_by(|a, b| a.id == b.id);
+    neighbours
+}
+
+impl Investigation {
+    pub fn of(role: Role) -> Option<Self> {
+        match role {
+            Role::Mafia(MafiaRole::Detective) => Some(Investigation::Team(Alignment::Mafia)),
+            Role::Mafia(MafiaRole::Priest) => Some(Investigation::Team(Alignment::Maniac)),
+            Role::Werewolf(WerewolfRole::Seer) => Some(Investigation::Wolf),
+            Role::Werewolf(WerewolfRole::ParanormalInvestigator) => Some(Investigation::WolfNearby),
+            Role::Werewolf(WerewolfRole::Mentalist) => Some(Investigation::SameTeam),
+            _ => None,
+        }
+    }
+
+    // Players the moderator should keep an eye on during the check
+    pub fn is_suspect(&self, user: &Player) -> bool {
+        match self {
+            Investigation::Team(alignment) => user.alignment() == *alignment,
+            Investigation::Wolf => is_wolf(user) || user.role.contains(&Role::Werewolf(WerewolfRole::Lycan)),
+            Investigation::WolfNearby => is_wolf(user),
+            Investigation::SameTeam => user.alignment() != Alignment::Village,
+        }
+    }
+
+    // None until the moderator has picked the players the role checks
+    pub fn check(&self, users: &[Player], targets: &HashSet<String>) -> Option<CheckResult> {
+        let checked = users
+            .iter()
+            .filter(|u| targets.contains(&u.id))
+            .collect::<Vec<_>>();
+
+        let result = match (self, checked.as_slice()) {
+            (Investigation::Team(alignment), [user]) => Some(if self.is_suspect(user) {
+                CheckResult::InTeam(*alignment)
+            } else {
+                CheckResult::NotInTeam(*alignment)
+            }),
+            (Investigation::Wolf, [user]) => Some(CheckResult::from_bool(self.is_suspect(user))),
+            (Investigation::WolfNearby, [user]) => {
+                let nearby = seat_neighbours(users, &user.id);
+
+                Some(CheckResult::from_bool(
+                    self.is_suspect(user) || nearby.iter().any(|u| self.is_suspect(u)),
+                ))
+            }
+            (Investigation::SameTeam, [first, second]) => {
+                Some(if first.alignment() == second.alignment() {
+                    CheckResult::SameTeam
+                } else {
+                    CheckResult::DifferentTeam
+                })
+            }
+            _ => None,
+        };
+
+        // a Tinker among the checked players turns the answer over
+        let has_tinker = checked.iter().any(|u| u.attributes.contains(&RoleAttribute::Tinker));
+        result.map(|result| if has_tinker { result.inverted() } else { result })
+    }
+
+    // Alive players the awakened one would get a "Да" for, the others of the pair for the team check
+    pub fn suspects<'a>(&self, users: &'a [Player]) -> Vec<&'a Player> {
+        users
+            .iter()
+            .filter(|user| user.is_alive)
+            .filter(|user| match self {
+                Investigation::SameTeam => self.is_suspect(user),
+                _ => self
+                    .check(users, &HashSet::from([user.id.clone()]))
+                    .is_some_and(|result| result.is_positive()),
+            })
+            .collect()
+    }
+}
+
+// The check of a night, kept on the checked players
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CheckRecord {
+    pub round: usize,
+    pub role: Role,
+    pub result: CheckResult,
+}
+
+pub fn record_check(users: &mut [Player], targets: &[String], round: usize, role: Role, result: CheckResult) {
+    users
+        .iter_mut()
+        .filter(|u| targets.contains(&u.id))
+        .for_each(|u| u.check_results.push(CheckRecord { round, role, result }));
+}
+
+// Big card the moderator turns to the awakened player, a tap closes it
+#[component]
+pub fn CheckResultCard(
+    role_info: &'static RoleInfo,
+    result: CheckResult,
+    on_close: impl Fn() + 'static,
+) -> impl IntoView {
+    view! {
+        <button
+            class="fixed inset-0 z-50 bg-white flex flex-col gap-4 items-center justify-center"
+            on:click=move |_| on_close()
+        >
+            <div class="text-2xl">{role_info.get_role_icon()}" "{role_info.get_role_name()}</div>
+            <div class="text-[10rem] leading-none">{result.icon()}</div>
+            <div class="text-3xl">{result.label()}</div>
+            <div class="text-sm opacity-50">"Нажмите, чтобы продолжить"</div>
+        </button>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mafia::*;
+    use crate::werewolf::*;
+
+    fn check(role: WerewolfRole, users: &[Player], targets: &[&str]) -> Option<CheckResult> {
+        let targets = targets.iter().map(|id| id.to_string()).collect::<HashSet<_>>();
+        Investigation::of(Role::Werewolf(role))?.check(users, &targets)
+    }
+
+    #[test]
+    fn test_lycan_is_a_wolf_only_for_seer() {
+        let users = test_players(&[Role::Werewolf(WerewolfRole::Lycan), Role::None, Role::None, Role::None]);
+
+        assert_eq!(check(WerewolfRole::Seer, &users, &["001"]), Some(CheckResult::Yes));
+        assert_eq!(check(WerewolfRole::ParanormalInvestigator, &users, &["001"]), Some(CheckResult::No));
+        assert_eq!(check(WerewolfRole::Seer, &users, &["001", "002"]), None);
+    }
+
+    #[test]
+    fn test_wolf_cub_is_a_wolf_only_when_promoted() {
+        let mut users = test_players(&[Role::Werewolf(WerewolfRole::WolfCub), Role::None, Role::None, Role::None]);
+
+        assert_eq!(check(WerewolfRole::Seer, &users, &["001"]), Some(CheckResult::No));
+        assert_eq!(check(WerewolfRole::ParanormalInvestigator, &users, &["002"]), Some(CheckResult::No));
+
+        users[0].role.insert(Role::Werewolf(WerewolfRole::Werewolf));
+        assert_eq!(check(WerewolfRole::Seer, &users, &["001"]), Some(CheckResult::Yes));
+    }
+
+    #[test]
+    fn test_paranormal_investigator_checks_alive_neighbours() {
+        let mut users = test_players(&[
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::None,
+            Role::None,
+            Role::None,
+            Role::None,
+        ]);
+        let investigator = WerewolfRole::ParanormalInvestigator;
+
+        // the seats wrap around the table
+        assert_eq!(check(investigator, &users, &["005"]), Some(CheckResult::Yes));
+        assert_eq!(check(investigator, &users, &["002"]), Some(CheckResult::Yes));
+        assert_eq!(check(investigator, &users, &["003"]), Some(CheckResult::No));
+
+        // the dead neighbour is skipped, the next alive one is checked
+        users[2].is_alive = false;
+        assert_eq!(
+            seat_neighbours(&users, "002").iter().map(|u| u.id.as_str()).collect::<Vec<_>>(),
+            vec!["001", "004"]
+        );
+        assert_eq!(check(investigator, &users, &["004"]), Some(CheckResult::No));
+        users[1].is_alive = false;
+        assert_eq!(check(investigator, &users, &["004"]), Some(CheckResult::Yes));
+    }
+
+    #[test]
+    fn test_mentalist_compares_alignments() {
+        let users = test_players(&[
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::Werewolf(WerewolfRole::Minion),
+            Role::Werewolf(WerewolfRole::Lycan),
+        ]);
+        let mentalist = WerewolfRole::Mentalist;
+
+        assert_eq!(check(mentalist, &users, &["001", "002"]), Some(CheckResult::SameTeam));
+        assert_eq!(check(mentalist, &users, &["001", "003"]), Some(CheckResult::DifferentTeam));
+        assert_eq!(check(mentalist, &users, &["001"]), None);
+    }
+
+    #[test]
+    fn test_tinker_turns_the_answer_over() {
+        let mut users = test_players(&[Role::Werewolf(WerewolfRole::Werewolf), Role::Werewolf(WerewolfRole::Minion), Role::None]);
+        users[0].attributes.insert(RoleAttribute::Tinker);
+
+        assert_eq!(check(WerewolfRole::Seer, &users, &["001"]), Some(CheckResult::No));
+        assert_eq!(check(WerewolfRole::Seer, &users, &["003"]), Some(CheckResult::No));
+        assert_eq!(check(WerewolfRole::Mentalist, &users, &["001", "002"]), Some(CheckResult::DifferentTeam));
+    }
+
+    #[test]
+    fn test_seer_hint_skips_dead_wolves() {
+        let mut users = test_players(&[
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::Werewolf(WerewolfRole::Lycan),
+            Role::Werewolf(WerewolfRole::Minion),
+        ]);
+        users[1].is_alive = false;
+
+        let hints = crate::werewolf::night_hints(Role::Werewolf(WerewolfRole::Seer), &users, &[], 0);
+
+        assert!(matches!(
+            hints.as_slice(),
+            [WerewolfHint::Suspects(_, suspects)] if suspects.len() == 1 && suspects[0].id == "001"
+        ));
+    }
+
+    #[test]
+    fn test_detective_gets_the_team() {
+        let mut users = test_players(&[Role::None; 2]);
+        users[0].role.insert(Role::Mafia(MafiaRole::Mafia));
+        let detective = Investigation::of(Role::Mafia(MafiaRole::Detective)).unwrap();
+
+        let result = detective.check(&users, &HashSet::from(["001".to_string()]));
+        assert_eq!(result, Some(CheckResult::InTeam(Alignment::Mafia)));
+        assert_eq!(result.map(|r| r.icon()), Some("🔫"));
+        assert_eq!(
+            detective.check(&users, &HashSet::from(["002".to_string()])),
+            Some(CheckResult::NotInTeam(Alignment::Mafia))
+        );
+        assert_eq!(detective.suspects(&users).len(), 1);
+    }
+
+    #[test]
+    fn test_record_check() {
+        let mut users = test_players(&[Role::None; 2]);
+        let detective = Role::Mafia(MafiaRole::Detective);
+
+        record_check(&mut users, &["002".to_string()], 2, detective, CheckResult::from_bool(true));
+
+        assert!(users[0].check_results.is_empty());
+        assert_eq!(
+            users[1].check_results,
+            vec![CheckRecord {
+                round: 2,
+                role: detective,
+                result: CheckResult::Yes,
+            }]
+        );
+    }
+}
@@ -2,14 +2,45 @@ use std::vec;
 
 use itertools::Itertools;
 use leptos::*;
+use leptos_use::*;
+mod attributes;
+mod components;
+mod effects;
+mod events;
+mod invariants;
+mod investigation;
 mod mafia;
+mod narration;
+mod notes;
+mod one_night;
+mod print;
+mod replay;
 mod roles;
+mod ruleset;
+mod saves;
+#[cfg(test)]
+mod scenarios;
+#[cfg(test)]
+mod simulator;
+mod storage;
+mod store;
+mod summary;
 mod user;
+mod validation;
+mod voting;
 mod werewolf;
 
 use mafia::*;
+use components::*;
+use events::*;
+use one_night::*;
+use replay::*;
 use roles::Role;
+use ruleset::*;
+use saves::*;
 use serde::{Deserialize, Serialize};
+use storage::*;
+use store::*;
 use user::*;
 use wasm_bindgen::prelude::*;
 use web_sys::{console, js_sys, Document, Window};
@@ -72,8 +103,7 @@ fn main() {
 #[derive(Clone, Copy, Debug)]
 pub enum GameState<'a> {
     SetupNames,
-    Mafia(MafiaGameState<'a>),
-    Werewolf(WerewolfGameState<'a>),
+    Game(&'static dyn Ruleset, GamePhase<'a>),
 }
 
 impl Serialize for GameState<'_> {
@@ -83,28 +113,9 @@ impl Serialize for GameState<'_> {
     {
         match self {
             GameState::SetupNames => ("SetupNames", "", Role::None).serialize(serializer),
-            GameState::Mafia(state) => match state {
-                MafiaGameState::Day => ("Mafia", "Day", Role::None).serialize(serializer),
-                MafiaGameState::Night(role) => {
-                    ("Mafia", "Night", role.get_role()).serialize(serializer)
-                }
-                MafiaGameState::SetupRoles(role) => {
-                    ("Mafia", "SetupRoles", role.get_role()).serialize(serializer)
-                }
-            },
-            GameState::Werewolf(state) => match state {
-                WerewolfGameState::Day => ("Werewolf", "Day", Role::None).serialize(serializer),
-                WerewolfGameState::Night(role) => {
-                    ("Werewolf", "Night", role.get_role()).serialize(serializer)
-                }
-                WerewolfGameState::SelectActiveRoles => {
-                    ("Werewolf", "SelectActiveRoles", Role::None).serialize(serializer)
-                }
-                WerewolfGameState::End => ("Werewolf", "End", Role::None).serialize(serializer),
-                WerewolfGameState::SetupRoles(role) => {
-                    ("Werewolf", "SetupRoles", role.get_role()).serialize(serializer)
-                }
-            },
+            GameState::Game(ruleset, phase) => {
+                (ruleset.name(), phase.name(), phase.role()).serialize(serializer)
+            }
         }
     }
 }
@@ -114,42 +125,19 @@ impl<'de, 'a> Deserialize<'de> for GameState<'a> {
     where
         D: serde::de::Deserializer<'de>,
     {
-        let value = serde::de::Deserialize::deserialize(deserializer)?;
-
-        match value {
-            ("SetupNames", "", Role::None) => Ok(GameState::SetupNames),
-            ("Mafia", "Day", Role::None) => Ok(GameState::Mafia(MafiaGameState::Day)),
-            ("Mafia", "Night", role) => MAFIA_ROLES
-                .iter()
-                .find(|r| r.get_role() == role)
-                .map_or(Err(serde::de::Error::custom("invalid value")), |role| {
-                    Ok(GameState::Mafia(MafiaGameState::Night(role)))
-                }),
-            ("Mafia", "SetupRoles", role) => MAFIA_ROLES
-                .iter()
-                .find(|r| r.get_role() == role)
-                .map_or(Err(serde::de::Error::custom("invalid value")), |role| {
-                    Ok(GameState::Mafia(MafiaGameState::SetupRoles(role)))
-                }),
-            ("Werewolf", "Day", Role::None) => Ok(GameState::Werewolf(WerewolfGameState::Day)),
-            ("Werewolf", "Night", role) => WEREWOLF_ROLES
-                .iter()
-                .find(|r| r.get_role() == role)
-                .map_or(Err(serde::de::Error::custom("invalid value")), |role| {
-                    Ok(GameState::Werewolf(WerewolfGameState::Night(role)))
-                }),
-            ("Werewolf", "SelectActiveRoles", Role::None) => {
-                Ok(GameState::Werewolf(WerewolfGameState::SelectActiveRoles))
-            }
-            ("Werewolf", "End", Role::None) => Ok(GameState::Werewolf(WerewolfGameState::End)),
-            ("Werewolf", "SetupRoles", role) => WEREWOLF_ROLES
-                .iter()
-                .find(|r| r.get_role() == role)
-                .map_or(Err(serde::de::Error::custom("invalid value")), |role| {
-                    Ok(GameState::Werewolf(WerewolfGameState::SetupRoles(role)))
-                }),
-            _ => Err(serde::de::Error::custom("invalid value")),
+        let (game, state, role): (String, String, Role) =
+            serde::de::Deserialize::deserialize(deserializer)?;
+
+        if (game.as_str(), state.as_str(), role) == ("SetupNames", "", Role::None) {
+            return Ok(GameState::SetupNames);
         }
+
+        ruleset_by_name(&game)
+            .and_then(|ruleset| {
+                GamePhase::decode(&state, role, ruleset)
+                    .map(|phase| GameState::Game(ruleset, phase))
+            })
+            .ok_or_else(|| serde::de::Error::custom("invalid value"))
     }
 }
 
@@ -164,70 +152,121 @@ struct GameContextHistory {
     users: Vec<Player>,
     round: usize,
     game_state: GameState<'static>,
+    save_id: Option<String>,
+    one_night: OneNightCards,
+    #[serde(default)]
+    events: Vec<GameEvent>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
 struct GameContext {
-    users: RwSignal<Vec<Player>>,
+    users: PlayerStore,
     round: RwSignal<usize>,
     game_state: RwSignal<GameState<'static>>,
+    save_id: RwSignal<Option<String>>,
+    one_night: RwSignal<OneNightCards>,
+    events: RwSignal<Vec<GameEvent>>,
 }
 
-const STORAGE_LAST_STATE: &str = "last_state";
+const PERSIST_DELAY_MS: f64 = 500.0;
+
+const GAME_STATE_STORAGE: VersionedStorage = VersionedStorage {
+    key: "last_state",
+    migrations: &GAME_STATE_MIGRATIONS,
+};
+
+// keep the players of a broken saved game, the rest of the state can't be trusted
+fn recover_game_context_history(value: &serde_json::Value) -> Option<GameContextHistory> {
+    let users = value
+        .get("users")?
+        .as_array()?
+        .iter()
+        .filter_map(|user| {
+            let id = user.get("id")?.as_str()?.to_string();
+            let name = user.get("name")?.as_str()?.to_string();
+            let comment = user.get("comment").and_then(|c| c.as_str()).unwrap_or("").to_string();
+            let is_guest = user.get("is_guest").and_then(|g| g.as_bool()).unwrap_or(false);
+
+            Some(Player::new(id, name, comment, is_guest))
+        })
+        .collect::<Vec<_>>();
+
+    Some(GameContextHistory {
+        users,
+        round: 0,
+        game_state: GameState::SetupNames,
+        save_id: None,
+        one_night: OneNightCards::default(),
+        events: vec![],
+    })
+}
 
 impl Default for GameContext {
     fn default() -> Self {
         // load from local storage
-        let local_storage = window().local_storage().ok().flatten();
-        let starting_users = local_storage.and_then(|storage| {
-            storage
-                .get_item(STORAGE_LAST_STATE)
-                .ok()
-                .flatten()
-                .and_then(|value| serde_json::from_str::<GameContextHistory>(&value).ok())
-        });
+        let starting_users = GAME_STATE_STORAGE.load_or_recover(recover_game_context_history);
 
         if let Some(history) = starting_users {
             return Self {
-                users: create_rw_signal(history.users),
+                users: PlayerStore::new(history.users),
                 round: create_rw_signal(history.round),
                 game_state: create_rw_signal(history.game_state),
+                save_id: create_rw_signal(history.save_id),
+                one_night: create_rw_signal(history.one_night),
+                events: create_rw_signal(history.events),
             };
         }
 
         Self {
-            users: create_rw_signal(vec![]),
+            users: PlayerStore::new(vec![]),
             round: create_rw_signal(0),
             game_state: create_rw_signal(GameState::SetupNames),
+            save_id: create_rw_signal(None),
+            one_night: create_rw_signal(OneNightCards::default()),
+            events: create_rw_signal(vec![]),
         }
     }
 }
 
 impl GameContext {
-    pub fn store_context_to_local_storage(&self) {
-        let context = self.get_history();
-
-        let json = serde_json::to_string(&context).expect("couldn't serialize GameContext");
-
-        if let Ok(Some(storage)) = window().local_storage() {
-            if storage.set_item(STORAGE_LAST_STATE, &json).is_err() {
-                //log::error!("error while trying to set item in localStorage");
-            }
-        }
-    }
-
     pub fn get_history(&self) -> GameContextHistory {
         GameContextHistory {
             users: self.users.get(),
             round: self.round.get(),
             game_state: self.game_state.get(),
+            save_id: self.save_id.get(),
+            one_night: self.one_night.get(),
+            events: self.events.get(),
         }
     }
 
     pub fn set_history(&self, history: GameContextHistory) {
-        self.users.set(history.users);
-        self.round.set(history.round);
-        self.game_state.set(history.game_state);
+        // autosave should see the whole state at once
+        batch(|| {
+            self.users.set(history.users);
+            self.round.set(history.round);
+            self.game_state.set(history.game_state);
+            self.save_id.set(history.save_id);
+            self.one_night.set(history.one_night);
+            self.events.set(history.events);
+        });
+    }
+
+    // Players and the event log change together when a phase is over
+    pub fn update_game(&self, f: impl FnOnce(&mut Vec<Player>, &mut Vec<GameEvent>)) {
+        let mut events = self.events.get_untracked();
+
+        batch(|| {
+            self.users.update(|users| f(users, &mut events));
+            self.events.set(events);
+        });
+    }
+
+    // Step back to an earlier state of the same game
+    pub fn undo_to(&self, mut history: GameContextHistory) {
+        carry_notes(&self.users.get_untracked(), &mut history.users);
+
+        self.set_history(history);
     }
 }
 
@@ -248,7 +287,7 @@ fn StartScreen() -> impl IntoView {
 
     let game_context = GameContext::default();
 
-    provide_context(game_context.clone());
+    provide_context(game_context);
 
     let global_info = GlobalInfo {
         is_authenticated: create_rw_signal(false),
@@ -284,8 +323,6 @@ fn StartScreen() -> impl IntoView {
 
     wasm_bindgen_futures::spawn_local(check_auth);
 
-    let game_context_clone = game_context.clone();
-
     create_effect(move |_| {
         if global_info.is_authenticated.get() {
             wasm_bindgen_futures::spawn_local(async move {
@@ -318,7 +355,7 @@ fn StartScreen() -> impl IntoView {
                         let global_info_users = global_info.users.get_untracked();
 
                         // Update the selected user names
-                        game_context_clone.users.update(move |users| {
+                        game_context.users.update(move |users| {
                             for user in users.iter_mut() {
                                 if let Some(info) = global_info_users.iter().find(|u| *u == user) {
                                     user.name = info.name().to_string();
@@ -340,14 +377,47 @@ fn StartScreen() -> impl IntoView {
     provide_context(context_history);
     provide_context(set_context_history);
 
-    let game_context_clone = game_context.clone();
+    let saved_games = SavedGames::default();
+
+    provide_context(saved_games.clone());
+
+    let finished_games = FinishedGames::default();
+
+    provide_context(finished_games.clone());
+
+    // the whole game is serialized once the moderator pauses, not on every click
+    let persist_game = move || {
+        let state = game_context.get_history();
+        let history = context_history.get_untracked();
+
+        GAME_STATE_STORAGE.store(&state);
+        saved_games.0.update(|games| {
+            upsert_saved_game(games, state, history, js_sys::Date::now());
+        });
+    };
+    let persist_game_debounced = use_debounce_fn(persist_game, PERSIST_DELAY_MS);
+
+    create_effect(move |_| {
+        game_context.users.track();
+        game_context.round.track();
+        game_context.game_state.track();
+        game_context.save_id.track();
+        game_context.one_night.track();
+        game_context.events.track();
+        context_history.track();
+
+        persist_game_debounced();
+    });
+
+    // the pending save must not be lost when the tab is closed
+    window_event_listener(ev::pagehide, move |_| persist_game());
 
     create_effect(move |_| {
-        // game_context_clone.users.get();
-        // game_context_clone.round.get();
-        // game_context_clone.game_state.get();
+        SAVED_GAMES_STORAGE.store(&saved_games.0.get());
+    });
 
-        game_context_clone.store_context_to_local_storage();
+    create_effect(move |_| {
+        FINISHED_GAMES_STORAGE.store(&finished_games.0.get());
     });
 
     let game_state_view = move || match game_context.game_state.get() {
@@ -355,12 +425,8 @@ fn StartScreen() -> impl IntoView {
             <SetupUsers />
         }
         .into_view(),
-        GameState::Mafia(_) => view! {
-            <MafiaGameView />
-        }
-        .into_view(),
-        GameState::Werewolf(_) => view! {
-            <WerewolfGameView />
+        GameState::Game(ruleset, _) => view! {
+            <GameView ruleset />
         }
         .into_view(),
     };
@@ -384,17 +450,21 @@ fn SetupUsers() -> impl IntoView {
     let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
 
     let players = move || game_ctx.users.get().into_iter().enumerate();
-    let player_len = move || players().count();
+    let player_len = move || game_ctx.users.with(|users| users.len());
 
     let is_adding_player = create_rw_signal(false);
+    let is_showing_finished_games = create_rw_signal(false);
 
     view! {
         <div class="relative flex flex-col gap-4 w-full h-full">
-            <h2 class="flex w-full items-baseline justify-start gap-2">
-                "Выбранные игроки ("{player_len}")"
-            </h2>
             {move ||
-                if is_adding_player.get() {
+                if is_showing_finished_games.get() {
+                    view! {
+                        <FinishedGamesView on_close=move || {
+                            is_showing_finished_games.set(false);
+                        } />
+                    }
+                } else if is_adding_player.get() {
                     view! {
                         <SelectPlayersForGame on_close=move || {
                             is_adding_player.set(false);
@@ -402,6 +472,18 @@ fn SetupUsers() -> impl IntoView {
                     }
                 } else {
                     view! {
+                        <SavedGamesList />
+                        <h2 class="flex w-full items-baseline justify-between gap-2">
+                            "Выбранные игроки ("{player_len}")"
+                            <button
+                                class="text-sm px-2 py-1 bg-gray-200 rounded-full"
+                                on:click=move |_| {
+                                    is_showing_finished_games.set(true);
+                                }
+                            >
+                                "Завершенные игры"
+                            </button>
+                        </h2>
                         <div class="flex-1 flex flex-col gap-1 overflow-auto -mx-4 px-4">
                             <For
                                 each=players
@@ -769,65 +851,41 @@ fn SelectPlayersForGame(on_close: impl Fn() -> () + Clone + 'static) -> impl Int
 fn StartGames() -> impl IntoView {
     let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
 
-    let game_ctx_clone = game_ctx.clone();
-    let start_werewolf_game = move |_| {
+    let start_game = move |ruleset: &'static dyn Ruleset| {
         game_ctx.users.update(|users| {
             reset_user_roles(users);
         });
+        game_ctx.one_night.set(OneNightCards::default());
+        game_ctx.events.set(vec![]);
 
         let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
             .expect("MafiaContext history not found");
 
         set_context_history.update(|history| {
             history.clear();
-            history.push(game_ctx_clone.get_history());
-        });
-
-        // // let first_role = WEREWOLF_ROLES.iter().filter(|r| r.get_role() == Role::Werewolf(WerewolfRole::Bodyguard)).next().unwrap();
-
-        game_ctx
-            .game_state
-            .set(GameState::Werewolf(WerewolfGameState::SelectActiveRoles));
-    };
-
-    let game_ctx_clone = game_ctx.clone();
-    let start_mafia_game = move |_| {
-        game_ctx.users.update(|users| {
-            reset_user_roles(users);
+            history.push(game_ctx.get_history());
         });
 
-        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
-            .expect("MafiaContext history not found");
-
-        set_context_history.update(|history| {
-            history.clear();
-            history.push(game_ctx_clone.get_history());
+        batch(|| {
+            game_ctx.save_id.set(Some(new_save_id()));
+            game_ctx
+                .game_state
+                .set(GameState::Game(ruleset, ruleset.start_phase()));
         });
-
-        let first_role = MAFIA_ROLES
-            .iter()
-            .filter(|r| r.get_role() == Role::Mafia(MafiaRole::Mafia))
-            .next()
-            .unwrap();
-        game_ctx
-            .game_state
-            .set(GameState::Mafia(MafiaGameState::SetupRoles(first_role)));
     };
 
     view! {
         <div class="flex gap-1 items-center justify-between">
-            <button
-                on:click=start_werewolf_game
-                    class="flex-grow px-4 py-1 bg-gray-200 rounded-full"
-            >
-                "Начать в Werewolf"
-            </button>
-            <button
-                on:click=start_mafia_game
-                class="flex-grow px-4 py-1 bg-gray-200 rounded-full"
-            >
-                "Начать в Мафию"
-            </button>
+            {RULESETS.iter().map(|ruleset| {
+                view! {
+                    <button
+                        on:click=move |_| start_game(*ruleset)
+                        class="flex-grow px-4 py-1 bg-gray-200 rounded-full"
+                    >
+                        {ruleset.start_label()}
+                    </button>
+                }
+            }).collect::<Vec<_>>()}
         </div>
     }
 }
@@ -0,0 +1,188 @@
+use leptos::*;
+
+use crate::attributes::*;
+use crate::roles::*;
+use crate::user::*;
+
+const NIGHT_START: &str = "Город засыпает. Все закрывают глаза.";
+const NIGHT_END: &str = "Город просыпается.";
+
+fn is_night_role_alive(role_info: &RoleInfo, users: &[Player]) -> bool {
+    matches!(role_info, RoleInfo::Night(_))
+        && users
+            .iter()
+            .any(|u| u.is_alive && u.role.contains(&role_info.get_check_role()))
+}
+
+// continuation steps (check_role of the main role) share the wake up of the main role
+fn is_same_wake_up(role_info: &RoleInfo, other: &RoleInfo) -> bool {
+    role_info.get_check_role() == other.get_check_role()
+}
+
+// Script for the night step of role_info: wake up, question and sleep lines.
+// The first and the last alive night roles also get the city sleep / wake up lines.
+pub fn night_script(roles: &[RoleInfo], role_info: &RoleInfo, users: &[Player]) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let alive_roles = roles
+        .iter()
+        .filter(|r| is_night_role_alive(r, users))
+        .collect::<Vec<_>>();
+
+    let position = alive_roles.iter().position(|r| *r == role_info);
+    let prev_role = position.and_then(|p| p.checked_sub(1)).and_then(|p| alive_roles.get(p));
+    let next_role = position.and_then(|p| alive_roles.get(p + 1));
+
+    if position == Some(0) || position.is_none() {
+        lines.push(NIGHT_START.to_string());
+    }
+
+    if !prev_role.is_some_and(|r| is_same_wake_up(r, role_info)) {
+        lines.push(format!("Просыпается {}.", role_info.get_role_name()));
+    }
+
+    lines.push(role_info.get_night_description().to_string());
+
+    if !next_role.is_some_and(|r| is_same_wake_up(r, role_info)) {
+        lines.push(format!("{} засыпает.", role_info.get_role_name()));
+    }
+
+    if next_role.is_none() {
+        lines.push(NIGHT_END.to_string());
+    }
+
+    lines
+}
+
+// Morning announcement built from the night results, only the White Hat roles are revealed
+pub fn morning_script(users: &[Player], round: usize, roles: &[RoleInfo]) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    if round == 0 {
+        lines.push("Наступает первый день. Игроки знакомятся друг с другом.".to_string());
+        return lines;
+    }
+
+    lines.push("Наступает утро.".to_string());
+
+    let killed = users
+        .iter()
+        .filter(|u| u.was_killed && !u.is_alive)
+        .map(|u| u.name.clone())
+        .collect::<Vec<_>>();
+
+    match killed.len() {
+        0 => lines.push("Этой ночью никто не погиб.".to_string()),
+        1 => lines.push(format!("Этой ночью был убит {}.", killed[0])),
+        _ => lines.push(format!("Этой ночью были убиты: {}.", killed.join(", "))),
+    }
+
+    users
+        .iter()
+        .filter(|u| u.was_killed && !u.is_alive && is_role_revealed(u))
+        .for_each(|u| {
+            let role_names = roles
+                .iter()
+                .filter(|r| u.role.contains(&r.get_role()))
+                .map(|r| r.get_role_name())
+                .collect::<Vec<_>>();
+
+            if !role_names.is_empty() {
+                lines.push(format!("Роль {}: {}.", u.name, role_names.join(", ")));
+            }
+        });
+
+    users
+        .iter()
+        .filter(|u| u.is_alive && !u.can_speak())
+        .for_each(|u| {
+            lines.push(format!("{} сегодня не может говорить.", u.name));
+        });
+
+    lines.push("Начинается обсуждение.".to_string());
+
+    lines
+}
+
+pub fn speak_lines(lines: &[String]) {
+    let Ok(synth) = window().speech_synthesis() else {
+        return;
+    };
+
+    synth.cancel();
+
+    for line in lines {
+        if let Ok(utterance) = web_sys::SpeechSynthesisUtterance::new_with_text(line) {
+            utterance.set_lang("ru-RU");
+            synth.speak(&utterance);
+        }
+    }
+}
+
+#[component]
+pub fn NarrationScript(lines: Signal<Vec<String>>) -> impl IntoView {
+    let is_open = create_rw_signal(false);
+
+    view! {
+        <div class="flex flex-col gap-1 text-xs">
+            <div class="flex gap-1">
+                <button
+                    class=move || format!("px-2 py-0.5 rounded-full {}", if is_open.get() { "bg-blue-300" } else { "bg-gray-200" })
+                    on:click=move |_| is_open.update(|is_open| *is_open = !*is_open)
+                >
+                    "📜 Сценарий"
+                </button>
+                <button
+                    class="px-2 py-0.5 rounded-full bg-gray-200"
+                    on:click=move |_| speak_lines(&lines.get())
+                >
+                    "🔊"
+                </button>
+            </div>
+            <Show when=move || is_open.get()>
+                <div class="flex flex-col gap-0.5 bg-gray-100 rounded-xl px-3 py-2">
+                    {move || lines.get().into_iter().map(|line| view! {
+                        <div>{line}</div>
+                    }).collect::<Vec<_>>().into_view()}
+                </div>
+            </Show>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mafia::*;
+
+    #[test]
+    fn test_night_script_first_and_last_role() {
+        let users = test_players(&[Role::Mafia(MafiaRole::Mafia), Role::Mafia(MafiaRole::Doctor), Role::None]);
+        let mafia = MAFIA_ROLES.iter().find(|r| r.get_role() == Role::Mafia(MafiaRole::Mafia)).unwrap();
+        let doctor = MAFIA_ROLES.iter().find(|r| r.get_role() == Role::Mafia(MafiaRole::Doctor)).unwrap();
+
+        let script = night_script(&MAFIA_ROLES, mafia, &users);
+        assert_eq!(script.first().unwrap(), NIGHT_START);
+        assert!(!script.contains(&NIGHT_END.to_string()));
+
+        let script = night_script(&MAFIA_ROLES, doctor, &users);
+        assert_eq!(script.first().unwrap(), "Просыпается Доктор.");
+        assert_eq!(script.last().unwrap(), NIGHT_END);
+    }
+
+    #[test]
+    fn test_morning_script_does_not_reveal_roles() {
+        let mut users = test_players(&[Role::Mafia(MafiaRole::Mafia), Role::Mafia(MafiaRole::Doctor), Role::None]);
+        users[0].is_alive = false;
+        users[0].was_killed = true;
+
+        let script = morning_script(&users, 2, &MAFIA_ROLES);
+
+        assert!(script.contains(&"Этой ночью был убит User1.".to_string()));
+        assert!(!script.iter().any(|line| line.contains("Мафия")));
+
+        users[0].attributes.insert(RoleAttribute::WhiteHat);
+        let script = morning_script(&users, 2, &MAFIA_ROLES);
+        assert!(script.iter().any(|line| line.starts_with("Роль User1:")));
+    }
+}
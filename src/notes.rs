@@ -0,0 +1,167 @@
+use leptos::*;
+use std::collections::HashSet;
+use web_sys::js_sys;
+
+use crate::components::*;
+use crate::replay::*;
+use crate::saves::*;
+use crate::user::*;
+use crate::GameContext;
+
+// Notes whose text or player name contains the query, the oldest first
+pub fn search_notes<'a>(users: &'a [Player], query: &str) -> Vec<(&'a Player, &'a PlayerNote)> {
+    let query = query.trim().to_lowercase();
+
+    let mut notes = users
+        .iter()
+        .flat_map(|user| user.notes.iter().map(move |note| (user, note)))
+        .filter(|(user, note)| {
+            note.text.to_lowercase().contains(&query) || user.name.to_lowercase().contains(&query)
+        })
+        .collect::<Vec<_>>();
+
+    notes.sort_by(|(_, a), (_, b)| a.created_at.total_cmp(&b.created_at));
+    notes
+}
+
+#[component]
+pub fn NoteRow(name: String, note: PlayerNote) -> impl IntoView {
+    view! {
+        <div class="flex gap-1 items-baseline text-sm">
+            <span class="text-xs opacity-50 w-14 shrink-0">{phase_title(note.round)}</span>
+            <span class="bg-gray-100 px-1 rounded-md">{name}</span>
+            <span class="flex-1">{note.text}</span>
+            <span class="text-xs opacity-50">{format_timestamp(note.created_at)}</span>
+        </div>
+    }
+}
+
+#[component]
+pub fn PlayerNotes(on_close: impl Fn() + Copy + 'static) -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+    let (selected_users, set_selected_users) = create_signal::<HashSet<String>>(HashSet::new());
+    let text = create_rw_signal("".to_string());
+    let query = create_rw_signal("".to_string());
+
+    let notes = move || {
+        game_ctx.users.with(|users| {
+            search_notes(users, &query.get())
+                .into_iter()
+                .rev()
+                .map(|(user, note)| (user.name.clone(), note.clone()))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let on_submit = move |ev: ev::SubmitEvent| {
+        ev.prevent_default();
+
+        let note_text = text.get().trim().to_string();
+        if note_text.is_empty() {
+            return;
+        }
+
+        let note = PlayerNote {
+            round: phase_round(game_ctx.game_state.get(), game_ctx.round.get()),
+            text: note_text,
+            created_at: js_sys::Date::now(),
+        };
+        let selected_users = selected_users.get();
+
+        game_ctx.users.update(|users| {
+            users
+                .iter_mut()
+                .filter(|u| selected_users.contains(&u.id))
+                .for_each(|u| u.notes.push(note.clone()));
+        });
+
+        text.set("".to_string());
+    };
+
+    view! {
+        <h2>"О ком заметка?"</h2>
+        <div class="flex flex-col gap-1 w-full">
+            <SelectUsersForVote
+                selected_users set_selected_users
+                is_killed=move |user: &Player| !user.is_alive
+                is_disabled=move |_| false
+                is_highlighted=move |_| false
+                is_single_select=true
+            />
+        </div>
+        <form class="flex gap-2 w-full" on:submit=on_submit>
+            <input
+                class="flex-1 px-3 text-sm py-1 border-gray-200 border rounded-full"
+                placeholder="Заметка"
+                on:input=move |ev| text.set(event_target_value(&ev))
+                prop:value=text
+            />
+            <button type="submit" class="px-4 py-1 text-sm bg-gray-200 rounded-full">
+                "Добавить"
+            </button>
+        </form>
+        <input
+            class="px-3 text-sm py-1 border-gray-200 border rounded-full"
+            placeholder="Поиск по заметкам"
+            on:input=move |ev| query.set(event_target_value(&ev))
+            prop:value=query
+        />
+        <div class="flex-1 flex flex-col gap-1 overflow-auto -mx-4 px-4">
+            {move || notes().into_iter().map(|(name, note)| view! {
+                <NoteRow name note />
+            }).collect::<Vec<_>>()}
+        </div>
+        <button
+            class="px-4 py-2 text-sm bg-gray-200 rounded-full"
+            on:click=move |_| on_close()
+        >
+            "Закрыть"
+        </button>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(round: usize, text: &str, created_at: f64) -> PlayerNote {
+        PlayerNote {
+            round,
+            text: text.to_string(),
+            created_at,
+        }
+    }
+
+    #[test]
+    fn test_search_notes() {
+        let mut users = vec![
+            Player::new_guest("001".to_string(), "Ivan".to_string()),
+            Player::new_guest("002".to_string(), "Olga".to_string()),
+        ];
+        users[0].notes.push(note(3, "claimed Seer", 2.0));
+        users[1].notes.push(note(1, "got a warning", 1.0));
+
+        let found = search_notes(&users, "SEER");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.name, "Ivan");
+
+        assert_eq!(search_notes(&users, "olga")[0].1.text, "got a warning");
+
+        let all = search_notes(&users, "");
+        assert_eq!(all.iter().map(|(u, _)| u.name.as_str()).collect::<Vec<_>>(), ["Olga", "Ivan"]);
+    }
+
+    #[test]
+    fn test_undo_keeps_notes() {
+        let mut previous = vec![Player::new_guest("001".to_string(), "Ivan".to_string())];
+        let mut current = previous.clone();
+        current[0].notes.push(note(2, "claimed Seer", 1.0));
+        current[0].is_alive = false;
+
+        carry_notes(&current, &mut previous);
+
+        assert!(previous[0].is_alive);
+        assert_eq!(previous[0].notes, current[0].notes);
+    }
+}
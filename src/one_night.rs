@@ -0,0 +1,958 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::components::*;
+use crate::events::*;
+use crate::invariants::*;
+use crate::narration::*;
+use crate::roles::*;
+use crate::ruleset::*;
+use crate::user::*;
+use crate::validation::*;
+use crate::GameContext;
+use crate::GameContextHistory;
+use crate::GameState;
+use leptos::*;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum OneNightRole {
+    Werewolf,
+    Seer,
+    Robber,
+    Troublemaker,
+    Drunk,
+    Insomniac,
+    Villager,
+}
+
+pub const CENTER_CARDS: usize = 3;
+
+// The only night of the game is played on round 2, like the first night of the other games
+const NIGHT_ROUND: usize = 2;
+
+pub const ONE_NIGHT_ROLES: [RoleInfo; 8] = [
+    RoleInfo::Icon(IconRoleInfo {
+        role: Role::WasKilled,
+        role_name: "Killed",
+        role_name_color: "red-950",
+        role_icon: "❌",
+    }),
+    RoleInfo::Night(NightRoleInfo {
+        role: Role::OneNight(OneNightRole::Werewolf),
+        check_role: None,
+        role_name: "Werewolf",
+        role_name_color: "red-950",
+        role_icon: "🐺",
+        prepare_description: "Выберите игроков с картой Werewolf",
+        night_description: "Оборотни смотрят друг на друга. Одинокий оборотень может посмотреть карту из центра.",
+        targeting_rules: NightTargetingRules::No,
+    }),
+    RoleInfo::Night(NightRoleInfo {
+        role: Role::OneNight(OneNightRole::Seer),
+        check_role: None,
+        role_name: "Seer",
+        role_name_color: "purple-950",
+        role_icon: "🔮",
+        prepare_description: "Выберите игрока с картой Seer",
+        night_description: "Seer смотрит карту одного игрока или две карты из центра.",
+        targeting_rules: NightTargetingRules::Anyone,
+    }),
+    RoleInfo::Night(NightRoleInfo {
+        role: Role::OneNight(OneNightRole::Robber),
+        check_role: None,
+        role_name: "Robber",
+        role_name_color: "green-950",
+        role_icon: "🦹",
+        prepare_description: "Выберите игрока с картой Robber",
+        night_description: "С кем Robber поменяется картой? Robber смотрит свою новую карту.",
+        targeting_rules: NightTargetingRules::Anyone,
+    }),
+    RoleInfo::Night(NightRoleInfo {
+        role: Role::OneNight(OneNightRole::Troublemaker),
+        check_role: None,
+        role_name: "Troublemaker",
+        role_name_color: "green-950",
+        role_icon: "🔀",
+        prepare_description: "Выберите игрока с картой Troublemaker",
+        night_description: "Карты каких двух игроков поменяет Troublemaker?",
+        targeting_rules: NightTargetingRules::Anyone,
+    }),
+    RoleInfo::Night(NightRoleInfo {
+        role: Role::OneNight(OneNightRole::Drunk),
+        check_role: None,
+        role_name: "Drunk",
+        role_name_color: "gray-950",
+        role_icon: "🍺",
+        prepare_description: "Выберите игрока с картой Drunk",
+        night_description: "Какую карту из центра заберет Drunk? Свою новую карту он не смотрит.",
+        targeting_rules: NightTargetingRules::No,
+    }),
+    RoleInfo::Night(NightRoleInfo {
+        role: Role::OneNight(OneNightRole::Insomniac),
+        check_role: None,
+        role_name: "Insomniac",
+        role_name_color: "purple-950",
+        role_icon: "👁️",
+        prepare_description: "Выберите игрока с картой Insomniac",
+        night_description: "Insomniac смотрит на свою карту.",
+        targeting_rules: NightTargetingRules::No,
+    }),
+    RoleInfo::Passive(PassiveRoleInfo {
+        role: Role::OneNight(OneNightRole::Villager),
+        role_name: "Villager",
+        role_icon: "✋",
+        additional_role: None,
+        role_name_color: "blue-950",
+        prepare_description: "Выберите игроков с картой Villager",
+    }),
+];
+
+const ONE_NIGHT_WINNER_ROLES: [RoleInfo; 2] = [
+    RoleInfo::Icon(IconRoleInfo {
+        role: Role::OneNight(OneNightRole::Werewolf),
+        role_name: "Оборотни",
+        role_name_color: "red-950",
+        role_icon: "🐺",
+    }),
+    RoleInfo::Icon(IconRoleInfo {
+        role: Role::OneNight(OneNightRole::Villager),
+        role_name: "Жители деревни",
+        role_name_color: "green-950",
+        role_icon: "✋",
+    }),
+];
+
+// Cards which are not in Player::role: the center of the table and the cards the players were dealt.
+// Player::role is the card lying in front of the player right now, the night swaps change it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OneNightCards {
+    pub center: Vec<Role>,
+    pub dealt: HashMap<String, Role>,
+}
+
+impl OneNightCards {
+    pub fn center_card(&self, slot: usize) -> Role {
+        self.center.get(slot).copied().unwrap_or(Role::None)
+    }
+
+    pub fn set_center_card(&mut self, slot: usize, role: Role) {
+        self.center.resize(CENTER_CARDS, Role::None);
+        self.center[slot] = role;
+    }
+}
+
+fn player_card(user: &Player) -> Role {
+    user.role.iter().next().copied().unwrap_or(Role::None)
+}
+
+fn set_player_card(user: &mut Player, role: Role) {
+    user.role.clear();
+
+    if role != Role::None {
+        user.role.insert(role);
+    }
+}
+
+fn card_name(role: Role) -> String {
+    ONE_NIGHT_ROLES
+        .iter()
+        .find(|r| r.get_role() == role)
+        .map_or("Не выбрано".to_string(), |r| {
+            format!("{} {}", r.get_role_icon(), r.get_role_name())
+        })
+}
+
+// Remember who got which card, the night is played by these cards
+pub fn deal_cards(users: &[Player], cards: &mut OneNightCards) {
+    cards.dealt = users
+        .iter()
+        .filter(|u| !u.role.is_empty())
+        .map(|u| (u.id.clone(), player_card(u)))
+        .collect();
+}
+
+// Players with the cards they were dealt, a robbed Insomniac still wakes up as Insomniac
+pub fn dealt_users(users: &[Player], cards: &OneNightCards) -> Vec<Player> {
+    users
+        .iter()
+        .cloned()
+        .map(|mut user| {
+            if let Some(role) = cards.dealt.get(&user.id) {
+                set_player_card(&mut user, *role);
+            }
+            user
+        })
+        .collect()
+}
+
+fn swap_player_cards(users: &mut [Player], first: &str, second: &str) {
+    let first = users.iter().position(|u| u.id == first);
+    let second = users.iter().position(|u| u.id == second);
+
+    if let (Some(first), Some(second)) = (first, second) {
+        let first_card = player_card(&users[first]);
+        let second_card = player_card(&users[second]);
+
+        set_player_card(&mut users[first], second_card);
+        set_player_card(&mut users[second], first_card);
+    }
+}
+
+fn player_targets(role: Role) -> usize {
+    match role {
+        Role::OneNight(OneNightRole::Seer) | Role::OneNight(OneNightRole::Robber) => 1,
+        Role::OneNight(OneNightRole::Troublemaker) => 2,
+        _ => 0,
+    }
+}
+
+fn center_targets(role: Role, dealt_users: &[Player]) -> usize {
+    match role {
+        Role::OneNight(OneNightRole::Werewolf) => {
+            let werewolves = dealt_users.iter().filter(|u| u.role.contains(&role)).count();
+
+            (werewolves == 1) as usize
+        }
+        Role::OneNight(OneNightRole::Seer) => 2,
+        Role::OneNight(OneNightRole::Drunk) => 1,
+        _ => 0,
+    }
+}
+
+// Night step of role, an incomplete choice leaves the cards as they are
+pub fn apply_night_action(
+    role: Role,
+    users: &mut [Player],
+    events: &mut Vec<GameEvent>,
+    cards: &mut OneNightCards,
+    targets: &[String],
+    center: &[usize],
+) {
+    let Some(actor) = users
+        .iter()
+        .find(|u| cards.dealt.get(&u.id) == Some(&role))
+        .map(|u| u.id.clone())
+    else {
+        return;
+    };
+
+    let alignments = users.iter().map(Player::alignment).collect::<Vec<_>>();
+
+    match (role, targets, center) {
+        (Role::OneNight(OneNightRole::Robber), [target], _) => {
+            swap_player_cards(users, &actor, target);
+        }
+        (Role::OneNight(OneNightRole::Troublemaker), [first, second], _) => {
+            swap_player_cards(users, first, second);
+        }
+        (Role::OneNight(OneNightRole::Drunk), _, [slot]) => {
+            if let Some(user) = users.iter_mut().find(|u| u.id == actor) {
+                let card = cards.center_card(*slot);
+                cards.set_center_card(*slot, player_card(user));
+                set_player_card(user, card);
+            }
+        }
+        _ => {}
+    }
+
+    let targets = targets.iter().cloned().collect::<HashSet<_>>();
+    record_choice(users, events, NIGHT_ROUND, role, &[actor], &targets);
+
+    // a swapped card moves the player to the team of the card
+    for (user, from) in users.iter_mut().zip(alignments) {
+        let changes = user.alignment_changes.len();
+        user.record_alignment_change(from, NIGHT_ROUND, role);
+
+        events.extend(
+            user.alignment_changes[changes..]
+                .iter()
+                .map(|change| conversion_event(user, change)),
+        );
+    }
+}
+
+pub fn one_night_hints(role: Role, dealt_users: &[Player]) -> Vec<HintLine> {
+    let players = dealt_users
+        .iter()
+        .filter(|u| u.role.contains(&role))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    match (role, players.as_slice()) {
+        (Role::OneNight(OneNightRole::Werewolf), [werewolf]) => vec![HintLine::Player(
+            "🐺",
+            werewolf.clone(),
+            "одинокий оборотень, может посмотреть карту из центра.",
+        )],
+        (Role::OneNight(OneNightRole::Werewolf), _) => {
+            vec![HintLine::Players("🐺", players, "оборотни.")]
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Everybody votes at once, the players with the most votes are killed
+pub fn apply_one_night_vote(
+    users: &mut [Player],
+    events: &mut Vec<GameEvent>,
+    round: usize,
+    selected_users: &HashSet<String>,
+) {
+    let villager = Role::OneNight(OneNightRole::Villager);
+
+    for user in users.iter_mut().filter(|u| selected_users.contains(&u.id)) {
+        user.is_alive = false;
+        user.was_killed = true;
+
+        events.push(GameEvent::Lynched {
+            phase: round + 1,
+            target: user.id.clone(),
+            by: villager,
+        });
+        events.push(GameEvent::Killed {
+            phase: round + 1,
+            target: user.id.clone(),
+            cause: Some(villager),
+        });
+    }
+
+    debug_check_invariants(users, events, "one night vote");
+}
+
+pub fn calculate_one_night_winner(users: &[Player]) -> Option<Role> {
+    let werewolf = Role::OneNight(OneNightRole::Werewolf);
+    let villager = Role::OneNight(OneNightRole::Villager);
+
+    let killed = users.iter().filter(|u| !u.is_alive).collect::<Vec<_>>();
+
+    if killed.iter().any(|u| u.role.contains(&werewolf)) {
+        return Some(villager);
+    }
+
+    if users.iter().any(|u| u.role.contains(&werewolf)) {
+        return Some(werewolf);
+    }
+
+    // without werewolves the village wins only if nobody was killed
+    killed.is_empty().then_some(villager)
+}
+
+fn next_setup_role(role: Option<&RoleInfo>) -> Option<&'static RoleInfo> {
+    let start = role
+        .and_then(|role| ONE_NIGHT_ROLES.iter().position(|r| r == role))
+        .map_or(0, |index| index + 1);
+
+    ONE_NIGHT_ROLES
+        .iter()
+        .skip(start)
+        .find(|r| !r.get_prepare_description().is_empty())
+}
+
+// Every player needs a card and the center needs all of its cards before the night
+pub fn validate_one_night_setup(users: &[Player], cards: &OneNightCards) -> Vec<SetupIssue> {
+    let mut issues = vec![];
+    let first_phase = next_setup_role(None).map(GamePhase::SetupRoles);
+
+    for user in users.iter().filter(|u| u.role.is_empty()) {
+        issues.push(SetupIssue::error(
+            format!("У игрока {} нет карты", user.name),
+            "Выдайте игроку карту",
+            first_phase,
+        ));
+    }
+
+    if (0..CENTER_CARDS).any(|slot| cards.center_card(slot) == Role::None) {
+        issues.push(SetupIssue::error(
+            "В центре не хватает карт".to_string(),
+            "Положите в центр три карты",
+            first_phase,
+        ));
+    }
+
+    // only Werewolf and Villager cards come in several copies
+    for role_info in ONE_NIGHT_ROLES.iter().filter(|r| matches!(r, RoleInfo::Night(_))) {
+        let role = role_info.get_role();
+        if role == Role::OneNight(OneNightRole::Werewolf) {
+            continue;
+        }
+
+        if count_role(users, role) > 1 {
+            issues.push(SetupIssue::error(
+                format!("Карта {} выдана нескольким игрокам", role_info.get_role_name()),
+                "Оставьте карту одному игроку",
+                Some(GamePhase::SetupRoles(role_info)),
+            ));
+        }
+    }
+
+    let werewolf = Role::OneNight(OneNightRole::Werewolf);
+    if count_role(users, werewolf) == 0 && !cards.center.contains(&werewolf) {
+        issues.push(SetupIssue::warning(
+            "В колоде нет Werewolf".to_string(),
+            "Добавьте карту Werewolf",
+            setup_phase(&ONE_NIGHT_ROLES, werewolf),
+        ));
+    }
+
+    issues
+}
+
+pub struct OneNightRuleset;
+
+impl Ruleset for OneNightRuleset {
+    fn name(&self) -> &'static str {
+        "OneNight"
+    }
+
+    fn title(&self) -> &'static str {
+        "One Night"
+    }
+
+    fn start_label(&self) -> &'static str {
+        "Начать в One Night"
+    }
+
+    fn roles(&self) -> &'static [RoleInfo] {
+        &ONE_NIGHT_ROLES
+    }
+
+    fn start_phase(&self) -> GamePhase<'static> {
+        GamePhase::SetupRoles(next_setup_role(None).expect("One Night has setup roles"))
+    }
+
+    fn phase_view(&'static self, phase: GamePhase<'static>) -> View {
+        match phase {
+            GamePhase::SetupRoles(role) => view! {
+                <SetupCards role />
+            }
+            .into_view(),
+            GamePhase::Night(role) => view! {
+                <NightTurn role_info={role} />
+            }
+            .into_view(),
+            GamePhase::Day => view! {
+                <VoteTurn />
+            }
+            .into_view(),
+            GamePhase::End => view! {
+                <FinalReveal />
+            }
+            .into_view(),
+            GamePhase::SelectActiveRoles | GamePhase::FirstNight(_) => view! {
+                <div>"Ошибка"</div>
+            }
+            .into_view(),
+        }
+    }
+
+    fn role_color(&self, user: &Player) -> &'static str {
+        match player_card(user) {
+            Role::OneNight(OneNightRole::Werewolf) => "bg-red-100",
+            Role::OneNight(OneNightRole::Seer) | Role::OneNight(OneNightRole::Insomniac) => {
+                "bg-cyan-100"
+            }
+            Role::OneNight(OneNightRole::Robber)
+            | Role::OneNight(OneNightRole::Troublemaker)
+            | Role::OneNight(OneNightRole::Drunk) => "bg-emerald-100",
+            _ => "bg-gray-100",
+        }
+    }
+
+    fn alive_counters(&self) -> &'static [(&'static str, Role)] {
+        &[("оборотней", Role::OneNight(OneNightRole::Werewolf))]
+    }
+
+    fn killer_roles(&self) -> &'static [Role] {
+        &[]
+    }
+
+    fn apply_day_vote(
+        &self,
+        users: &mut [Player],
+        events: &mut Vec<GameEvent>,
+        round: usize,
+        selected_users: &HashSet<String>,
+    ) {
+        apply_one_night_vote(users, events, round, selected_users);
+    }
+
+    // nobody dies before the vote in One Night
+    fn kill_at_day_start(
+        &self,
+        _users: &mut [Player],
+        _events: &mut Vec<GameEvent>,
+        _round: usize,
+        _selected_users: &HashSet<String>,
+    ) {
+    }
+
+    fn day_hints(&self, _users: &[Player], _events: &[GameEvent]) -> Vec<HintLine> {
+        Vec::new()
+    }
+
+    fn winner_roles(&self) -> &'static [RoleInfo] {
+        &ONE_NIGHT_WINNER_ROLES
+    }
+
+    // the team is decided by the card the player holds at the end
+    fn role_index(&self, user: &Player) -> &'static str {
+        match user.alignment() {
+            Alignment::Werewolf => "Werewolf",
+            _ => "Villager",
+        }
+    }
+
+    fn is_mafia_log(&self) -> bool {
+        false
+    }
+}
+
+fn set_phase(game_ctx: &GameContext, phase: GamePhase<'static>) {
+    game_ctx
+        .game_state
+        .set(GameState::Game(&OneNightRuleset, phase));
+}
+
+#[component]
+fn CenterCards(
+    is_selected: impl Fn(usize) -> bool + Copy + 'static,
+    on_select: impl Fn(usize) + Copy + 'static,
+) -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+    view! {
+        <div class="text-sm">"Карты в центре"</div>
+        <div class="grid grid-cols-3 gap-1">
+            {(0..CENTER_CARDS).map(|slot| view! {
+                <button
+                    class=move || format!("rounded-2xl px-3 py-1 text-sm min-h-8 {}", if is_selected(slot) {
+                        "bg-blue-300"
+                    } else {
+                        "bg-gray-100"
+                    })
+                    on:click=move |_| on_select(slot)
+                >
+                    {move || card_name(game_ctx.one_night.get().center_card(slot))}
+                </button>
+            }).collect::<Vec<_>>()}
+        </div>
+    }
+}
+
+#[component]
+fn SetupCards(role: &'static RoleInfo) -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+    let card = role.get_role();
+
+    let setup_roles = || ONE_NIGHT_ROLES.iter().filter(|r| !r.get_prepare_description().is_empty());
+    let index = setup_roles().position(|r| r == role).unwrap_or(0) + 1;
+    let len = setup_roles().count();
+
+    let ids_sorted = move || users_sorted(game_ctx.users.ids());
+
+    let setup_issues = create_rw_signal::<Vec<SetupIssue>>(vec![]);
+
+    let push_history = move || {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
+
+        set_context_history.update(|history| history.push(game_ctx.get_history()));
+    };
+
+    let start_game = move || {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+        push_history();
+
+        let users = game_ctx.users.get();
+        let mut cards = game_ctx.one_night.get();
+        deal_cards(&users, &mut cards);
+
+        let next_role = OneNightRuleset.next_night_role(None, &dealt_users(&users, &cards));
+
+        batch(|| {
+            game_ctx.one_night.set(cards);
+            game_ctx.round.set(NIGHT_ROUND);
+            set_phase(&game_ctx, next_role.map_or(GamePhase::Day, GamePhase::Night));
+        });
+    };
+
+    let onclick_next_role = move |_| {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+        match next_setup_role(Some(role)) {
+            Some(role_info) => {
+                push_history();
+                set_phase(&game_ctx, GamePhase::SetupRoles(role_info));
+            }
+            None => {
+                let issues =
+                    validate_one_night_setup(&game_ctx.users.get(), &game_ctx.one_night.get());
+
+                if issues.is_empty() {
+                    start_game();
+                } else {
+                    setup_issues.set(issues);
+                }
+            }
+        }
+    };
+
+    let onclick_prev_role = move |_| {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
+
+        set_context_history.update(|history| {
+            if let Some(prev_ctx) = history.pop() {
+                game_ctx.undo_to(prev_ctx);
+            }
+        });
+    };
+
+    view! {
+        <h2 class="text-base">
+            <span class="bg-black/5 mr-2 rounded-md px-1.5 text-xs py-0.5">{index} <span class="text-black/30">" / " {len}</span></span>{role.get_prepare_description()}
+        </h2>
+        <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+            <div class="flex-1"></div>
+            <div class="grid grid-cols-2 gap-y-1 gap-x-3">
+                <For
+                    each=ids_sorted
+                    key=|id| id.clone()
+                    children=move |id| {
+                        let Some(user) = game_ctx.users.player(&id) else {
+                            return ().into_view();
+                        };
+
+                        view!{
+                            <UserSelectRole
+                                user
+                                disabled=false
+                                highlighted=false
+                                killed=false
+                                is_selected=move |u| u.role.contains(&card)
+                                on:click=move |_| {
+                                    game_ctx.users.update_player(&id, |user| {
+                                        let role = if user.role.contains(&card) { Role::None } else { card };
+                                        set_player_card(user, role);
+                                    });
+                                }
+                            />
+                        }.into_view()
+                    }
+                />
+            </div>
+        </div>
+        <CenterCards
+            is_selected=move |slot| game_ctx.one_night.get().center_card(slot) == card
+            on_select=move |slot| {
+                game_ctx.one_night.update(|cards| {
+                    let role = if cards.center_card(slot) == card { Role::None } else { card };
+                    cards.set_center_card(slot, role);
+                });
+            }
+        />
+        <div class="flex gap-2 w-full items-center">
+            <button
+                class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full flex items-center justify-center"
+                on:click=onclick_prev_role
+            >
+                "←"
+            </button>
+            <button
+                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                on:click=onclick_next_role
+            >
+                "Далее"
+            </button>
+        </div>
+        {move || (!setup_issues.get().is_empty()).then(|| view! {
+            <SetupCheck
+                issues=setup_issues.get()
+                on_continue=move || {
+                    setup_issues.set(vec![]);
+                    start_game();
+                }
+                on_close=move || setup_issues.set(vec![])
+            />
+        })}
+    }
+}
+
+#[component]
+fn NightTurn(role_info: &'static RoleInfo) -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+    let role = role_info.get_role();
+
+    let (selected_users, set_selected_users) = create_signal::<HashSet<String>>(HashSet::new());
+    let selected_center = create_rw_signal(Vec::<usize>::new());
+
+    let dealt = move || game_ctx.users.with(|users| game_ctx.one_night.with(|cards| dealt_users(users, cards)));
+    let player_targets = player_targets(role);
+    let center_targets = center_targets(role, &untrack(dealt));
+
+    let is_actor = move |user: &Player| {
+        game_ctx
+            .one_night
+            .with_untracked(|cards| cards.dealt.get(&user.id) == Some(&role))
+    };
+
+    let onclick_next_role = move || {
+        let targets = selected_users.get().into_iter().collect::<Vec<_>>();
+        let mut users = game_ctx.users.get();
+        let mut events = game_ctx.events.get();
+        let mut cards = game_ctx.one_night.get();
+
+        apply_night_action(role, &mut users, &mut events, &mut cards, &targets, &selected_center.get());
+
+        let next_role = OneNightRuleset.next_night_role(Some(role_info), &dealt_users(&users, &cards));
+
+        batch(|| {
+            game_ctx.users.set(users);
+            game_ctx.events.set(events);
+            game_ctx.one_night.set(cards);
+            set_phase(&game_ctx, next_role.map_or(GamePhase::Day, GamePhase::Night));
+        });
+    };
+
+    let narration = Signal::derive(move || night_script(&ONE_NIGHT_ROLES, role_info, &dealt()));
+    let game_log = create_memo(move |_| one_night_hints(role, &dealt()));
+
+    view! {
+        <h2>
+            {role_info.get_night_description()}
+        </h2>
+        <NarrationScript lines=narration />
+        <DisplayLogs logs=game_log />
+        <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+            <div class="flex-1"></div>
+            <div class="flex flex-col gap-1 w-full">
+            <SelectUsersForVote
+                is_killed=move |_| false
+                is_disabled=move |user| player_targets == 0 || is_actor(user)
+                is_highlighted=is_actor
+                selected_users set_selected_users
+                is_single_select={player_targets == 1}
+                highlight_color="ring-blue-600/80".to_string()
+            />
+            </div>
+        </div>
+        <CenterCards
+            is_selected=move |slot| selected_center.get().contains(&slot)
+            on_select=move |slot| {
+                selected_center.update(|selected| {
+                    if let Some(index) = selected.iter().position(|s| *s == slot) {
+                        selected.remove(index);
+                    } else if center_targets > 0 {
+                        if selected.len() == center_targets {
+                            selected.remove(0);
+                        }
+                        selected.push(slot);
+                    }
+                });
+            }
+        />
+        <NextTurnButtons onclick_next_role />
+    }
+}
+
+#[component]
+fn VoteTurn() -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+    let (selected_users, set_selected_users) = create_signal::<HashSet<String>>(HashSet::new());
+
+    let onclick_next_role = move || {
+        let selected_users = selected_users.get();
+        let round = game_ctx.round.get();
+
+        game_ctx.update_game(|users, events| {
+            OneNightRuleset.apply_day_vote(users, events, round, &selected_users)
+        });
+        set_phase(&game_ctx, GamePhase::End);
+    };
+
+    view! {
+        <h2>"Кого игроки выбрали общим голосованием?"</h2>
+        <div class="text-sm opacity-50">
+            "Выберите игроков с наибольшим числом голосов. Если каждый получил по одному голосу, никого не выбирайте."
+        </div>
+        <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+            <div class="flex-1"></div>
+            <div class="flex flex-col gap-1 w-full pb-0.5">
+                <SelectUsersForVote selected_users set_selected_users
+                    is_killed=move |_| false
+                    is_disabled=move |_| false
+                    is_highlighted=move |_| false
+                    highlight_color="ring-blue-600/80".to_string()
+                />
+            </div>
+        </div>
+        <Timer />
+        <NextTurnButtons onclick_next_role />
+    }
+}
+
+#[component]
+fn FinalReveal() -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+    let open_dialogue =
+        use_context::<RwSignal<OpenFinishGameDialogue>>().expect("MafiaContext not found");
+
+    let users = game_ctx.users.get_untracked();
+    let cards = game_ctx.one_night.get_untracked();
+
+    let winner = calculate_one_night_winner(&users)
+        .and_then(|winner| ONE_NIGHT_WINNER_ROLES.iter().find(|r| r.get_role() == winner))
+        .map_or("Никто не победил".to_string(), |r| {
+            format!("Победили: {}", r.get_role_name())
+        });
+
+    let center = (0..CENTER_CARDS)
+        .map(|slot| card_name(cards.center_card(slot)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let onclick_prev_role = move |_| {
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
+
+        set_context_history.update(|history| {
+            if let Some(prev_ctx) = history.pop() {
+                game_ctx.undo_to(prev_ctx);
+            }
+        });
+    };
+
+    view! {
+        <h2>"Вскрытие карт"</h2>
+        <div class="text-sm">{winner}</div>
+        <div class="flex-1 flex flex-col gap-1 relative overflow-auto px-4 -mx-4">
+            {users.iter().map(|user| {
+                let dealt = cards.dealt.get(&user.id).copied().unwrap_or(Role::None);
+
+                view! {
+                    <div class=format!("flex gap-2 items-center px-3 py-1 text-sm rounded-2xl {}", if user.is_alive {
+                        OneNightRuleset.role_color(user)
+                    } else {
+                        "opacity-50 bg-gray-100"
+                    })>
+                        <div class="flex-1">{user.name.clone()} {(!user.is_alive).then_some(" ❌")}</div>
+                        <div class="text-xs opacity-50">{card_name(dealt)}</div>
+                        "→"
+                        <div class="text-xs">{card_name(player_card(user))}</div>
+                    </div>
+                }
+            }).collect::<Vec<_>>()}
+            <div class="text-sm">"В центре: "{center}</div>
+        </div>
+        <div class="flex gap-2 w-full items-center">
+            <button
+                class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full flex items-center justify-center"
+                on:click=onclick_prev_role
+            >
+                "←"
+            </button>
+            <button
+                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                on:click=move |_| open_dialogue.set(OpenFinishGameDialogue(true))
+            >
+                "Сохранить результаты"
+            </button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(users: &[Player], index: usize) -> Role {
+        player_card(&users[index])
+    }
+
+    #[test]
+    fn test_night_actions_swap_cards() {
+        let mut users = test_players(&[
+            Role::OneNight(OneNightRole::Robber),
+            Role::OneNight(OneNightRole::Troublemaker),
+            Role::OneNight(OneNightRole::Drunk),
+            Role::OneNight(OneNightRole::Werewolf),
+        ]);
+        let mut cards = OneNightCards::default();
+        cards.set_center_card(0, Role::OneNight(OneNightRole::Villager));
+        deal_cards(&users, &mut cards);
+
+        let mut events = vec![];
+        let robber = Role::OneNight(OneNightRole::Robber);
+        apply_night_action(robber, &mut users, &mut events, &mut cards, &["004".to_string()], &[]);
+        assert_eq!(card(&users, 0), Role::OneNight(OneNightRole::Werewolf));
+        assert_eq!(card(&users, 3), robber);
+        assert_eq!(phase_choices(&events, NIGHT_ROUND, "004"), HashSet::from([robber]));
+        assert!(events.contains(&GameEvent::Targeted {
+            phase: NIGHT_ROUND,
+            actor_role: robber,
+            actors: vec!["001".to_string()],
+            target: "004".to_string(),
+        }));
+
+        let troublemaker = Role::OneNight(OneNightRole::Troublemaker);
+        let targets = ["001".to_string(), "003".to_string()];
+        apply_night_action(troublemaker, &mut users, &mut events, &mut cards, &targets, &[]);
+        assert_eq!(card(&users, 0), Role::OneNight(OneNightRole::Drunk));
+        assert_eq!(card(&users, 2), Role::OneNight(OneNightRole::Werewolf));
+
+        // the Drunk gives away the card lying in front now, not the dealt one
+        let drunk = Role::OneNight(OneNightRole::Drunk);
+        apply_night_action(drunk, &mut users, &mut events, &mut cards, &[], &[0]);
+        assert_eq!(card(&users, 2), Role::OneNight(OneNightRole::Villager));
+        assert_eq!(cards.center_card(0), Role::OneNight(OneNightRole::Werewolf));
+
+        // a single Troublemaker target is not a swap
+        apply_night_action(troublemaker, &mut users, &mut events, &mut cards, &["001".to_string()], &[]);
+        assert_eq!(card(&users, 0), Role::OneNight(OneNightRole::Drunk));
+    }
+
+    #[test]
+    fn test_night_order_goes_by_dealt_cards() {
+        let mut users = test_players(&[Role::OneNight(OneNightRole::Robber), Role::OneNight(OneNightRole::Insomniac)]);
+        let mut cards = OneNightCards::default();
+        deal_cards(&users, &mut cards);
+
+        let robber = ONE_NIGHT_ROLES
+            .iter()
+            .find(|r| r.get_role() == Role::OneNight(OneNightRole::Robber))
+            .unwrap();
+        apply_night_action(robber.get_role(), &mut users, &mut vec![], &mut cards, &["002".to_string()], &[]);
+
+        let next_role = OneNightRuleset.next_night_role(Some(robber), &dealt_users(&users, &cards));
+        assert_eq!(next_role.unwrap().get_role(), Role::OneNight(OneNightRole::Insomniac));
+        assert_eq!(card(&users, 0), Role::OneNight(OneNightRole::Insomniac));
+    }
+
+    #[test]
+    fn test_calculate_one_night_winner() {
+        let werewolf = Role::OneNight(OneNightRole::Werewolf);
+        let villager = Role::OneNight(OneNightRole::Villager);
+        let mut users = test_players(&[Role::OneNight(OneNightRole::Werewolf), Role::OneNight(OneNightRole::Seer)]);
+
+        assert_eq!(calculate_one_night_winner(&users), Some(werewolf));
+
+        let mut events = vec![];
+        apply_one_night_vote(&mut users, &mut events, NIGHT_ROUND, &HashSet::from(["001".to_string()]));
+        assert_eq!(calculate_one_night_winner(&users), Some(villager));
+        assert_eq!(
+            marked_roles(&events, "001"),
+            vec![(NIGHT_ROUND + 1, HashSet::from([Role::OneNight(OneNightRole::Villager), Role::WasKilled]))]
+        );
+
+        let mut users = test_players(&[Role::OneNight(OneNightRole::Villager), Role::OneNight(OneNightRole::Seer)]);
+        assert_eq!(calculate_one_night_winner(&users), Some(villager));
+
+        apply_one_night_vote(&mut users, &mut vec![], NIGHT_ROUND, &HashSet::from(["002".to_string()]));
+        assert_eq!(calculate_one_night_winner(&users), None);
+        assert!(!OneNightRuleset.is_winner(&users[1], &HashSet::from([werewolf])));
+    }
+}
@@ -0,0 +1,209 @@
+use leptos::*;
+use std::collections::HashSet;
+
+use crate::components::*;
+use crate::events::*;
+use crate::mafia::*;
+use crate::replay::*;
+use crate::roles::*;
+use crate::ruleset::*;
+use crate::user::*;
+use crate::GameContext;
+use crate::MafiaRole;
+use crate::OneNightRole;
+use crate::WerewolfRole;
+
+// Round columns of an empty sheet, the paper game may be longer than the one played in the app
+const MIN_SHEET_ROUNDS: usize = 10;
+
+const VILLAGER_ROLES: [Role; 3] = [
+    Role::Mafia(MafiaRole::Citizen),
+    Role::Werewolf(WerewolfRole::Villager),
+    Role::OneNight(OneNightRole::Villager),
+];
+
+const SHEET_STYLE: &str = "body{font-family:sans-serif;margin:16px}\
+.cards{display:flex;flex-wrap:wrap;gap:8px}\
+.card{width:6cm;height:9cm;border:1px solid #999;border-radius:12px;padding:8px;box-sizing:border-box;\
+display:flex;flex-direction:column;align-items:center;text-align:center;gap:4px;break-inside:avoid}\
+.card img{max-width:100%;max-height:5cm}.icon{font-size:48px}.name{font-size:20px;font-weight:bold}\
+.description{font-size:12px;color:#555}\
+table{border-collapse:collapse;width:100%;margin-top:16px;break-before:page}\
+td,th{border:1px solid #999;padding:4px;font-size:12px;min-width:1.5cm;height:0.8cm}";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn role_card_html(role_info: &RoleInfo, base_url: &str) -> String {
+    let picture = role_picture(role_info.get_role()).map_or_else(
+        || format!("<div class=\"icon\">{}</div>", role_info.get_role_icon()),
+        |picture| format!("<img src=\"{}{}\">", base_url, picture),
+    );
+
+    format!(
+        "<div class=\"card\">{}<div class=\"name\">{} {}</div><div class=\"description\">{}</div></div>",
+        picture,
+        role_info.get_role_icon(),
+        escape_html(role_info.get_role_name()),
+        escape_html(role_info.get_prepare_description()),
+    )
+}
+
+// One card for every dealt role, the players without a role get a villager card
+fn setup_cards(ruleset: &dyn Ruleset, users: &[Player], center: &[Role]) -> Vec<&'static RoleInfo> {
+    let roles = ruleset.roles();
+    let find_role = |role: &Role| roles.iter().find(|r| r.get_role() == *role);
+    let villager = roles.iter().find(|r| VILLAGER_ROLES.contains(&r.get_role()));
+
+    let mut cards = users
+        .iter()
+        .flat_map(|user| {
+            let user_cards = user
+                .role
+                .iter()
+                .chain(user.additional_role.iter())
+                .filter_map(find_role)
+                .collect::<Vec<_>>();
+
+            if user_cards.is_empty() {
+                villager.into_iter().collect()
+            } else {
+                user_cards
+            }
+        })
+        .chain(center.iter().filter_map(find_role))
+        .collect::<Vec<_>>();
+
+    cards.sort_by_key(|card| roles.iter().position(|r| r == *card));
+    cards
+}
+
+fn score_sheet_html(ruleset: &dyn Ruleset, users: &[Player], events: &[GameEvent]) -> String {
+    let logs = calculate_user_logs(ruleset, users, events, &HashSet::new(), &HashSet::new());
+    let rounds = logs
+        .iter()
+        .map(|log| log.rounds.len())
+        .max()
+        .unwrap_or(0)
+        .max(MIN_SHEET_ROUNDS);
+
+    // round columns start from the first day, like UserLogs::rounds
+    let header = (0..rounds)
+        .map(|round| format!("<th>{}</th>", phase_title(round + 1)))
+        .collect::<String>();
+
+    let rows = users
+        .iter()
+        .enumerate()
+        .flat_map(|(seat, user)| std::iter::repeat_n(seat + 1, user.previous_holders.len() + 1))
+        .zip(logs.iter())
+        .map(|(seat, log)| {
+            let cells = (0..rounds)
+                .map(|round| format!("<td>{}</td>", log.rounds.get(round).map_or("", |r| r.as_str())))
+                .collect::<String>();
+
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td>{}<td></td><td></td></tr>",
+                seat,
+                escape_html(&log.name),
+                escape_html(&log.role),
+                cells,
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        "<table><tr><th>№</th><th>Игрок</th><th>Роль</th>{}<th>Очки</th><th>Лучший</th></tr>{}</table>",
+        header, rows,
+    )
+}
+
+// Printable page with the role cards and the paper score sheet of the current game
+pub fn print_sheet_html(
+    ruleset: &dyn Ruleset,
+    users: &[Player],
+    events: &[GameEvent],
+    center: &[Role],
+    base_url: &str,
+) -> String {
+    let cards = setup_cards(ruleset, users, center)
+        .into_iter()
+        .map(|card| role_card_html(card, base_url))
+        .collect::<String>();
+
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head>\
+<body><h1>{}</h1><div class=\"cards\">{}</div>{}</body></html>",
+        ruleset.title(),
+        SHEET_STYLE,
+        ruleset.title(),
+        cards,
+        score_sheet_html(ruleset, users, events),
+    )
+}
+
+#[component]
+pub fn PrintSheetButton() -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+    let ruleset = use_ruleset();
+
+    let on_print = move |_| {
+        // the downloaded page loads the card art from the app
+        let base_url = document()
+            .base_uri()
+            .ok()
+            .flatten()
+            .map(|url| url[..url.rfind('/').map_or(url.len(), |i| i + 1)].to_string())
+            .unwrap_or_default();
+
+        let html = print_sheet_html(
+            ruleset,
+            &game_ctx.users.get(),
+            &game_ctx.events.get(),
+            &game_ctx.one_night.get().center,
+            &base_url,
+        );
+
+        download_file(&format!("{}-sheet.html", ruleset.name()), "text/html", &html);
+    };
+
+    view! {
+        <button class="text-sm px-2 py-1 bg-gray-200 rounded-full" on:click=on_print>
+            "🖨️"
+        </button>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::one_night::*;
+
+    #[test]
+    fn test_print_sheet_html() {
+        let mut users = vec![
+            Player::new_guest("001".to_string(), "<Ivan>".to_string()),
+            Player::new_guest("002".to_string(), "Olga".to_string()),
+        ];
+        users[0].role.insert(Role::Mafia(MafiaRole::Mafia));
+        let mut events = vec![];
+        test_choice(&users, &mut events, 1, Role::Mafia(MafiaRole::Detective), &["001"]);
+
+        let html = print_sheet_html(&MafiaRuleset, &users, &events, &[], "https://club.example/");
+
+        assert!(html.contains("<img src=\"https://club.example/assets/mafia.png\">"));
+        assert!(html.contains("<img src=\"https://club.example/assets/citizen.png\">"));
+        assert!(html.contains("Выберите игроков Мафии"));
+        assert!(html.contains("&lt;Ivan&gt;"));
+        assert_eq!(html.matches("<th>").count(), 3 + MIN_SHEET_ROUNDS + 2);
+        assert_eq!(html.matches("<tr>").count(), users.len() + 1);
+
+        let cards = setup_cards(&OneNightRuleset, &[], &[Role::OneNight(OneNightRole::Seer)]);
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].get_role(), Role::OneNight(OneNightRole::Seer));
+    }
+}
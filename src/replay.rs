@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsCast;
+use web_sys::js_sys;
+
+use crate::events::*;
+use crate::notes::*;
+use crate::roles::*;
+use crate::saves::*;
+use crate::storage::*;
+use crate::summary::*;
+use crate::user::*;
+use crate::GameContext;
+use crate::GameState;
+
+const FINISHED_GAMES_LIMIT: usize = 30;
+
+pub const FINISHED_GAMES_STORAGE: VersionedStorage = VersionedStorage {
+    key: "finished_games",
+    migrations: &FINISHED_GAMES_MIGRATIONS,
+};
+
+// exported game files use the same schema as the archive entries
+pub const FINISHED_GAME_FILE: VersionedStorage = VersionedStorage {
+    key: "finished_game",
+    migrations: &FINISHED_GAMES_MIGRATIONS,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FinishedGame {
+    pub id: String,
+    pub name: String,
+    pub finished_at: f64,
+    pub game_state: GameState<'static>,
+    pub users: Vec<Player>,
+    // empty when the game was finished without results
+    #[serde(default)]
+    pub winners: HashSet<Role>,
+    #[serde(default)]
+    pub best_players: HashSet<String>,
+    // voter -> the player they picked as the best one
+    #[serde(default)]
+    pub best_player_votes: HashMap<String, String>,
+    // the re-vote among the leaders when the best player vote was tied
+    #[serde(default)]
+    pub best_player_revotes: HashMap<String, String>,
+    #[serde(default)]
+    pub events: Vec<GameEvent>,
+}
+
+pub fn roles_for_game(game_state: &GameState) -> &'static [RoleInfo] {
+    match game_state {
+        GameState::Game(ruleset, _) => ruleset.roles(),
+        GameState::SetupNames => &[],
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayEntry {
+    pub user: Player,
+    pub targeted_by: HashSet<Role>,
+    pub actors: Vec<String>,
+    pub died: bool,
+    pub killed_by: Option<Role>,
+    // name of the dying Brutal player who took the player along
+    pub taken_by: Option<String>,
+    pub protected: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayPhase {
+    pub index: usize,
+    pub entries: Vec<ReplayEntry>,
+    // moderator notes written during the phase with the player name
+    pub notes: Vec<(String, PlayerNote)>,
+}
+
+pub fn phase_title(index: usize) -> String {
+    if index.is_multiple_of(2) {
+        format!("Ночь {}", index / 2)
+    } else {
+        format!("День {}", index.div_ceil(2))
+    }
+}
+
+impl ReplayPhase {
+    // even rounds are nights, odd rounds are the days after them
+    pub fn is_night(&self) -> bool {
+        self.index.is_multiple_of(2)
+    }
+
+    pub fn title(&self) -> String {
+        phase_title(self.index)
+    }
+}
+
+// Phases of the game from the event log, the choices of the unfinished phase are already logged
+pub fn build_replay(users: &[Player], events: &[GameEvent]) -> Vec<ReplayPhase> {
+    let last_note_round = users
+        .iter()
+        .flat_map(|user| user.notes.iter().map(|note| note.round))
+        .max()
+        .unwrap_or(0);
+
+    (0..=last_phase(events).max(last_note_round))
+        .map(|index| {
+            let phase_events = events
+                .iter()
+                .filter(|event| event.phase() == index)
+                .collect::<Vec<_>>();
+
+            let entries = users
+                .iter()
+                .filter_map(|user| {
+                    let targeted_by = marked_roles(events, &user.id)
+                        .into_iter()
+                        .filter(|(round, _)| *round == index)
+                        .flat_map(|(_, roles)| roles)
+                        .collect::<HashSet<_>>();
+
+                    if targeted_by.is_empty() {
+                        return None;
+                    }
+
+                    let user_events = phase_events
+                        .iter()
+                        .filter(|event| event.target() == user.id)
+                        .collect::<Vec<_>>();
+
+                    let actors = user_events
+                        .iter()
+                        .filter_map(|event| match event {
+                            GameEvent::Targeted { actors, .. } => Some(actors),
+                            _ => None,
+                        })
+                        .flatten()
+                        .unique()
+                        .filter_map(|id| users.iter().find(|actor| actor.id == *id))
+                        .map(|actor| actor.name.clone())
+                        .collect::<Vec<_>>();
+
+                    let killed_by = user_events.iter().find_map(|event| match event {
+                        GameEvent::Killed { cause, .. } => Some(*cause),
+                        _ => None,
+                    });
+                    let taken_by = user_events.iter().find_map(|event| match event {
+                        GameEvent::Revenge { actor, .. } => users.iter().find(|u| u.id == *actor).map(|u| u.name.clone()),
+                        _ => None,
+                    });
+                    let protected = user_events
+                        .iter()
+                        .any(|event| matches!(event, GameEvent::Protected { .. }));
+
+                    Some(ReplayEntry {
+                        user: user.clone(),
+                        targeted_by,
+                        actors,
+                        died: killed_by.is_some(),
+                        killed_by: killed_by.flatten(),
+                        taken_by,
+                        protected,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let notes = users
+                .iter()
+                .flat_map(|user| {
+                    user.notes
+                        .iter()
+                        .filter(|note| note.round == index)
+                        .map(|note| (user.name.clone(), note.clone()))
+                })
+                .collect::<Vec<_>>();
+
+            ReplayPhase {
+                index,
+                entries,
+                notes,
+            }
+        })
+        .filter(|phase| !phase.entries.is_empty() || !phase.notes.is_empty())
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct FinishedGames(pub RwSignal<Vec<FinishedGame>>);
+
+impl Default for FinishedGames {
+    fn default() -> Self {
+        let finished_games = FINISHED_GAMES_STORAGE
+            .load_or_recover(|value| {
+                value.as_array().map(|games| {
+                    games
+                        .iter()
+                        .filter_map(|game| serde_json::from_value::<FinishedGame>(game.clone()).ok())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .unwrap_or_default();
+
+        Self(create_rw_signal(finished_games))
+    }
+}
+
+pub fn add_finished_game(finished_games: &mut Vec<FinishedGame>, game: FinishedGame) {
+    finished_games.retain(|g| g.id != game.id);
+    finished_games.push(game);
+
+    if finished_games.len() > FINISHED_GAMES_LIMIT {
+        let overflow = finished_games.len() - FINISHED_GAMES_LIMIT;
+        finished_games.drain(0..overflow);
+    }
+}
+
+pub fn archive_finished_game(
+    game_ctx: &GameContext,
+    winners: HashSet<Role>,
+    best_players: HashSet<String>,
+    best_player_votes: HashMap<String, String>,
+    best_player_revotes: HashMap<String, String>,
+) {
+    let finished_games = use_context::<FinishedGames>().expect("FinishedGames not found");
+    let game_state = game_ctx.game_state.get_untracked();
+    let users = game_ctx.users.get_untracked();
+    let finished_at = js_sys::Date::now();
+
+    let game = FinishedGame {
+        id: game_ctx.save_id.get_untracked().unwrap_or_else(new_save_id),
+        name: default_save_name(&game_state, users.len()),
+        finished_at,
+        game_state,
+        users,
+        winners,
+        best_players,
+        best_player_votes,
+        best_player_revotes,
+        events: game_ctx.events.get_untracked(),
+    };
+
+    finished_games.0.update(|games| add_finished_game(games, game));
+}
+
+pub fn download_file(file_name: &str, content_type: &str, content: &str) {
+    let href = format!(
+        "data:{};charset=utf-8,{}",
+        content_type,
+        js_sys::encode_uri_component(content)
+    );
+
+    let Ok(link) = document().create_element("a") else {
+        return;
+    };
+
+    let _ = link.set_attribute("href", &href);
+    let _ = link.set_attribute("download", file_name);
+
+    if let Ok(link) = link.dyn_into::<web_sys::HtmlElement>() {
+        link.click();
+    }
+}
+
+#[component]
+fn ReplayEntryRow(entry: ReplayEntry, roles: &'static [RoleInfo], round: usize) -> impl IntoView {
+    let find_role = move |role: &Role| roles.iter().find(|r| r.get_role() == *role);
+
+    let checks = entry
+        .user
+        .check_results
+        .iter()
+        .filter(|check| check.round == round)
+        .map(|check| {
+            let icon = find_role(&check.role).map_or("❓", |role_info| role_info.get_role_icon());
+            format!("{} {}", icon, check.result.icon())
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let icons = entry
+        .targeted_by
+        .iter()
+        .filter(|role| **role != Role::WasKilled)
+        .filter_map(find_role)
+        .map(|role_info| role_info.get_role_icon())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let killed_by = match (&entry.killed_by, &entry.taken_by) {
+        (Some(role), _) => find_role(role).map_or("".to_string(), |role_info| format!(" (by {})", role_info.get_role_name())),
+        (None, Some(brutal)) => format!(" (🪓 {})", brutal),
+        (None, None) => "".to_string(),
+    };
+
+    let user_roles = if entry.user.role.is_empty() {
+        "Мирный".to_string()
+    } else {
+        entry
+            .user
+            .role
+            .iter()
+            .filter_map(find_role)
+            .map(|role_info| role_info.get_role_name())
+            .collect::<Vec<_>>()
+            .join(" • ")
+    };
+
+    view! {
+        <div class="w-full flex flex-wrap items-center justify-start gap-1 text-sm">
+            <span class="w-16 text-right">{icons}</span>
+            <span class="bg-gray-100 text-gray-900 px-1 rounded-md">{entry.user.name.clone()}</span>
+            <span class="text-xs opacity-50">{user_roles}</span>
+            {(!entry.actors.is_empty()).then(|| view! {
+                <span class="text-xs opacity-50">{format!("← {}", entry.actors.join(", "))}</span>
+            })}
+            {(!checks.is_empty()).then(|| view! {
+                <span class="text-xs">{checks}</span>
+            })}
+            {if entry.died {
+                view! {
+                    <span class="text-red-900">"❌ убит" {killed_by}</span>
+                }.into_view()
+            } else if entry.protected {
+                view! { <span class="text-green-900">"🛡️ защищен"</span> }.into_view()
+            } else {
+                "".into_view()
+            }}
+        </div>
+    }
+}
+
+#[component]
+pub fn GameReplay(game: FinishedGame, on_close: impl Fn() + Clone + 'static) -> impl IntoView {
+    let roles = roles_for_game(&game.game_state);
+    let phases = build_replay(&game.users, &game.events);
+    let phases_len = phases.len();
+    let phase_index = create_rw_signal(0usize);
+
+    let phases_clone = phases.clone();
+    let current_phase = move || phases_clone.get(phase_index.get()).cloned();
+
+    view! {
+        <div class="flex flex-col gap-2 flex-1 overflow-hidden">
+            <h2 class="flex items-baseline gap-2">
+                {game.name.clone()}
+                <span class="text-xs opacity-50">
+                    {move || format!("{} / {}", (phase_index.get() + 1).min(phases_len), phases_len)}
+                </span>
+            </h2>
+            <div class="flex-1 flex flex-col gap-1 overflow-auto -mx-4 px-4">
+                {move || match current_phase() {
+                    Some(phase) => view! {
+                        <h3 class="text-base">{if phase.is_night() { "🌙 " } else { "☀️ " }}{phase.title()}</h3>
+                        {phase.entries.into_iter().map(|entry| view! {
+                            <ReplayEntryRow entry roles round=phase.index />
+                        }).collect::<Vec<_>>().into_view()}
+                        {phase.notes.into_iter().map(|(name, note)| view! {
+                            <div class="text-sm">"📝 "<span class="bg-gray-100 px-1 rounded-md">{name}</span>" "{note.text}</div>
+                        }).collect::<Vec<_>>().into_view()}
+                    }.into_view(),
+                    None => view! { <div class="text-sm opacity-50">"В этой игре нет событий"</div> }.into_view(),
+                }}
+            </div>
+            <CopySummaryButtons game=game.clone() />
+            <div class="flex gap-2 w-full items-center">
+                <button
+                    class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                    on:click=move |_| on_close()
+                >
+                    "Закрыть"
+                </button>
+                <button
+                    class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full"
+                    on:click=move |_| phase_index.update(|index| *index = index.saturating_sub(1))
+                >
+                    "←"
+                </button>
+                <button
+                    class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full"
+                    on:click=move |_| phase_index.update(|index| {
+                        if *index + 1 < phases_len {
+                            *index += 1;
+                        }
+                    })
+                >
+                    "→"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+pub fn FinishedGamesView(on_close: impl Fn() + Clone + 'static) -> impl IntoView {
+    let finished_games = use_context::<FinishedGames>().expect("FinishedGames not found").0;
+    let replay_game = create_rw_signal(None::<FinishedGame>);
+    let query = create_rw_signal("".to_string());
+
+    let games = move || {
+        let query = query.get();
+        let mut games = finished_games.get();
+        games.retain(|game| query.trim().is_empty() || !search_notes(&game.users, &query).is_empty());
+        games.sort_by(|a, b| b.finished_at.total_cmp(&a.finished_at));
+        games
+    };
+
+    let on_import = move |ev: web_sys::Event| {
+        let input = event_target::<web_sys::HtmlInputElement>(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+
+        wasm_bindgen_futures::spawn_local(async move {
+            let Ok(text) = wasm_bindgen_futures::JsFuture::from(file.text()).await else {
+                return;
+            };
+
+            match FINISHED_GAME_FILE.decode::<FinishedGame>(&text.as_string().unwrap_or_default()) {
+                Ok(game) => finished_games.update(|games| add_finished_game(games, game)),
+                Err(err) => {
+                    logging::warn!("couldn't import game: {:?}", err);
+                    let _ = window().alert_with_message("Не удалось загрузить файл игры");
+                }
+            }
+        });
+    };
+
+    view! {
+        {move || match replay_game.get() {
+            Some(game) => view! {
+                <GameReplay game on_close=move || replay_game.set(None) />
+            }.into_view(),
+            None => {
+                let on_close = on_close.clone();
+
+                view! {
+                    <div class="flex-1 flex flex-col gap-1 overflow-auto -mx-4 px-4">
+                        <h2>"Завершенные игры"</h2>
+                        <input
+                            class="px-3 text-sm py-1 border-gray-200 border rounded-full"
+                            placeholder="Поиск по заметкам"
+                            on:input=move |ev| query.set(event_target_value(&ev))
+                            prop:value=query
+                        />
+                        <For
+                            each=games
+                            key=|game| game.id.clone()
+                            children=move |game| {
+                                let game_replay = game.clone();
+                                let game_export = game.clone();
+                                let id_discard = game.id.clone();
+                                let users = game.users.clone();
+                                let found_notes = move || {
+                                    let query = query.get();
+                                    if query.trim().is_empty() {
+                                        return Vec::new();
+                                    }
+
+                                    search_notes(&users, &query)
+                                        .into_iter()
+                                        .map(|(user, note)| view! {
+                                            <NoteRow name=user.name.clone() note=note.clone() />
+                                        })
+                                        .collect::<Vec<_>>()
+                                };
+
+                                view! {
+                                    <div class="flex gap-1 items-center text-sm">
+                                        <button
+                                            class="flex-1 text-left px-3 py-1 bg-gray-100 rounded-full truncate"
+                                            on:click=move |_| replay_game.set(Some(game_replay.clone()))
+                                        >
+                                            {game.name.clone()}
+                                        </button>
+                                        <button
+                                            class="px-2 py-1 bg-gray-200 rounded-full"
+                                            on:click=move |_| {
+                                                if let Ok(json) = FINISHED_GAME_FILE.encode(&game_export) {
+                                                    download_file(&format!("game-{}.json", game_export.id), "application/json", &json);
+                                                }
+                                            }
+                                        >
+                                            "Экспорт"
+                                        </button>
+                                        <button
+                                            class="text-lg"
+                                            on:click=move |_| {
+                                                if window().confirm_with_message("Удалить игру из истории?").unwrap_or(false) {
+                                                    finished_games.update(|games| games.retain(|g| g.id != id_discard));
+                                                }
+                                            }
+                                        >
+                                            "✕"
+                                        </button>
+                                    </div>
+                                    <div class="flex flex-col gap-0.5 pl-3">{found_notes}</div>
+                                }
+                            }
+                        />
+                    </div>
+                    <label class="flex justify-center px-4 py-1 bg-gray-200 rounded-full">
+                        "Импорт игры"
+                        <input type="file" accept=".json,application/json" class="hidden" on:change=on_import />
+                    </label>
+                    <button
+                        class="px-4 py-1 bg-gray-200 rounded-full"
+                        on:click=move |_| on_close()
+                    >
+                        "Закрыть"
+                    </button>
+                }.into_view()
+            }
+        }}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attributes::*;
+    use crate::werewolf::*;
+
+    #[test]
+    fn test_build_replay_phases() {
+        let mut users = test_players(&[
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::Werewolf(WerewolfRole::Bodyguard),
+            Role::Werewolf(WerewolfRole::Werewolf),
+        ]);
+
+        let mut events = vec![];
+        test_choice(&users, &mut events, 2, Role::Werewolf(WerewolfRole::Werewolf), &["002"]);
+        test_choice(&users, &mut events, 2, Role::Werewolf(WerewolfRole::Bodyguard), &["002"]);
+        resolve_night(&mut users, &mut events, 2);
+        apply_day_vote(&mut users, &mut events, 2, &HashSet::from(["001".to_string()]));
+        // the lynched Brutal werewolf takes the other one along
+        take_revenge(&mut users, &mut events, 3, "001", Some("003"));
+
+        let phases = build_replay(&users, &events);
+
+        assert_eq!(phases.len(), 2);
+        assert_eq!(phases[0].title(), "Ночь 1");
+        assert!(phases[0].entries[0].protected);
+        // both werewolves made the choice, the Bodyguard protected themselves
+        assert_eq!(
+            phases[0].entries[0].actors,
+            vec!["User1".to_string(), "User3".to_string(), "User2".to_string()]
+        );
+        assert_eq!(phases[1].title(), "День 2");
+        assert!(phases[1].entries[0].died);
+        assert_eq!(phases[1].entries[0].killed_by, Some(Role::Werewolf(WerewolfRole::Villager)));
+        assert_eq!(phases[1].entries[1].killed_by, None);
+        assert_eq!(phases[1].entries[1].taken_by, Some("User1".to_string()));
+    }
+
+    #[test]
+    fn test_build_replay_includes_current_phase() {
+        let users = test_players(&[Role::Werewolf(WerewolfRole::Werewolf), Role::Werewolf(WerewolfRole::Bodyguard)]);
+        let mut events = vec![];
+        test_choice(&users, &mut events, 2, Role::Werewolf(WerewolfRole::Werewolf), &["002"]);
+
+        let phases = build_replay(&users, &events);
+
+        assert_eq!(phases.len(), 1);
+        assert_eq!(phases[0].index, 2);
+        assert!(!phases[0].entries[0].died);
+    }
+
+    #[test]
+    fn test_build_replay_includes_notes() {
+        let mut users = test_players(&[Role::Werewolf(WerewolfRole::Werewolf), Role::Werewolf(WerewolfRole::Bodyguard)]);
+        users[0].notes.push(PlayerNote {
+            round: 3,
+            text: "claimed Seer".to_string(),
+            created_at: 0.0,
+        });
+
+        let phases = build_replay(&users, &[]);
+
+        assert_eq!(phases.len(), 1);
+        assert_eq!(phases[0].title(), "День 2");
+        assert_eq!(phases[0].notes[0].0, "User1");
+        assert!(phases[0].entries.is_empty());
+    }
+}
@@ -1,17 +1,63 @@
+use std::collections::HashSet;
+
 use serde::Deserialize;
 use serde::Serialize;
 
 use crate::MafiaRole;
+use crate::OneNightRole;
 use crate::WerewolfRole;
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum Role {
     Mafia(MafiaRole),
     Werewolf(WerewolfRole),
+    OneNight(OneNightRole),
     WasKilled,
     None,
 }
 
+// Team the role plays for. A converted player keeps the old role next to the new one,
+// so the later variant wins when the roles of a player disagree.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum Alignment {
+    Village,
+    Tanner,
+    Maniac,
+    Mafia,
+    Vampire,
+    Werewolf,
+}
+
+impl Role {
+    pub fn alignment(&self) -> Option<Alignment> {
+        match self {
+            Role::Mafia(MafiaRole::Mafia) => Some(Alignment::Mafia),
+            Role::Mafia(MafiaRole::Maniac) => Some(Alignment::Maniac),
+            Role::Mafia(_) => Some(Alignment::Village),
+            Role::Werewolf(WerewolfRole::Werewolf)
+            | Role::Werewolf(WerewolfRole::WolfCub)
+            | Role::Werewolf(WerewolfRole::Minion) => Some(Alignment::Werewolf),
+            Role::Werewolf(WerewolfRole::Vampire) => Some(Alignment::Vampire),
+            Role::Werewolf(WerewolfRole::Tanner) => Some(Alignment::Tanner),
+            // Masons win as a pair, not as a team
+            Role::Werewolf(WerewolfRole::Mason) => None,
+            Role::Werewolf(_) => Some(Alignment::Village),
+            Role::OneNight(OneNightRole::Werewolf) => Some(Alignment::Werewolf),
+            Role::OneNight(_) => Some(Alignment::Village),
+            Role::WasKilled | Role::None => None,
+        }
+    }
+}
+
+// Players without a role are villagers
+pub fn roles_alignment(roles: &HashSet<Role>) -> Alignment {
+    roles
+        .iter()
+        .filter_map(|role| role.alignment())
+        .max()
+        .unwrap_or(Alignment::Village)
+}
+
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 pub enum RoleInfo {
     Night(NightRoleInfo),
@@ -0,0 +1,447 @@
+use itertools::Itertools;
+use leptos::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+
+use crate::attributes::*;
+use crate::events::*;
+use crate::roles::*;
+use crate::user::*;
+use crate::MafiaRuleset;
+use crate::OneNightRuleset;
+use crate::UserLogs;
+use crate::WerewolfRuleset;
+
+// Everything the shared components need to know about a game type.
+// Adding a new game means implementing this trait and listing it in RULESETS.
+pub trait Ruleset: Sync {
+    // Tag stored in saved games, never change it for an existing game
+    fn name(&self) -> &'static str;
+    fn title(&self) -> &'static str;
+    fn start_label(&self) -> &'static str;
+
+    // Role table in night order
+    fn roles(&self) -> &'static [RoleInfo];
+    fn start_phase(&self) -> GamePhase<'static>;
+    fn phase_view(&'static self, phase: GamePhase<'static>) -> View;
+
+    // Contexts that live for the whole game view
+    fn provide_game_context(&self) {}
+
+    fn role_color(&self, user: &Player) -> &'static str;
+    // Labels and roles for the "players left" counter
+    fn alive_counters(&self) -> &'static [(&'static str, Role)];
+    // Roles shown as the killer in the morning hints
+    fn killer_roles(&self) -> &'static [Role];
+
+    fn apply_day_vote(
+        &self,
+        users: &mut [Player],
+        events: &mut Vec<GameEvent>,
+        round: usize,
+        selected_users: &HashSet<String>,
+    );
+    // Players removed by the moderator at the start of the day
+    fn kill_at_day_start(
+        &self,
+        users: &mut [Player],
+        events: &mut Vec<GameEvent>,
+        round: usize,
+        selected_users: &HashSet<String>,
+    );
+    fn day_hints(&self, users: &[Player], events: &[GameEvent]) -> Vec<HintLine>;
+    // Players who die with the ones killed outside of the night and the vote, like the Brutal victim
+    fn after_kills(&self, _users: &mut [Player], _events: &mut Vec<GameEvent>, _phase: usize) {}
+
+    // How many votes the player has in the day vote count
+    fn vote_weight(&self, user: &Player) -> u32 {
+        user.is_alive as u32
+    }
+    // The pick of this voter decides a tied vote
+    fn breaks_vote_ties(&self, _user: &Player) -> bool {
+        false
+    }
+
+    // First night role after `role` that still has someone alive to play it
+    fn next_night_role(&self, role: Option<&RoleInfo>, users: &[Player]) -> Option<&'static RoleInfo> {
+        next_alive_role(self.roles(), role, users)
+    }
+
+    // Steps played once between the setup and the first day
+    fn first_night_roles(&self) -> &'static [RoleInfo] {
+        &[]
+    }
+    fn next_first_night_role(&self, role: Option<&RoleInfo>, users: &[Player]) -> Option<&'static RoleInfo> {
+        next_alive_role(self.first_night_roles(), role, users)
+    }
+
+    // Sides the moderator can pick when the game is finished
+    fn winner_roles(&self) -> &'static [RoleInfo];
+    // Winners are picked by team, a converted player wins with the team they ended the game in
+    fn is_winner(&self, user: &Player, winners: &HashSet<Role>) -> bool {
+        winners
+            .iter()
+            .any(|winner| winner.alignment() == Some(user.alignment()))
+    }
+    fn role_index(&self, user: &Player) -> &'static str;
+    fn role_score(&self, _user: &Player, _users: &[Player], _history: &RoundHistory, winner: bool) -> u32 {
+        winner as u32
+    }
+    // Which sheet the game log goes to
+    fn is_mafia_log(&self) -> bool;
+}
+
+impl fmt::Debug for dyn Ruleset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+pub static RULESETS: [&dyn Ruleset; 3] = [&WerewolfRuleset, &MafiaRuleset, &OneNightRuleset];
+
+fn next_alive_role(
+    roles: &'static [RoleInfo],
+    role: Option<&RoleInfo>,
+    users: &[Player],
+) -> Option<&'static RoleInfo> {
+    let start = role
+        .and_then(|role| roles.iter().position(|r| r == role))
+        .map_or(0, |index| index + 1);
+
+    roles.iter().skip(start).find(|r| {
+        matches!(r, RoleInfo::Night(_))
+            && users
+                .iter()
+                .any(|u| u.is_alive && u.role.contains(&r.get_check_role()))
+    })
+}
+
+pub fn ruleset_by_name(name: &str) -> Option<&'static dyn Ruleset> {
+    RULESETS.iter().copied().find(|r| r.name() == name)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum GamePhase<'a> {
+    SelectActiveRoles,
+    SetupRoles(&'a RoleInfo),
+    Day,
+    Night(&'a RoleInfo),
+    FirstNight(&'a RoleInfo),
+    End,
+}
+
+impl GamePhase<'_> {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GamePhase::SelectActiveRoles => "SelectActiveRoles",
+            GamePhase::SetupRoles(_) => "SetupRoles",
+            GamePhase::Day => "Day",
+            GamePhase::Night(_) => "Night",
+            GamePhase::FirstNight(_) => "FirstNight",
+            GamePhase::End => "End",
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        match self {
+            GamePhase::SetupRoles(role) | GamePhase::Night(role) | GamePhase::FirstNight(role) => {
+                role.get_role()
+            }
+            _ => Role::None,
+        }
+    }
+
+    pub fn decode(name: &str, role: Role, ruleset: &'static dyn Ruleset) -> Option<GamePhase<'static>> {
+        let find_role = |roles: &'static [RoleInfo]| roles.iter().find(|r| r.get_role() == role);
+        let role_info = || find_role(ruleset.roles());
+
+        match (name, role) {
+            ("SelectActiveRoles", Role::None) => Some(GamePhase::SelectActiveRoles),
+            ("SetupRoles", _) => role_info().map(GamePhase::SetupRoles),
+            ("Day", Role::None) => Some(GamePhase::Day),
+            ("Night", _) => role_info().map(GamePhase::Night),
+            ("FirstNight", _) => find_role(ruleset.first_night_roles()).map(GamePhase::FirstNight),
+            ("End", Role::None) => Some(GamePhase::End),
+            _ => None,
+        }
+    }
+}
+
+// One line of the moderator hints
+#[derive(Clone, Debug, PartialEq)]
+pub enum HintLine {
+    Killed(Player, HashSet<Role>),
+    Player(&'static str, Player, &'static str),
+    Players(&'static str, Vec<Player>, &'static str),
+}
+
+// Night choices of every player by round, the current round included
+pub type RoundHistory = HashMap<String, Vec<(usize, HashSet<Role>)>>;
+
+// Icons of the rounds in `played`, the other rounds stay empty
+fn format_rounds(
+    roles: &[RoleInfo],
+    history: &[(usize, HashSet<Role>)],
+    last_round: usize,
+    played: Range<usize>,
+) -> Vec<String> {
+    let mut rounds = vec!["".to_string(); last_round + 1];
+
+    for (index, round_roles) in history.iter().filter(|(index, _)| played.contains(index)) {
+        let adjusted_index = index.saturating_sub(1).min(last_round);
+
+        rounds[adjusted_index] = round_roles
+            .iter()
+            //sort WasKilled role
+            .sorted_by(|a, b| {
+                if **a == Role::WasKilled {
+                    Ordering::Greater
+                } else if **b == Role::WasKilled {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .map(|role| {
+                roles
+                    .iter()
+                    .find(|r| r.get_role() == *role)
+                    .map(|r| r.get_role_icon())
+                    .unwrap_or("❓")
+            })
+            .join(" ");
+    }
+
+    rounds
+}
+
+pub fn calculate_user_logs(
+    ruleset: &dyn Ruleset,
+    users: &[Player],
+    events: &[GameEvent],
+    best_players: &HashSet<String>,
+    selected_winners: &HashSet<Role>,
+) -> Vec<UserLogs> {
+    let roles = ruleset.roles();
+    let last_round = last_phase(events);
+
+    let user_history = users
+        .iter()
+        .map(|user| (user.id.clone(), marked_roles(events, &user.id)))
+        .collect::<RoundHistory>();
+
+    users
+        .iter()
+        .flat_map(|user| {
+            let history = &user_history[&user.id];
+            let winner = ruleset.is_winner(user, selected_winners);
+            let role_index = ruleset.role_index(user).to_string();
+
+            let role = if user.role.is_empty() {
+                "Мирный".to_string()
+            } else {
+                user.role
+                    .iter()
+                    .filter_map(|role| roles.iter().find(|r| r.get_role() == *role))
+                    .map(|r| r.get_role_name())
+                    .join(" • ")
+            };
+            let role = player_attributes(user)
+                .iter()
+                .fold(role, |role, info| format!("{} • {}", role, info.name));
+
+            // players who left the seat get only their rounds, the result goes to the one who finished the game
+            let mut from_round = 0;
+            let mut logs = user
+                .previous_holders
+                .iter()
+                .map(|holder| {
+                    let played = from_round..holder.until_round;
+                    from_round = holder.until_round;
+
+                    UserLogs {
+                        id: holder.id.clone(),
+                        name: holder.name.clone(),
+                        is_guest: holder.is_guest,
+                        role: role.clone(),
+                        score: 0,
+                        winner: false,
+                        best_player: false,
+                        role_index: role_index.clone(),
+                        role_score: 0,
+                        rounds: format_rounds(roles, history, last_round, played),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            logs.push(UserLogs {
+                id: user.id.clone(),
+                name: user.name.clone(),
+                is_guest: user.is_guest,
+                role,
+                score: 0,
+                winner,
+                best_player: best_players.contains(&user.id),
+                role_index,
+                role_score: ruleset.role_score(user, users, &user_history, winner),
+                rounds: format_rounds(roles, history, last_round, from_round..usize::MAX),
+            });
+
+            logs
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mafia::*;
+    use crate::werewolf::*;
+    use crate::GameState;
+
+    #[test]
+    fn test_game_state_serde_keeps_format() {
+        let detective = MAFIA_ROLES
+            .iter()
+            .find(|r| r.get_role() == Role::Mafia(MafiaRole::Detective))
+            .unwrap();
+
+        let states = [
+            (GameState::SetupNames, r#"["SetupNames","","None"]"#),
+            (
+                GameState::Game(&MafiaRuleset, GamePhase::Night(detective)),
+                r#"["Mafia","Night",{"Mafia":"Detective"}]"#,
+            ),
+            (
+                GameState::Game(&WerewolfRuleset, GamePhase::SelectActiveRoles),
+                r#"["Werewolf","SelectActiveRoles","None"]"#,
+            ),
+            (
+                GameState::Game(&WerewolfRuleset, GamePhase::FirstNight(&WEREWOLF_FIRST_NIGHT_ROLES[0])),
+                r#"["Werewolf","FirstNight",{"Werewolf":"Cupid"}]"#,
+            ),
+            (
+                GameState::Game(&OneNightRuleset, GamePhase::End),
+                r#"["OneNight","End","None"]"#,
+            ),
+        ];
+
+        for (state, json) in states {
+            let encoded = serde_json::to_string(&state).unwrap();
+            let decoded: GameState = serde_json::from_str(&encoded).unwrap();
+
+            assert_eq!(encoded, json);
+            assert_eq!(format!("{:?}", decoded), format!("{:?}", state));
+        }
+
+        assert!(serde_json::from_str::<GameState>(r#"["Chess","Day","None"]"#).is_err());
+    }
+
+    #[test]
+    fn test_next_night_role_skips_dead_roles() {
+        let mut users = test_players(&[Role::None; 2]);
+        users[0].role.insert(Role::Mafia(MafiaRole::Mafia));
+        users[1].role.insert(Role::Mafia(MafiaRole::Doctor));
+
+        let first = MafiaRuleset.next_night_role(None, &users).unwrap();
+        assert_eq!(first.get_role(), Role::Mafia(MafiaRole::Mafia));
+
+        users[1].is_alive = false;
+        assert!(MafiaRuleset.next_night_role(Some(first), &users).is_none());
+    }
+
+    #[test]
+    fn test_winner_by_alignment_after_conversion() {
+        let mut users = test_players(&[Role::None; 3]);
+        users[0].role.insert(Role::Werewolf(WerewolfRole::Werewolf));
+        users[1].role.insert(Role::Werewolf(WerewolfRole::Cursed));
+        users[2].role.insert(Role::Werewolf(WerewolfRole::Seer));
+        let mut events = vec![];
+        test_choice(&users, &mut events, 2, Role::Werewolf(WerewolfRole::Werewolf), &["002"]);
+
+        resolve_night(&mut users, &mut events, 2);
+
+        assert_eq!(users[1].original_alignment(), Alignment::Village);
+        assert_eq!(users[1].alignment(), Alignment::Werewolf);
+        assert_eq!(
+            users[1].alignment_changes,
+            vec![AlignmentChange {
+                round: 2,
+                from: Alignment::Village,
+                to: Alignment::Werewolf,
+                cause: Role::Werewolf(WerewolfRole::Cursed),
+            }]
+        );
+
+        let logs = calculate_user_logs(
+            &WerewolfRuleset,
+            &users,
+            &events,
+            &HashSet::new(),
+            &HashSet::from([Role::Werewolf(WerewolfRole::Villager)]),
+        );
+
+        assert_eq!(logs.iter().map(|log| log.winner).collect::<Vec<_>>(), [false, false, true]);
+        assert_eq!(logs[1].role_index, "Werewolf");
+        assert!(MafiaRuleset.is_winner(&users[2], &HashSet::from([Role::Mafia(MafiaRole::Citizen)])));
+    }
+
+    #[test]
+    fn test_calculate_user_logs() {
+        let mut users = test_players(&[Role::None; 2]);
+        users[0].role.insert(Role::Werewolf(WerewolfRole::Werewolf));
+        let mut events = vec![];
+        test_choice(&users, &mut events, 0, Role::Werewolf(WerewolfRole::Werewolf), &["002"]);
+        resolve_night(&mut users, &mut events, 0);
+
+        let logs = calculate_user_logs(
+            &WerewolfRuleset,
+            &users,
+            &events,
+            &HashSet::from(["002".to_string()]),
+            &HashSet::from([Role::Werewolf(WerewolfRole::Werewolf)]),
+        );
+
+        assert!(logs[0].winner);
+        assert_eq!(logs[0].role_index, "Werewolf");
+        assert!(!logs[1].winner);
+        assert!(logs[1].best_player);
+        assert_eq!(logs[1].role, "Мирный");
+        assert!(logs[1].rounds[0].ends_with("❌"));
+    }
+
+    #[test]
+    fn test_calculate_user_logs_splits_substituted_seat() {
+        let mut users = test_players(&[Role::None]);
+        users[0].role.insert(Role::Werewolf(WerewolfRole::Werewolf));
+        let mut events = vec![];
+        test_choice(&users, &mut events, 2, Role::Werewolf(WerewolfRole::Seer), &["001"]);
+        substitute_player(
+            &mut users[0],
+            &UserSheetInfo::new("002".to_string(), "User2".to_string(), "".to_string(), false),
+            3,
+        );
+        rename_player(&mut events, "001", "002");
+        test_choice(&users, &mut events, 4, Role::Werewolf(WerewolfRole::Bodyguard), &["002"]);
+
+        let logs = calculate_user_logs(
+            &WerewolfRuleset,
+            &users,
+            &events,
+            &HashSet::new(),
+            &HashSet::from([Role::Werewolf(WerewolfRole::Werewolf)]),
+        );
+
+        assert_eq!(logs.len(), 2);
+        assert_eq!((logs[0].id.as_str(), logs[1].id.as_str()), ("001", "002"));
+        assert_eq!(logs[0].rounds[1], "🔍");
+        assert!(logs[0].rounds[3].is_empty());
+        assert!(logs[1].rounds[1].is_empty());
+        assert_eq!(logs[1].rounds[3], "🛡️");
+        assert!(!logs[0].winner);
+        assert!(logs[1].winner);
+    }
+}
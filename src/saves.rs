@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use web_sys::js_sys;
+
+use crate::one_night::*;
+use crate::replay::*;
+use crate::roles::*;
+use crate::storage::*;
+use crate::user::*;
+use crate::GameContext;
+use crate::GameContextHistory;
+use crate::GameState;
+
+pub const SAVED_GAMES_STORAGE: VersionedStorage = VersionedStorage {
+    key: "saved_games",
+    migrations: &SAVED_GAMES_MIGRATIONS,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub id: String,
+    pub name: String,
+    pub updated_at: f64,
+    pub state: GameContextHistory,
+    pub history: Vec<GameContextHistory>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SavedGames(pub RwSignal<Vec<SavedGame>>);
+
+impl Default for SavedGames {
+    fn default() -> Self {
+        let saved_games = SAVED_GAMES_STORAGE
+            .load_or_recover(|value| {
+                // keep the slots which still can be read
+                value.as_array().map(|games| {
+                    games
+                        .iter()
+                        .filter_map(|game| serde_json::from_value::<SavedGame>(game.clone()).ok())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .unwrap_or_default();
+
+        Self(create_rw_signal(saved_games))
+    }
+}
+
+pub fn new_save_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+pub fn game_state_name(game_state: &GameState) -> &'static str {
+    match game_state {
+        GameState::SetupNames => "Новая игра",
+        GameState::Game(ruleset, _) => ruleset.title(),
+    }
+}
+
+pub fn format_timestamp(timestamp: f64) -> String {
+    let date = js_sys::Date::new(&timestamp.into());
+
+    format!(
+        "{:02}.{:02} {:02}:{:02}",
+        date.get_date(),
+        date.get_month() + 1,
+        date.get_hours(),
+        date.get_minutes()
+    )
+}
+
+pub fn default_save_name(game_state: &GameState, players: usize) -> String {
+    format!("{} • {} игроков", game_state_name(game_state), players)
+}
+
+// Save the running game into its slot, setup screen is never saved
+pub fn upsert_saved_game(
+    saved_games: &mut Vec<SavedGame>,
+    state: GameContextHistory,
+    history: Vec<GameContextHistory>,
+    timestamp: f64,
+) {
+    let Some(id) = state.save_id.clone() else {
+        return;
+    };
+
+    if matches!(state.game_state, GameState::SetupNames) {
+        return;
+    }
+
+    match saved_games.iter_mut().find(|game| game.id == id) {
+        Some(game) => {
+            game.state = state;
+            game.history = history;
+            game.updated_at = timestamp;
+        }
+        None => saved_games.push(SavedGame {
+            id,
+            name: default_save_name(&state.game_state, state.users.len()),
+            updated_at: timestamp,
+            state,
+            history,
+        }),
+    }
+}
+
+pub fn duplicate_saved_game(saved_games: &mut Vec<SavedGame>, id: &str, timestamp: f64) {
+    if let Some(game) = saved_games.iter().find(|game| game.id == id) {
+        let mut copy = game.clone();
+        copy.id = new_save_id();
+        copy.name = format!("{} (копия)", game.name);
+        copy.updated_at = timestamp;
+        copy.state.save_id = Some(copy.id.clone());
+        copy.history
+            .iter_mut()
+            .for_each(|state| state.save_id = Some(copy.id.clone()));
+
+        saved_games.push(copy);
+    }
+}
+
+fn set_context_history(history: Vec<GameContextHistory>) {
+    let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+        .expect("MafiaContext history not found");
+
+    set_context_history.set(history);
+}
+
+// Leave the running game in its slot and go back to the players setup with the same players
+pub fn pause_game(game_ctx: &GameContext) {
+    let mut users = game_ctx.users.get_untracked();
+    reset_user_roles(&mut users);
+
+    game_ctx.set_history(GameContextHistory {
+        users,
+        round: 0,
+        game_state: GameState::SetupNames,
+        save_id: None,
+        one_night: OneNightCards::default(),
+        events: vec![],
+    });
+
+    set_context_history(Vec::new());
+}
+
+// The game is over, its slot is not needed anymore
+pub fn finish_game(
+    game_ctx: &GameContext,
+    winners: HashSet<Role>,
+    best_players: HashSet<String>,
+    best_player_votes: HashMap<String, String>,
+    best_player_revotes: HashMap<String, String>,
+) {
+    let saved_games = use_context::<SavedGames>().expect("SavedGames not found");
+
+    archive_finished_game(game_ctx, winners, best_players, best_player_votes, best_player_revotes);
+
+    if let Some(id) = game_ctx.save_id.get_untracked() {
+        saved_games.0.update(|games| games.retain(|game| game.id != id));
+    }
+
+    game_ctx.save_id.set(None);
+    game_ctx.game_state.set(GameState::SetupNames);
+}
+
+#[component]
+pub fn PauseGameButton() -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+    view! {
+        <button
+            class="text-sm px-2 py-1 bg-gray-200 rounded-full"
+            on:click=move |_| pause_game(&game_ctx)
+        >
+            "Пауза"
+        </button>
+    }
+}
+
+#[component]
+pub fn SavedGamesList() -> impl IntoView {
+    let saved_games = use_context::<SavedGames>().expect("SavedGames not found").0;
+
+    let games = move || {
+        let mut games = saved_games.get();
+        games.sort_by(|a, b| b.updated_at.total_cmp(&a.updated_at));
+        games
+    };
+
+    view! {
+        <Show when=move || !saved_games.get().is_empty()>
+            <div class="flex flex-col gap-1">
+                <h2>"Незаконченные игры"</h2>
+                <For
+                    each=games
+                    key=|game| format!("{}_{}_{}", game.id, game.name, game.updated_at)
+                    children=move |game| {
+                        let id_resume = game.id.clone();
+                        let id_rename = game.id.clone();
+                        let id_duplicate = game.id.clone();
+                        let id_discard = game.id.clone();
+                        let name = game.name.clone();
+
+                        view! {
+                            <div class="flex gap-1 items-center text-sm">
+                                <button
+                                    class="flex-1 text-left px-3 py-1 bg-gray-100 rounded-full truncate"
+                                    on:click=move |_| {
+                                        if let Ok(Some(name)) = window().prompt_with_message_and_default("Название игры", &name) {
+                                            saved_games.update(|games| {
+                                                if let Some(game) = games.iter_mut().find(|g| g.id == id_rename) {
+                                                    game.name = name;
+                                                }
+                                            });
+                                        }
+                                    }
+                                >
+                                    {game.name.clone()}
+                                    <span class="opacity-50 ml-1">{format_timestamp(game.updated_at)}</span>
+                                </button>
+                                <button
+                                    class="px-3 py-1 bg-blue-300 rounded-full"
+                                    on:click=move |_| {
+                                        let Some(game) = saved_games.get_untracked().into_iter().find(|g| g.id == id_resume) else {
+                                            return;
+                                        };
+
+                                        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+                                        set_context_history(game.history);
+                                        game_ctx.set_history(game.state);
+                                    }
+                                >
+                                    "Продолжить"
+                                </button>
+                                <button
+                                    class="px-2 py-1 bg-gray-200 rounded-full"
+                                    on:click=move |_| {
+                                        saved_games.update(|games| {
+                                            duplicate_saved_game(games, &id_duplicate, js_sys::Date::now());
+                                        });
+                                    }
+                                >
+                                    "Копия"
+                                </button>
+                                <button
+                                    class="text-lg"
+                                    on:click=move |_| {
+                                        if window().confirm_with_message("Удалить незаконченную игру?").unwrap_or(false) {
+                                            saved_games.update(|games| games.retain(|g| g.id != id_discard));
+                                        }
+                                    }
+                                >
+                                    "✕"
+                                </button>
+                            </div>
+                        }
+                    }
+                />
+            </div>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mafia::*;
+    use crate::ruleset::*;
+
+    fn create_state_for_test(save_id: Option<String>) -> GameContextHistory {
+        GameContextHistory {
+            users: vec![Player::new_player("001".to_string(), "User1".to_string(), "".to_string())],
+            round: 2,
+            game_state: GameState::Game(&MafiaRuleset, GamePhase::Day),
+            save_id,
+            one_night: OneNightCards::default(),
+            events: vec![],
+        }
+    }
+
+    #[test]
+    fn test_upsert_saved_game() {
+        let mut saved_games = Vec::new();
+
+        upsert_saved_game(&mut saved_games, create_state_for_test(None), vec![], 0.0);
+        assert!(saved_games.is_empty());
+
+        let state = create_state_for_test(Some("slot".to_string()));
+        upsert_saved_game(&mut saved_games, state.clone(), vec![], 0.0);
+        upsert_saved_game(&mut saved_games, state.clone(), vec![state], 1.0);
+
+        assert_eq!(saved_games.len(), 1);
+        assert_eq!(saved_games[0].history.len(), 1);
+        assert_eq!(saved_games[0].updated_at, 1.0);
+    }
+
+    #[test]
+    fn test_duplicate_saved_game() {
+        let mut saved_games = Vec::new();
+        let state = create_state_for_test(Some("slot".to_string()));
+        upsert_saved_game(&mut saved_games, state.clone(), vec![state], 0.0);
+
+        duplicate_saved_game(&mut saved_games, "slot", 1.0);
+
+        assert_eq!(saved_games.len(), 2);
+        let copy = &saved_games[1];
+        assert_ne!(copy.id, "slot");
+        assert_eq!(copy.state.save_id, Some(copy.id.clone()));
+        assert_eq!(copy.history[0].save_id, Some(copy.id.clone()));
+    }
+}
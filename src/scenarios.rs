@@ -0,0 +1,446 @@
+// Data driven rules tests, every scenarios/*.json file is played phase by phase
+// with the real night resolution and day vote of its ruleset.
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::events::*;
+use crate::mafia;
+use crate::roles::*;
+use crate::ruleset::*;
+use crate::user::*;
+use crate::werewolf;
+use crate::MafiaRole;
+use crate::MafiaRuleset;
+use crate::WerewolfRole;
+use crate::WerewolfRuleset;
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum ScenarioGame {
+    Mafia,
+    Werewolf,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScenarioPlayer {
+    pub name: String,
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub additional_roles: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScenarioAction {
+    // night role name -> chosen players
+    Night(HashMap<String, Vec<String>>),
+    // players executed by the day vote
+    Day(Vec<String>),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ScenarioExpect {
+    // players who died during the phase, exact list
+    pub killed: Option<Vec<String>>,
+    pub alive: Vec<String>,
+    // player name -> exact list of roles after the phase
+    pub roles: HashMap<String, Vec<String>>,
+    pub additional_roles: HashMap<String, Vec<String>>,
+    // player name -> exact list of status effects after the phase
+    pub effects: HashMap<String, Vec<String>>,
+    // morning hints after the phase, exact list
+    pub hints: Option<Vec<String>>,
+    // night role name -> hints shown when the role wakes up
+    pub night_hints: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScenarioPhase {
+    #[serde(flatten)]
+    pub action: ScenarioAction,
+    #[serde(default)]
+    pub expect: ScenarioExpect,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    pub game: ScenarioGame,
+    pub players: Vec<ScenarioPlayer>,
+    pub phases: Vec<ScenarioPhase>,
+}
+
+fn names(users: &[Player]) -> String {
+    users
+        .iter()
+        .map(|u| u.name.clone())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl ScenarioGame {
+    fn parse_role(&self, name: &str) -> Result<Role, String> {
+        let value = serde_json::Value::String(name.to_string());
+
+        match self {
+            ScenarioGame::Mafia => serde_json::from_value::<MafiaRole>(value).map(Role::Mafia),
+            ScenarioGame::Werewolf => {
+                serde_json::from_value::<WerewolfRole>(value).map(Role::Werewolf)
+            }
+        }
+        .map_err(|_| format!("unknown role {}", name))
+    }
+
+    fn insert_role(&self, user: &mut Player, role: Role) {
+        match self {
+            ScenarioGame::Mafia => {
+                user.role.insert(role);
+            }
+            ScenarioGame::Werewolf => werewolf::insert_user_role(user, role),
+        }
+    }
+
+    fn ruleset(&self) -> &'static dyn Ruleset {
+        match self {
+            ScenarioGame::Mafia => &MafiaRuleset,
+            ScenarioGame::Werewolf => &WerewolfRuleset,
+        }
+    }
+
+    fn resolve_night(&self, users: &mut [Player], events: &mut Vec<GameEvent>, round: usize) {
+        match self {
+            ScenarioGame::Mafia => mafia::calculate_night_kills(users, events, round),
+            ScenarioGame::Werewolf => werewolf::resolve_night(users, events, round),
+        }
+    }
+
+    fn day_hints(&self, users: &[Player], events: &[GameEvent]) -> Vec<String> {
+        match self {
+            ScenarioGame::Mafia => mafia::day_hints(users, events)
+                .into_iter()
+                .map(describe_mafia_hint)
+                .collect(),
+            ScenarioGame::Werewolf => werewolf::day_hints(users, events)
+                .into_iter()
+                .map(describe_werewolf_hint)
+                .collect(),
+        }
+    }
+
+    fn night_hints(&self, role: Role, users: &[Player], events: &[GameEvent], round: usize) -> Vec<String> {
+        match self {
+            ScenarioGame::Mafia => mafia::night_hints(role, users)
+                .into_iter()
+                .map(describe_mafia_hint)
+                .collect(),
+            ScenarioGame::Werewolf => werewolf::night_hints(role, users, events, round)
+                .into_iter()
+                .map(describe_werewolf_hint)
+                .collect(),
+        }
+    }
+}
+
+fn describe_mafia_hint(hint: mafia::MafiaHint) -> String {
+    match hint {
+        mafia::MafiaHint::Killed(user, _) => format!("Killed:{}", user.name),
+        mafia::MafiaHint::Prostitute(user) => format!("Prostitute:{}", user.name),
+        mafia::MafiaHint::Detective(users) => format!("Detective:{}", names(&users)),
+        mafia::MafiaHint::Priest(users) => format!("Priest:{}", names(&users)),
+    }
+}
+
+fn describe_werewolf_hint(hint: werewolf::WerewolfHint) -> String {
+    match hint {
+        werewolf::WerewolfHint::Mayor(user) => format!("Mayor:{}", user.name),
+        werewolf::WerewolfHint::Cursed(user) => format!("Cursed:{}", user.name),
+        werewolf::WerewolfHint::LostHeart(user) => format!("LostHeart:{}", user.name),
+        werewolf::WerewolfHint::Effect(kind, user) => format!("{:?}:{}", kind, user.name),
+        werewolf::WerewolfHint::Killed(user, _) => format!("Killed:{}", user.name),
+        werewolf::WerewolfHint::Suspects(role, users) => format!("Suspects:{:?}:{}", role, names(&users)),
+        werewolf::WerewolfHint::Meeting(role, users) => format!("Meeting:{:?}:{}", role, names(&users)),
+        werewolf::WerewolfHint::Victim(user) => format!("Victim:{}", user.name),
+    }
+}
+
+fn sorted(mut items: Vec<String>) -> Vec<String> {
+    items.sort();
+    items
+}
+
+fn role_name(role: &Role) -> String {
+    match role {
+        Role::Mafia(role) => format!("{:?}", role),
+        Role::Werewolf(role) => format!("{:?}", role),
+        role => format!("{:?}", role),
+    }
+}
+
+fn role_names(roles: &HashSet<Role>) -> Vec<String> {
+    sorted(roles.iter().map(role_name).collect())
+}
+
+struct ScenarioRun<'a> {
+    scenario: &'a Scenario,
+    users: Vec<Player>,
+    events: Vec<GameEvent>,
+    round: usize,
+}
+
+impl<'a> ScenarioRun<'a> {
+    fn new(scenario: &'a Scenario) -> Result<Self, String> {
+        let game = scenario.game;
+        let mut users = Vec::new();
+
+        for player in &scenario.players {
+            let mut user = Player::new_guest(player.name.clone(), player.name.clone());
+
+            for role in &player.roles {
+                game.insert_role(&mut user, game.parse_role(role)?);
+            }
+            for role in &player.additional_roles {
+                user.additional_role.insert(game.parse_role(role)?);
+            }
+
+            users.push(user);
+        }
+
+        if let ScenarioGame::Werewolf = game {
+            werewolf::initialize_user_roles(&mut users);
+        }
+
+        Ok(Self {
+            scenario,
+            users,
+            events: vec![],
+            round: 0,
+        })
+    }
+
+    fn user_index(&self, name: &str) -> Result<usize, String> {
+        self.users
+            .iter()
+            .position(|u| u.name == name)
+            .ok_or(format!("unknown player {}", name))
+    }
+
+    fn play_night(
+        &mut self,
+        actions: &HashMap<String, Vec<String>>,
+        expect: &ScenarioExpect,
+    ) -> Result<(), String> {
+        let game = self.scenario.game;
+        let mut pending = actions.keys().cloned().collect::<HashSet<_>>();
+        let mut role_info = game.ruleset().next_night_role(None, &self.users);
+
+        while let Some(current) = role_info {
+            let role = current.get_role();
+            let role_name = role_name(&role);
+
+            if let Some(expected) = expect.night_hints.get(&role_name) {
+                let hints = game.night_hints(role, &self.users, &self.events, self.round);
+                if sorted(hints.clone()) != sorted(expected.clone()) {
+                    return Err(format!(
+                        "{} hints {:?}, expected {:?}",
+                        role_name, hints, expected
+                    ));
+                }
+            }
+
+            if let Some(targets) = actions.get(&role_name) {
+                pending.remove(&role_name);
+
+                let targets = targets
+                    .iter()
+                    .map(|target| self.user_index(target).map(|index| self.users[index].id.clone()))
+                    .collect::<Result<HashSet<_>, _>>()?;
+                let actors = role_actors(&self.users, role);
+                record_choice(&self.users, &mut self.events, self.round, role, &actors, &targets);
+            }
+
+            role_info = game.ruleset().next_night_role(Some(current), &self.users);
+        }
+
+        if !pending.is_empty() {
+            return Err(format!("roles {:?} did not wake up this night", pending));
+        }
+
+        game.resolve_night(&mut self.users, &mut self.events, self.round);
+
+        Ok(())
+    }
+
+    fn play_day(&mut self, executed: &[String]) -> Result<(), String> {
+        let selected = executed
+            .iter()
+            .map(|name| {
+                self.user_index(name)
+                    .map(|index| self.users[index].id.clone())
+            })
+            .collect::<Result<HashSet<_>, _>>()?;
+
+        self.scenario
+            .game
+            .ruleset()
+            .apply_day_vote(&mut self.users, &mut self.events, self.round, &selected);
+        self.round += 2;
+
+        Ok(())
+    }
+
+    fn check(&self, expect: &ScenarioExpect, alive_before: &[bool]) -> Result<(), String> {
+        let game = self.scenario.game;
+
+        if let Some(expected) = &expect.killed {
+            let killed = self
+                .users
+                .iter()
+                .zip(alive_before)
+                .filter(|(user, was_alive)| **was_alive && !user.is_alive)
+                .map(|(user, _)| user.name.clone())
+                .collect::<Vec<_>>();
+
+            if sorted(killed.clone()) != sorted(expected.clone()) {
+                return Err(format!("killed {:?}, expected {:?}", killed, expected));
+            }
+        }
+
+        for name in &expect.alive {
+            if !self.users[self.user_index(name)?].is_alive {
+                return Err(format!("{} should be alive", name));
+            }
+        }
+
+        for (name, expected) in &expect.roles {
+            let roles = role_names(&self.users[self.user_index(name)?].role);
+            if roles != sorted(expected.clone()) {
+                return Err(format!(
+                    "{} roles {:?}, expected {:?}",
+                    name, roles, expected
+                ));
+            }
+        }
+
+        for (name, expected) in &expect.additional_roles {
+            let roles = role_names(&self.users[self.user_index(name)?].additional_role);
+            if roles != sorted(expected.clone()) {
+                return Err(format!(
+                    "{} additional roles {:?}, expected {:?}",
+                    name, roles, expected
+                ));
+            }
+        }
+
+        for (name, expected) in &expect.effects {
+            let effects = sorted(
+                self.users[self.user_index(name)?]
+                    .effects
+                    .iter()
+                    .map(|effect| format!("{:?}", effect.kind))
+                    .collect(),
+            );
+            if effects != sorted(expected.clone()) {
+                return Err(format!(
+                    "{} effects {:?}, expected {:?}",
+                    name, effects, expected
+                ));
+            }
+        }
+
+        if let Some(expected) = &expect.hints {
+            let hints = game.day_hints(&self.users, &self.events);
+            if sorted(hints.clone()) != sorted(expected.clone()) {
+                return Err(format!("hints {:?}, expected {:?}", hints, expected));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn run_scenario(scenario: &Scenario) -> Result<(), String> {
+    let mut run = ScenarioRun::new(scenario)?;
+
+    for (index, phase) in scenario.phases.iter().enumerate() {
+        let alive_before = run.users.iter().map(|u| u.is_alive).collect::<Vec<_>>();
+
+        match &phase.action {
+            ScenarioAction::Night(actions) => run.play_night(actions, &phase.expect),
+            ScenarioAction::Day(executed) => run.play_day(executed),
+        }
+        .and_then(|_| run.check(&phase.expect, &alive_before))
+        .map_err(|err| format!("phase {}: {}", index + 1, err))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn scenario_files(dir: &Path) -> Vec<std::path::PathBuf> {
+        let mut files = Vec::new();
+
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(scenario_files(&path));
+            } else if path.extension().is_some_and(|ext| ext == "json") {
+                files.push(path);
+            }
+        }
+
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_scenario_files() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        let files = scenario_files(&dir);
+        assert!(!files.is_empty());
+
+        let failures = files
+            .iter()
+            .filter_map(|path| {
+                let json = std::fs::read_to_string(path).unwrap();
+                let result = serde_json::from_str::<Scenario>(&json)
+                    .map_err(|err| err.to_string())
+                    .and_then(|scenario| {
+                        run_scenario(&scenario).map_err(|err| format!("{}: {}", scenario.name, err))
+                    });
+
+                result
+                    .err()
+                    .map(|err| format!("{}: {}", path.display(), err))
+            })
+            .collect::<Vec<_>>();
+
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_scenario_reports_wrong_expectation() {
+        let scenario = serde_json::from_str::<Scenario>(
+            r#"{
+                "name": "wrong",
+                "game": "Mafia",
+                "players": [
+                    { "name": "Don", "roles": ["Mafia"] },
+                    { "name": "Bob", "roles": ["Citizen"] }
+                ],
+                "phases": [
+                    { "night": { "Mafia": ["Bob"] }, "expect": { "killed": [] } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            run_scenario(&scenario),
+            Err("phase 1: killed [\"Bob\"], expected []".to_string())
+        );
+    }
+}
@@ -0,0 +1,386 @@
+// Headless Werewolf simulator for setup balancing.
+// Bots play full games with the real night resolution and win checks,
+// run it with `SIM_ROLES=Werewolf,Werewolf,Seer SIM_PLAYERS=10 cargo test simulate_werewolf_setup -- --ignored --nocapture`
+use std::collections::{HashMap, HashSet};
+
+use crate::events::*;
+use crate::roles::*;
+use crate::ruleset::*;
+use crate::user::*;
+use crate::werewolf::*;
+
+const MAX_ROUNDS: usize = 60;
+
+// xorshift64*, good enough for bots and keeps the runs reproducible by seed
+pub struct SimRng(u64);
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn chance(&mut self, probability: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+
+    pub fn pick<T: Copy>(&mut self, items: &[T]) -> Option<T> {
+        if items.is_empty() {
+            None
+        } else {
+            Some(items[self.below(items.len())])
+        }
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SimulationReport {
+    pub games: usize,
+    pub wins: HashMap<Role, usize>,
+    pub draws: usize,
+    pub total_days: usize,
+}
+
+impl SimulationReport {
+    pub fn win_rate(&self, role: &Role) -> f64 {
+        *self.wins.get(role).unwrap_or(&0) as f64 / self.games.max(1) as f64
+    }
+
+    pub fn average_days(&self) -> f64 {
+        self.total_days as f64 / self.games.max(1) as f64
+    }
+
+    pub fn format(&self) -> String {
+        let mut lines = vec![format!(
+            "games: {}, average days: {:.2}",
+            self.games,
+            self.average_days()
+        )];
+
+        let mut wins = self.wins.iter().collect::<Vec<_>>();
+        wins.sort_by(|a, b| b.1.cmp(a.1));
+        for (role, _) in wins {
+            lines.push(format!("{:?}: {:.1}%", role, self.win_rate(role) * 100.0));
+        }
+        lines.push(format!(
+            "draws: {:.1}%",
+            self.draws as f64 / self.games.max(1) as f64 * 100.0
+        ));
+
+        lines.join("\n")
+    }
+}
+
+// What the village has learned during the game, shared by all village bots
+#[derive(Default)]
+struct VillageKnowledge {
+    suspects: HashSet<usize>,
+    cleared: HashSet<usize>,
+}
+
+// Players with the given roles, the rest of the table are Villagers
+pub fn create_table(roles: &[Role], players: usize, rng: &mut SimRng) -> Vec<Player> {
+    let mut users = (0..players)
+        .map(|i| Player::new_guest(format!("{:03}", i), format!("Bot{}", i)))
+        .collect::<Vec<_>>();
+
+    let mut seats = (0..players).collect::<Vec<_>>();
+    rng.shuffle(&mut seats);
+
+    for (role, seat) in roles.iter().zip(seats) {
+        insert_user_role(&mut users[seat], *role);
+    }
+
+    for user in users.iter_mut().filter(|u| u.role.is_empty()) {
+        user.role.insert(Role::Werewolf(WerewolfRole::Villager));
+    }
+
+    initialize_user_roles(&mut users);
+
+    users
+}
+
+fn has_role(user: &Player, role: WerewolfRole) -> bool {
+    user.role.contains(&Role::Werewolf(role))
+}
+
+fn alive_indices(users: &[Player], filter: impl Fn(usize, &Player) -> bool) -> Vec<usize> {
+    users
+        .iter()
+        .enumerate()
+        .filter(|(index, u)| u.is_alive && filter(*index, u))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+// Bot policy for a night role, returns the player the role chooses
+fn choose_night_target(
+    role: WerewolfRole,
+    users: &[Player],
+    events: &[GameEvent],
+    knowledge: &VillageKnowledge,
+    round: usize,
+    rng: &mut SimRng,
+) -> Option<usize> {
+    let actor = Role::Werewolf(role);
+    let actors = alive_indices(users, |_, u| u.role.contains(&actor));
+    let is_actor = |index: usize| actors.contains(&index);
+
+    match role {
+        WerewolfRole::Werewolf => rng.pick(&alive_indices(users, |_, u| {
+            !has_role(u, WerewolfRole::Werewolf)
+        })),
+        WerewolfRole::Vampire => rng.pick(&alive_indices(users, |_, u| {
+            !has_role(u, WerewolfRole::Vampire)
+        })),
+        WerewolfRole::Bodyguard => rng.pick(&alive_indices(users, |_, u| {
+            !was_targeted_by(events, &u.id, &actor)
+        })),
+        WerewolfRole::Priest if !is_role_used(events, actor) => {
+            rng.pick(&alive_indices(users, |index, _| !is_actor(index)))
+        }
+        // the witch sees the werewolves victim and saves it
+        WerewolfRole::WitchHeal if !is_role_used(events, actor) => alive_indices(users, |_, u| {
+            phase_choices(events, round, &u.id).contains(&Role::Werewolf(WerewolfRole::Werewolf))
+        })
+        .first()
+        .copied(),
+        WerewolfRole::WitchPoison if round > 0 && !is_role_used(events, actor) && rng.chance(0.3) => {
+            let suspects = alive_indices(users, |index, _| knowledge.suspects.contains(&index));
+            rng.pick(&suspects)
+                .or_else(|| rng.pick(&alive_indices(users, |index, _| !is_actor(index))))
+        }
+        WerewolfRole::Seer => rng.pick(&alive_indices(users, |index, u| {
+            !is_actor(index) && !was_targeted_by(events, &u.id, &actor)
+        })),
+        WerewolfRole::Spellcaster => rng.pick(&alive_indices(users, |index, _| !is_actor(index))),
+        WerewolfRole::Huntress if round > 0 && !is_role_used(events, actor) && rng.chance(0.3) => {
+            let suspects = alive_indices(users, |index, _| knowledge.suspects.contains(&index));
+            rng.pick(&suspects)
+                .or_else(|| rng.pick(&alive_indices(users, |index, _| !is_actor(index))))
+        }
+        WerewolfRole::Revealer => rng.pick(&alive_indices(users, |index, _| {
+            knowledge.suspects.contains(&index)
+        })),
+        _ => None,
+    }
+}
+
+fn play_night(
+    users: &mut [Player],
+    events: &mut Vec<GameEvent>,
+    knowledge: &mut VillageKnowledge,
+    round: usize,
+    rng: &mut SimRng,
+) {
+    let mut last_role = None;
+
+    while let Some(role_info) = WerewolfRuleset.next_night_role(last_role, users) {
+        last_role = Some(role_info);
+
+        let Role::Werewolf(role) = role_info.get_role() else {
+            continue;
+        };
+
+        // the witch step gives both potions
+        let choices = match role {
+            WerewolfRole::WitchHeal => vec![WerewolfRole::WitchHeal, WerewolfRole::WitchPoison],
+            _ => vec![role],
+        };
+
+        for role in choices {
+            if let Some(target) = choose_night_target(role, users, events, knowledge, round, rng) {
+                let actors = role_actors(users, role_info.get_role());
+                let targets = HashSet::from([users[target].id.clone()]);
+                record_choice(users, events, round, Role::Werewolf(role), &actors, &targets);
+
+                if role == WerewolfRole::Seer {
+                    if has_role(&users[target], WerewolfRole::Werewolf) {
+                        knowledge.suspects.insert(target);
+                    } else {
+                        knowledge.cleared.insert(target);
+                    }
+                }
+            }
+        }
+    }
+
+    resolve_night(users, events, round);
+}
+
+// Everybody votes, the werewolves vote together against the village
+fn choose_day_victim(
+    users: &[Player],
+    knowledge: &VillageKnowledge,
+    rng: &mut SimRng,
+) -> Option<usize> {
+    let mut votes = HashMap::<usize, usize>::new();
+
+    let suspects = alive_indices(users, |index, _| knowledge.suspects.contains(&index));
+    let wolf_target = rng.pick(&alive_indices(users, |_, u| {
+        !has_role(u, WerewolfRole::Werewolf)
+    }));
+
+    for voter in alive_indices(users, |_, _| true) {
+        let vote = if has_role(&users[voter], WerewolfRole::Werewolf) {
+            wolf_target
+        } else if let Some(suspect) = rng.pick(&suspects) {
+            Some(suspect)
+        } else {
+            rng.pick(&alive_indices(users, |index, _| {
+                index != voter && !knowledge.cleared.contains(&index)
+            }))
+        };
+
+        if let Some(vote) = vote {
+            *votes.entry(vote).or_default() += 1;
+        }
+    }
+
+    let max_votes = votes.values().copied().max()?;
+    let mut leaders = votes
+        .into_iter()
+        .filter(|(_, count)| *count == max_votes)
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    leaders.sort();
+
+    rng.pick(&leaders)
+}
+
+// Same transition as the day vote screen
+fn play_day(
+    users: &mut [Player],
+    events: &mut Vec<GameEvent>,
+    knowledge: &VillageKnowledge,
+    round: usize,
+    rng: &mut SimRng,
+) {
+    // the first day is for acquaintance only
+    let victim = if round == 0 {
+        None
+    } else {
+        choose_day_victim(users, knowledge, rng)
+    };
+
+    let selected_users = victim
+        .map(|victim| users[victim].id.clone())
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    apply_day_vote(users, events, round, &selected_users);
+}
+
+// Plays a single game, returns the winner and the number of played days
+pub fn simulate_game(roles: &[Role], players: usize, rng: &mut SimRng) -> (Option<Role>, usize) {
+    let mut users = create_table(roles, players, rng);
+    let mut events = vec![];
+    let mut knowledge = VillageKnowledge::default();
+    let mut round = 0;
+
+    while round < MAX_ROUNDS {
+        play_day(&mut users, &mut events, &knowledge, round, rng);
+        round += 2;
+
+        if let Some(winner) = calculate_winner(&users, &events) {
+            return (Some(winner), round / 2);
+        }
+
+        play_night(&mut users, &mut events, &mut knowledge, round, rng);
+
+        if let Some(winner) = calculate_winner(&users, &events) {
+            return (Some(winner), round / 2);
+        }
+    }
+
+    (None, round / 2)
+}
+
+pub fn simulate(roles: &[Role], players: usize, games: usize, seed: u64) -> SimulationReport {
+    let mut rng = SimRng::new(seed);
+    let mut report = SimulationReport {
+        games,
+        ..Default::default()
+    };
+
+    for _ in 0..games {
+        let (winner, days) = simulate_game(roles, players, &mut rng);
+
+        match winner {
+            Some(winner) => *report.wins.entry(winner).or_default() += 1,
+            None => report.draws += 1,
+        }
+        report.total_days += days;
+    }
+
+    report
+}
+
+pub fn parse_role(name: &str) -> Option<Role> {
+    serde_json::from_value::<WerewolfRole>(serde_json::Value::String(name.trim().to_string()))
+        .ok()
+        .map(Role::Werewolf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    }
+
+    #[test]
+    fn test_simulation_is_reproducible() {
+        let roles = [
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::Werewolf(WerewolfRole::Seer),
+            Role::Werewolf(WerewolfRole::Bodyguard),
+            Role::Werewolf(WerewolfRole::WitchHeal),
+        ];
+
+        let report = simulate(&roles, 10, 200, 7);
+        let wins = report.wins.values().sum::<usize>();
+
+        assert_eq!(wins + report.draws, 200);
+        assert!(report.win_rate(&Role::Werewolf(WerewolfRole::Werewolf)) > 0.0);
+        assert!(report.win_rate(&Role::Werewolf(WerewolfRole::Villager)) > 0.0);
+        assert_eq!(simulate(&roles, 10, 200, 7).wins, report.wins);
+    }
+
+    // Balance report for a custom setup, see the module comment
+    #[test]
+    #[ignore]
+    fn simulate_werewolf_setup() {
+        let roles = std::env::var("SIM_ROLES")
+            .unwrap_or("Werewolf,Werewolf,Seer,Bodyguard".to_string())
+            .split(',')
+            .map(|name| parse_role(name).unwrap_or_else(|| panic!("unknown role {}", name)))
+            .collect::<Vec<_>>();
+        let players = env_or("SIM_PLAYERS", 10);
+        let games = env_or("SIM_GAMES", 10_000);
+        let seed = env_or("SIM_SEED", 1);
+
+        println!("{}", simulate(&roles, players, games, seed).format());
+    }
+}
@@ -0,0 +1,982 @@
+use leptos::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+use crate::effects::*;
+use crate::events::*;
+use crate::roles::*;
+use crate::ruleset::*;
+use crate::user::*;
+use crate::MafiaRole;
+use crate::OneNightRole;
+use crate::WerewolfRole;
+
+// Migration from version N to N + 1, index in the migrations list is the source version
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadError {
+    Missing,
+    Corrupted(String),
+    Unmigratable(u32),
+}
+
+#[derive(Serialize, Deserialize)]
+struct StorageEnvelope {
+    version: u32,
+    data: Value,
+}
+
+pub struct VersionedStorage {
+    pub key: &'static str,
+    pub migrations: &'static [Migration],
+}
+
+impl VersionedStorage {
+    pub fn version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    pub fn backup_key(&self) -> String {
+        format!("{}_backup", self.key)
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<String, String> {
+        let data = serde_json::to_value(value).map_err(|err| err.to_string())?;
+
+        serde_json::to_string(&StorageEnvelope {
+            version: self.version(),
+            data,
+        })
+        .map_err(|err| err.to_string())
+    }
+
+    // Values stored before versioning are plain json without envelope, they are treated as version 0
+    pub fn decode_value(&self, json: &str) -> Result<Value, LoadError> {
+        let value = serde_json::from_str::<Value>(json)
+            .map_err(|err| LoadError::Corrupted(err.to_string()))?;
+
+        let (mut version, mut data) = match serde_json::from_value::<StorageEnvelope>(value.clone()) {
+            Ok(envelope) => (envelope.version, envelope.data),
+            Err(_) => (0, value),
+        };
+
+        if version > self.version() {
+            return Err(LoadError::Unmigratable(version));
+        }
+
+        while version < self.version() {
+            let migration = self.migrations[version as usize];
+            data = migration(data).map_err(|_| LoadError::Unmigratable(version))?;
+            version += 1;
+        }
+
+        Ok(data)
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, json: &str) -> Result<T, LoadError> {
+        let data = self.decode_value(json)?;
+
+        serde_json::from_value::<T>(data).map_err(|err| LoadError::Corrupted(err.to_string()))
+    }
+
+    pub fn load_raw(&self) -> Option<String> {
+        window()
+            .local_storage()
+            .ok()
+            .flatten()
+            .and_then(|storage| storage.get_item(self.key).ok().flatten())
+    }
+
+    pub fn load<T: DeserializeOwned>(&self) -> Result<T, LoadError> {
+        let json = self.load_raw().ok_or(LoadError::Missing)?;
+
+        self.decode(&json)
+    }
+
+    // Load the value, broken values are kept in the backup key and passed to recover
+    pub fn load_or_recover<T: DeserializeOwned>(
+        &self,
+        recover: impl Fn(&Value) -> Option<T>,
+    ) -> Option<T> {
+        match self.load::<T>() {
+            Ok(value) => Some(value),
+            Err(LoadError::Missing) => None,
+            Err(err) => {
+                logging::warn!("couldn't load {}: {:?}", self.key, err);
+
+                let raw = self.load_raw().unwrap_or_default();
+                self.store_raw(&self.backup_key(), &raw);
+
+                serde_json::from_str::<Value>(&raw)
+                    .ok()
+                    .map(|value| match serde_json::from_value::<StorageEnvelope>(value.clone()) {
+                        Ok(envelope) => envelope.data,
+                        Err(_) => value,
+                    })
+                    .and_then(|value| recover(&value))
+            }
+        }
+    }
+
+    pub fn store<T: Serialize>(&self, value: &T) {
+        match self.encode(value) {
+            Ok(json) => self.store_raw(self.key, &json),
+            Err(err) => logging::error!("couldn't serialize {}: {}", self.key, err),
+        }
+    }
+
+    fn store_raw(&self, key: &str, json: &str) {
+        if let Ok(Some(storage)) = window().local_storage() {
+            if storage.set_item(key, json).is_err() {
+                logging::error!("error while trying to set {} in localStorage", key);
+            }
+        }
+    }
+}
+
+// v0 -> v1: unversioned value is wrapped into the envelope as is
+pub fn migrate_wrap_unversioned(value: Value) -> Result<Value, String> {
+    Ok(value)
+}
+
+// v1 -> v2: game state is bound to a save slot
+pub fn migrate_game_state_add_save_id(mut value: Value) -> Result<Value, String> {
+    value
+        .as_object_mut()
+        .ok_or("game state is not an object")?
+        .entry("save_id")
+        .or_insert(Value::Null);
+
+    Ok(value)
+}
+
+// v2 -> v3: game state keeps the One Night center and dealt cards
+pub fn migrate_game_state_add_one_night(mut value: Value) -> Result<Value, String> {
+    value
+        .as_object_mut()
+        .ok_or("game state is not an object")?
+        .entry("one_night")
+        .or_insert_with(|| serde_json::json!({ "center": [], "dealt": {} }));
+
+    Ok(value)
+}
+
+// v0 -> v1: every state of a saved game gets the One Night cards
+pub fn migrate_saved_games_add_one_night(mut value: Value) -> Result<Value, String> {
+    for game in value.as_array_mut().ok_or("saved games is not a list")? {
+        let game = game.as_object_mut().ok_or("saved game is not an object")?;
+
+        if let Some(state) = game.get_mut("state") {
+            *state = migrate_game_state_add_one_night(state.take())?;
+        }
+
+        if let Some(Value::Array(history)) = game.get_mut("history") {
+            for state in history.iter_mut() {
+                *state = migrate_game_state_add_one_night(state.take())?;
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+// Attacks and protections of the marks stored before the event log recorded the choices
+fn is_legacy_attack_role(role: &Role) -> bool {
+    matches!(
+        role,
+        Role::Mafia(MafiaRole::Mafia)
+            | Role::Mafia(MafiaRole::Maniac)
+            | Role::Mafia(MafiaRole::Citizen)
+            | Role::Werewolf(WerewolfRole::Werewolf)
+            | Role::Werewolf(WerewolfRole::WitchPoison)
+            | Role::Werewolf(WerewolfRole::Huntress)
+            | Role::Werewolf(WerewolfRole::Revealer)
+            | Role::Werewolf(WerewolfRole::Vampire)
+            | Role::Werewolf(WerewolfRole::Villager)
+            | Role::OneNight(OneNightRole::Villager)
+    )
+}
+
+fn is_legacy_protect_role(role: &Role) -> bool {
+    matches!(
+        role,
+        Role::Mafia(MafiaRole::Doctor)
+            | Role::Mafia(MafiaRole::Prostitute)
+            | Role::Werewolf(WerewolfRole::Bodyguard)
+            | Role::Werewolf(WerewolfRole::WitchHeal)
+            | Role::Werewolf(WerewolfRole::Priest)
+    )
+}
+
+// Events guessed from the roles the moderator marked on the player during the phase, the old
+// saves kept neither the actors nor the order of the choices
+fn legacy_events(
+    roles: &[RoleInfo],
+    users: &[Player],
+    target: &Player,
+    marked: &HashSet<Role>,
+    phase: usize,
+) -> Vec<GameEvent> {
+    // night order of the ruleset, unknown roles go last
+    let mut actor_roles = marked
+        .iter()
+        .copied()
+        .filter(|role| *role != Role::WasKilled)
+        .collect::<Vec<_>>();
+    actor_roles.sort_by_key(|role| roles.iter().position(|r| r.get_role() == *role).unwrap_or(usize::MAX));
+
+    let is_night = is_night_phase(phase);
+    let died = marked.contains(&Role::WasKilled);
+    let attacked = actor_roles.iter().any(is_legacy_attack_role);
+    let target_id = target.id.clone();
+
+    let mut events = actor_roles
+        .iter()
+        .map(|role| {
+            if !is_night && is_legacy_attack_role(role) {
+                return GameEvent::Lynched {
+                    phase,
+                    target: target_id.clone(),
+                    by: *role,
+                };
+            }
+
+            // the actor is known when a single player holds the role
+            let holders = users.iter().filter(|u| u.role.contains(role)).collect::<Vec<_>>();
+            let actors = match holders[..] {
+                [holder] => vec![holder.id.clone()],
+                _ => vec![],
+            };
+
+            GameEvent::Targeted {
+                phase,
+                actor_role: *role,
+                actors,
+                target: target_id.clone(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if is_night && attacked && !died {
+        events.extend(actor_roles.iter().filter(|role| is_legacy_protect_role(role)).map(|role| {
+            GameEvent::Protected {
+                phase,
+                actor_role: *role,
+                target: target_id.clone(),
+            }
+        }));
+    }
+
+    if died {
+        let cause = actor_roles
+            .iter()
+            .find(|role| is_legacy_attack_role(role))
+            .or_else(|| actor_roles.iter().find(|role| !is_legacy_protect_role(role)))
+            .copied();
+
+        events.push(GameEvent::Killed {
+            phase,
+            target: target_id,
+            cause,
+        });
+    }
+
+    events
+}
+
+// Only the seat and the roles are read, the stored player is older than the current struct
+fn legacy_player(user: &serde_json::Map<String, Value>) -> Result<Player, String> {
+    let id = user.get("id").and_then(Value::as_str).ok_or("player has no id")?;
+    let role = user.get("role").cloned().unwrap_or(Value::Array(vec![]));
+
+    let mut player = Player::new_guest(id.to_string(), "".to_string());
+    player.role = serde_json::from_value::<HashSet<Role>>(role).map_err(|e| e.to_string())?;
+
+    Ok(player)
+}
+
+// v3 -> v4: the marks kept on every player become the event log of the game
+pub fn migrate_game_state_history_to_events(mut value: Value) -> Result<Value, String> {
+    let roles = value["game_state"][0]
+        .as_str()
+        .and_then(ruleset_by_name)
+        .map_or(&[][..], |ruleset| ruleset.roles());
+
+    let game = value.as_object_mut().ok_or("game state is not an object")?;
+    let users = game.get_mut("users").and_then(Value::as_array_mut).ok_or("players are not a list")?;
+
+    let mut histories = Vec::new();
+    let mut players = Vec::new();
+    for user in users.iter_mut() {
+        let user = user.as_object_mut().ok_or("player is not an object")?;
+        let history = user.remove("history_by").unwrap_or(Value::Array(vec![]));
+        players.push(legacy_player(user)?);
+
+        histories.push(
+            serde_json::from_value::<Vec<(usize, HashSet<Role>)>>(history).map_err(|e| e.to_string())?,
+        );
+    }
+
+    let mut events = players
+        .iter()
+        .zip(histories)
+        .flat_map(|(player, history)| {
+            history
+                .into_iter()
+                .flat_map(|(round, marked)| legacy_events(roles, &players, player, &marked, round))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    events.sort_by_key(GameEvent::phase);
+
+    game.insert("events".to_string(), serde_json::to_value(events).map_err(|e| e.to_string())?);
+
+    Ok(value)
+}
+
+// v1 -> v2: every state of a saved game gets the event log
+pub fn migrate_saved_games_history_to_events(mut value: Value) -> Result<Value, String> {
+    for game in value.as_array_mut().ok_or("saved games is not a list")? {
+        let game = game.as_object_mut().ok_or("saved game is not an object")?;
+
+        if let Some(state) = game.get_mut("state") {
+            *state = migrate_game_state_history_to_events(state.take())?;
+        }
+
+        if let Some(Value::Array(history)) = game.get_mut("history") {
+            for state in history.iter_mut() {
+                *state = migrate_game_state_history_to_events(state.take())?;
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+// v0 -> v1: finished games get the event log, the archive is a list and an exported file is one game
+pub fn migrate_finished_games_history_to_events(value: Value) -> Result<Value, String> {
+    match value {
+        Value::Array(games) => games
+            .into_iter()
+            .map(migrate_game_state_history_to_events)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        game => migrate_game_state_history_to_events(game),
+    }
+}
+
+// v4 -> v5: blessings, silences and extra lives kept as additional roles become status effects
+pub fn migrate_game_state_add_effects(mut value: Value) -> Result<Value, String> {
+    let round = value["round"].as_u64().unwrap_or(0) as usize;
+    let is_day = value["game_state"][1].as_str() == Some("Day");
+    let users = value
+        .get_mut("users")
+        .and_then(Value::as_array_mut)
+        .ok_or("players are not a list")?;
+
+    let state_roles = [
+        (Role::Werewolf(WerewolfRole::Priest), StatusKind::Blessed, Expiry::Triggered),
+        (Role::Werewolf(WerewolfRole::Spellcaster), StatusKind::Silenced, Expiry::EndOfDay),
+        (Role::Werewolf(WerewolfRole::ToughGuy), StatusKind::Protected, Expiry::Triggered),
+    ];
+
+    for user in users.iter_mut() {
+        let user = user.as_object_mut().ok_or("player is not an object")?;
+        let field = |name: &str| user.get(name).cloned().unwrap_or(Value::Array(vec![]));
+
+        let mut additional_roles =
+            serde_json::from_value::<HashSet<Role>>(field("additional_role")).map_err(|e| e.to_string())?;
+        let choosed_by = serde_json::from_value::<HashSet<Role>>(field("choosed_by")).map_err(|e| e.to_string())?;
+
+        let mut effects = state_roles
+            .iter()
+            .filter(|(source, _, _)| additional_roles.remove(source))
+            .map(|&(source, kind, expiry)| StatusEffect {
+                kind,
+                source,
+                round,
+                expiry,
+            })
+            .collect::<Vec<_>>();
+
+        // the Prostitute silence was read from the night choices during the day
+        if is_day && choosed_by.contains(&Role::Mafia(MafiaRole::Prostitute)) {
+            effects.push(StatusEffect {
+                kind: StatusKind::Silenced,
+                source: Role::Mafia(MafiaRole::Prostitute),
+                round,
+                expiry: Expiry::EndOfDay,
+            });
+        }
+
+        user.insert(
+            "additional_role".to_string(),
+            serde_json::to_value(additional_roles).map_err(|e| e.to_string())?,
+        );
+        user.insert("effects".to_string(), serde_json::to_value(effects).map_err(|e| e.to_string())?);
+    }
+
+    Ok(value)
+}
+
+// v2 -> v3: every state of a saved game gets the status effects
+pub fn migrate_saved_games_add_effects(mut value: Value) -> Result<Value, String> {
+    for game in value.as_array_mut().ok_or("saved games is not a list")? {
+        let game = game.as_object_mut().ok_or("saved game is not an object")?;
+
+        if let Some(state) = game.get_mut("state") {
+            *state = migrate_game_state_add_effects(state.take())?;
+        }
+
+        if let Some(Value::Array(history)) = game.get_mut("history") {
+            for state in history.iter_mut() {
+                *state = migrate_game_state_add_effects(state.take())?;
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+// v1 -> v2: the players of finished games get the status effects, the same way as the game state
+pub fn migrate_finished_games_add_effects(value: Value) -> Result<Value, String> {
+    match value {
+        Value::Array(games) => games
+            .into_iter()
+            .map(migrate_game_state_add_effects)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        game => migrate_game_state_add_effects(game),
+    }
+}
+
+// v5 -> v6: the choices of the unfinished phase kept on every player go to the event log and
+// the Targeted events list all the actors. A finished game has no round, its unfinished phase
+// follows the last logged one.
+// The revenge of a dying Brutal player was marked with a Brutal role, it becomes the Revenge event
+// of the player who has already used the attribute.
+fn migrate_choices_to_events(mut value: Value, is_finished: bool) -> Result<Value, String> {
+    let roles = value["game_state"][0]
+        .as_str()
+        .and_then(ruleset_by_name)
+        .map_or(&[][..], |ruleset| ruleset.roles());
+    let round = value["round"].as_u64().unwrap_or(0) as usize;
+    let brutal_mark = serde_json::json!({ "Werewolf": "Brutal" });
+    let brutal = value["users"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|user| {
+            user["used_attributes"]
+                .as_array()
+                .is_some_and(|used| used.contains(&Value::from("Brutal")))
+        })
+        .and_then(|user| user["id"].as_str())
+        .map(str::to_string);
+    let revenge = |phase: &Value, target: &Value| {
+        brutal.as_ref().map(|actor| {
+            serde_json::json!({ "Revenge": { "phase": phase, "actor": actor, "target": target } })
+        })
+    };
+
+    let game = value.as_object_mut().ok_or("game state is not an object")?;
+    let mut events = Vec::new();
+    let stored_events = game.remove("events").unwrap_or(Value::Array(vec![]));
+    for mut event in serde_json::from_value::<Vec<Value>>(stored_events).map_err(|e| e.to_string())? {
+        if let Some(targeted) = event.get_mut("Targeted").and_then(Value::as_object_mut) {
+            if let Some(actor) = targeted.remove("actor") {
+                let actors = actor.as_str().map(|actor| vec![Value::from(actor)]).unwrap_or_default();
+                targeted.insert("actors".to_string(), Value::Array(actors));
+            }
+        }
+
+        if let Some(killed) = event.get_mut("Killed").filter(|killed| killed["cause"] == brutal_mark) {
+            killed["cause"] = Value::Null;
+            events.extend(revenge(&killed["phase"], &killed["target"]));
+        }
+
+        events.push(event);
+    }
+    let mut events = serde_json::from_value::<Vec<GameEvent>>(Value::Array(events)).map_err(|e| e.to_string())?;
+    let phase = if is_finished { last_phase(&events) + 1 } else { round };
+
+    let users = game.get_mut("users").and_then(Value::as_array_mut).ok_or("players are not a list")?;
+    let mut choices = Vec::new();
+    let mut players = Vec::new();
+    for user in users.iter_mut() {
+        let user = user.as_object_mut().ok_or("player is not an object")?;
+        let mut marked = user.remove("choosed_by").unwrap_or(Value::Array(vec![]));
+        let changes = user.get("alignment_changes").cloned().unwrap_or(Value::Array(vec![]));
+        let player = legacy_player(user)?;
+
+        if let Some(marked) = marked.as_array_mut() {
+            let count = marked.len();
+            marked.retain(|role| *role != brutal_mark);
+
+            if let Some(event) = revenge(&Value::from(phase), &Value::from(player.id.clone())).filter(|_| marked.len() != count) {
+                events.push(serde_json::from_value::<GameEvent>(event).map_err(|e| e.to_string())?);
+            }
+        }
+        players.push(player);
+
+        choices.push((
+            serde_json::from_value::<HashSet<Role>>(marked).map_err(|e| e.to_string())?,
+            serde_json::from_value::<Vec<AlignmentChange>>(changes).map_err(|e| e.to_string())?,
+        ));
+    }
+
+    for (player, (marked, changes)) in players.iter().zip(choices) {
+        events.extend(legacy_events(roles, &players, player, &marked, phase));
+        events.extend(
+            changes
+                .iter()
+                .filter(|change| change.round == phase)
+                .map(|change| conversion_event(player, change)),
+        );
+    }
+
+    game.insert("events".to_string(), serde_json::to_value(events).map_err(|e| e.to_string())?);
+
+    Ok(value)
+}
+
+pub fn migrate_game_state_choices_to_events(value: Value) -> Result<Value, String> {
+    migrate_choices_to_events(value, false)
+}
+
+// v3 -> v4: every state of a saved game moves the choices to the event log
+pub fn migrate_saved_games_choices_to_events(mut value: Value) -> Result<Value, String> {
+    for game in value.as_array_mut().ok_or("saved games is not a list")? {
+        let game = game.as_object_mut().ok_or("saved game is not an object")?;
+
+        if let Some(state) = game.get_mut("state") {
+            *state = migrate_game_state_choices_to_events(state.take())?;
+        }
+
+        if let Some(Value::Array(history)) = game.get_mut("history") {
+            for state in history.iter_mut() {
+                *state = migrate_game_state_choices_to_events(state.take())?;
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+// v2 -> v3: finished games move the choices of the last phase to the event log
+pub fn migrate_finished_games_choices_to_events(value: Value) -> Result<Value, String> {
+    match value {
+        Value::Array(games) => games
+            .into_iter()
+            .map(|game| migrate_choices_to_events(game, true))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        game => migrate_choices_to_events(game, true),
+    }
+}
+
+pub const GAME_STATE_MIGRATIONS: [Migration; 6] = [
+    migrate_wrap_unversioned,
+    migrate_game_state_add_save_id,
+    migrate_game_state_add_one_night,
+    migrate_game_state_history_to_events,
+    migrate_game_state_add_effects,
+    migrate_game_state_choices_to_events,
+];
+
+pub const SAVED_GAMES_MIGRATIONS: [Migration; 4] = [
+    migrate_saved_games_add_one_night,
+    migrate_saved_games_history_to_events,
+    migrate_saved_games_add_effects,
+    migrate_saved_games_choices_to_events,
+];
+
+pub const FINISHED_GAMES_MIGRATIONS: [Migration; 3] = [
+    migrate_finished_games_history_to_events,
+    migrate_finished_games_add_effects,
+    migrate_finished_games_choices_to_events,
+];
+
+pub const ACTIVE_ROLES_MIGRATIONS: [Migration; 1] = [migrate_wrap_unversioned];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::one_night::*;
+    use crate::replay::*;
+    use crate::saves::*;
+    use crate::GameContextHistory;
+    use crate::GameState;
+    use crate::MafiaRole;
+
+    const GAME_STATE_STORAGE: VersionedStorage = VersionedStorage {
+        key: "test_state",
+        migrations: &GAME_STATE_MIGRATIONS,
+    };
+
+    fn create_history_for_test() -> GameContextHistory {
+        GameContextHistory {
+            users: vec![
+                Player::new_player("001".to_string(), "User1".to_string(), "".to_string()),
+                Player::new_guest("002".to_string(), "User2".to_string()),
+            ],
+            round: 2,
+            game_state: GameState::SetupNames,
+            save_id: Some("slot".to_string()),
+            one_night: OneNightCards::default(),
+            events: vec![],
+        }
+    }
+
+    #[test]
+    fn test_decode_unversioned_state() {
+        let history = create_history_for_test();
+        let mut value = serde_json::to_value(&history).unwrap();
+        value.as_object_mut().unwrap().remove("save_id");
+        let json = value.to_string();
+
+        let decoded = GAME_STATE_STORAGE.decode::<GameContextHistory>(&json).unwrap();
+
+        assert_eq!(decoded.users, history.users);
+        assert_eq!(decoded.round, 2);
+    }
+
+    #[test]
+    fn test_decode_state_without_save_id() {
+        let history = create_history_for_test();
+        let mut value = serde_json::to_value(&history).unwrap();
+        value.as_object_mut().unwrap().remove("save_id");
+        let json = format!("{{\"version\":1,\"data\":{}}}", value);
+
+        let decoded = GAME_STATE_STORAGE.decode::<GameContextHistory>(&json).unwrap();
+
+        assert_eq!(decoded.users, history.users);
+        assert_eq!(decoded.save_id, None);
+    }
+
+    #[test]
+    fn test_decode_state_without_one_night() {
+        let history = create_history_for_test();
+        let mut value = serde_json::to_value(&history).unwrap();
+        value.as_object_mut().unwrap().remove("one_night");
+        let json = format!("{{\"version\":2,\"data\":{}}}", value);
+
+        let decoded = GAME_STATE_STORAGE.decode::<GameContextHistory>(&json).unwrap();
+
+        assert_eq!(decoded.users, history.users);
+        assert_eq!(decoded.one_night, OneNightCards::default());
+    }
+
+    #[test]
+    fn test_migrate_saved_games_add_one_night() {
+        let mut state = serde_json::to_value(create_history_for_test()).unwrap();
+        state.as_object_mut().unwrap().remove("one_night");
+        let games = serde_json::json!([{ "id": "slot", "state": state, "history": [state] }]);
+
+        let migrated = migrate_saved_games_add_one_night(games).unwrap();
+
+        assert!(migrated[0]["state"]["one_night"]["center"].is_array());
+        assert!(migrated[0]["history"][0]["one_night"]["dealt"].is_object());
+    }
+
+    #[test]
+    fn test_migrate_history_to_events() {
+        let mut state = serde_json::to_value(create_history_for_test()).unwrap();
+        state["game_state"] = serde_json::json!(["Mafia", "Day", "None"]);
+        state.as_object_mut().unwrap().remove("events");
+        state["users"][0]["history_by"] = serde_json::json!([[1, [{ "Mafia": "Citizen" }, "WasKilled"]]]);
+        state["users"][1]["history_by"] = serde_json::json!([[0, [{ "Mafia": "Detective" }]]]);
+        let json = format!("{{\"version\":3,\"data\":{}}}", state);
+
+        let decoded = GAME_STATE_STORAGE.decode::<GameContextHistory>(&json).unwrap();
+
+        assert_eq!(
+            decoded.events,
+            vec![
+                GameEvent::Targeted {
+                    phase: 0,
+                    actor_role: Role::Mafia(MafiaRole::Detective),
+                    actors: vec![],
+                    target: "002".to_string(),
+                },
+                GameEvent::Lynched {
+                    phase: 1,
+                    target: "001".to_string(),
+                    by: Role::Mafia(MafiaRole::Citizen),
+                },
+                GameEvent::Killed {
+                    phase: 1,
+                    target: "001".to_string(),
+                    cause: Some(Role::Mafia(MafiaRole::Citizen)),
+                },
+            ]
+        );
+
+        let game = migrate_finished_games_history_to_events(state).unwrap();
+        assert!(game["users"][0].get("history_by").is_none());
+        assert_eq!(game["events"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_migrate_additional_roles_to_effects() {
+        let mut state = serde_json::to_value(create_history_for_test()).unwrap();
+        state["game_state"] = serde_json::json!(["Werewolf", "Day", "None"]);
+        state["users"][0]["additional_role"] =
+            serde_json::json!([{ "Werewolf": "Priest" }, { "Werewolf": "Mayor" }]);
+        state["users"][1]["additional_role"] = serde_json::json!([{ "Werewolf": "Spellcaster" }]);
+        state["users"][0].as_object_mut().unwrap().remove("effects");
+        let json = format!("{{\"version\":4,\"data\":{}}}", state);
+
+        let decoded = GAME_STATE_STORAGE.decode::<GameContextHistory>(&json).unwrap();
+
+        assert_eq!(
+            decoded.users[0].additional_role,
+            HashSet::from([Role::Werewolf(WerewolfRole::Mayor)])
+        );
+        assert!(decoded.users[0].has_effect(StatusKind::Blessed));
+        assert!(decoded.users[1].additional_role.is_empty());
+        assert!(!decoded.users[1].can_speak());
+    }
+
+    #[test]
+    fn test_migrate_finished_games_additional_roles_to_effects() {
+        let mut users = serde_json::to_value(create_history_for_test().users).unwrap();
+        users[0]["additional_role"] = serde_json::json!([{ "Werewolf": "ToughGuy" }]);
+        users[0].as_object_mut().unwrap().remove("effects");
+        let game = serde_json::json!({
+            "id": "game",
+            "name": "Пятница",
+            "finished_at": 0.0,
+            "game_state": ["Werewolf", "End", "None"],
+            "users": users,
+        });
+
+        let json = format!("{{\"version\":1,\"data\":[{}]}}", game);
+        let decoded = FINISHED_GAMES_STORAGE.decode::<Vec<FinishedGame>>(&json).unwrap();
+        assert!(decoded[0].users[0].additional_role.is_empty());
+        assert!(decoded[0].users[0].has_effect(StatusKind::Protected));
+
+        // an exported file is one game
+        let json = format!("{{\"version\":1,\"data\":{}}}", game);
+        let decoded = FINISHED_GAME_FILE.decode::<FinishedGame>(&json).unwrap();
+        assert!(decoded.users[0].has_effect(StatusKind::Protected));
+    }
+
+    // State as it was stored before the event log and the status effects
+    fn create_legacy_state_for_test() -> Value {
+        let mut state = serde_json::to_value(create_history_for_test()).unwrap();
+        state["game_state"] = serde_json::json!(["Werewolf", "Day", "None"]);
+        state.as_object_mut().unwrap().remove("events");
+        state["users"][0]["role"] = serde_json::json!([{ "Werewolf": "Werewolf" }]);
+        state["users"][0]["additional_role"] = serde_json::json!([{ "Werewolf": "Priest" }]);
+        state["users"][1]["history_by"] =
+            serde_json::json!([[0, [{ "Werewolf": "Werewolf" }, "WasKilled"]]]);
+
+        for user in state["users"].as_array_mut().unwrap() {
+            user.as_object_mut().unwrap().remove("effects");
+        }
+
+        state
+    }
+
+    fn assert_round_trip<T: Serialize + DeserializeOwned>(storage: &VersionedStorage, decoded: &T) {
+        let json = storage.encode(decoded).unwrap();
+        let again = storage.decode::<T>(&json).unwrap();
+
+        assert_eq!(serde_json::to_value(again).unwrap(), serde_json::to_value(decoded).unwrap());
+    }
+
+    #[test]
+    fn test_migrate_history_to_events_reads_only_seats_and_roles() {
+        let state = serde_json::json!({
+            "game_state": ["Werewolf", "Day", "None"],
+            "users": [
+                { "id": "001", "role": [{ "Werewolf": "Werewolf" }] },
+                { "id": "002", "history_by": [[0, [{ "Werewolf": "Werewolf" }]]] },
+            ],
+        });
+
+        let migrated = migrate_game_state_history_to_events(state).unwrap();
+
+        assert_eq!(migrated["events"][0]["Targeted"]["actors"][0], "001");
+        assert!(migrated["users"][1].get("history_by").is_none());
+    }
+
+    #[test]
+    fn test_migrate_choices_to_events() {
+        let mut state = serde_json::to_value(create_history_for_test()).unwrap();
+        state["game_state"] = serde_json::json!(["Mafia", "Day", "None"]);
+        state["users"][0]["role"] = serde_json::json!([{ "Mafia": "Mafia" }]);
+        state["users"][1]["choosed_by"] = serde_json::json!([{ "Mafia": "Mafia" }]);
+        state["events"] = serde_json::json!([{ "Targeted": {
+            "phase": 0,
+            "actor_role": { "Mafia": "Doctor" },
+            "actor": null,
+            "target": "001",
+        } }]);
+        let json = format!("{{\"version\":5,\"data\":{}}}", state);
+
+        let decoded = GAME_STATE_STORAGE.decode::<GameContextHistory>(&json).unwrap();
+
+        assert_eq!(
+            decoded.events,
+            vec![
+                GameEvent::Targeted {
+                    phase: 0,
+                    actor_role: Role::Mafia(MafiaRole::Doctor),
+                    actors: vec![],
+                    target: "001".to_string(),
+                },
+                GameEvent::Targeted {
+                    phase: 2,
+                    actor_role: Role::Mafia(MafiaRole::Mafia),
+                    actors: vec!["001".to_string()],
+                    target: "002".to_string(),
+                },
+            ]
+        );
+
+        // the choices of a finished game belong to the phase after the last logged one
+        let game = migrate_finished_games_choices_to_events(state).unwrap();
+        assert!(game["users"][1].get("choosed_by").is_none());
+        assert_eq!(game["events"][1]["Lynched"]["phase"], 1);
+    }
+
+    #[test]
+    fn test_migrate_brutal_role_to_revenge() {
+        let mut state = serde_json::to_value(create_history_for_test()).unwrap();
+        state["game_state"] = serde_json::json!(["Werewolf", "Day", "None"]);
+        state["users"][0]["used_attributes"] = serde_json::json!(["Brutal"]);
+        state["users"][1]["choosed_by"] = serde_json::json!([{ "Werewolf": "Brutal" }, "WasKilled"]);
+        state["events"] = serde_json::json!([{ "Killed": {
+            "phase": 1,
+            "target": "002",
+            "cause": { "Werewolf": "Brutal" },
+        } }]);
+
+        let migrated = migrate_game_state_choices_to_events(state).unwrap();
+        let events = serde_json::from_value::<Vec<GameEvent>>(migrated["events"].clone()).unwrap();
+
+        let revenge = |phase| GameEvent::Revenge {
+            phase,
+            actor: "001".to_string(),
+            target: "002".to_string(),
+        };
+        let killed = |phase| GameEvent::Killed {
+            phase,
+            target: "002".to_string(),
+            cause: None,
+        };
+        assert_eq!(events, vec![revenge(1), killed(1), revenge(2), killed(2)]);
+    }
+
+    #[test]
+    fn test_saved_games_round_trip_from_v1() {
+        let state = create_legacy_state_for_test();
+        let games = serde_json::json!([{
+            "id": "slot",
+            "name": "Пятница",
+            "updated_at": 0.0,
+            "state": state,
+            "history": [state],
+        }]);
+        let json = format!("{{\"version\":1,\"data\":{}}}", games);
+
+        let decoded = SAVED_GAMES_STORAGE.decode::<Vec<SavedGame>>(&json).unwrap();
+
+        for state in [&decoded[0].state, &decoded[0].history[0]] {
+            assert_eq!(
+                state.events,
+                vec![
+                    GameEvent::Targeted {
+                        phase: 0,
+                        actor_role: Role::Werewolf(WerewolfRole::Werewolf),
+                        actors: vec!["001".to_string()],
+                        target: "002".to_string(),
+                    },
+                    GameEvent::Killed {
+                        phase: 0,
+                        target: "002".to_string(),
+                        cause: Some(Role::Werewolf(WerewolfRole::Werewolf)),
+                    },
+                ]
+            );
+            assert!(state.users[0].has_effect(StatusKind::Blessed));
+            assert!(state.users[0].additional_role.is_empty());
+        }
+
+        assert_round_trip(&SAVED_GAMES_STORAGE, &decoded);
+    }
+
+    #[test]
+    fn test_saved_games_round_trip_from_v2() {
+        let state = migrate_game_state_history_to_events(create_legacy_state_for_test()).unwrap();
+        let games = serde_json::json!([{
+            "id": "slot",
+            "name": "Пятница",
+            "updated_at": 0.0,
+            "state": state,
+            "history": [],
+        }]);
+        let json = format!("{{\"version\":2,\"data\":{}}}", games);
+
+        let decoded = SAVED_GAMES_STORAGE.decode::<Vec<SavedGame>>(&json).unwrap();
+
+        assert_eq!(decoded[0].state.events.len(), 2);
+        assert!(decoded[0].state.users[0].has_effect(StatusKind::Blessed));
+        assert!(decoded[0].history.is_empty());
+
+        assert_round_trip(&SAVED_GAMES_STORAGE, &decoded);
+    }
+
+    #[test]
+    fn test_finished_games_round_trip_from_v0() {
+        let state = create_legacy_state_for_test();
+        let game = serde_json::json!({
+            "id": "game",
+            "name": "Пятница",
+            "finished_at": 0.0,
+            "game_state": state["game_state"],
+            "users": state["users"],
+        });
+        let json = format!("{{\"version\":0,\"data\":[{}]}}", game);
+
+        let decoded = FINISHED_GAMES_STORAGE.decode::<Vec<FinishedGame>>(&json).unwrap();
+
+        assert_eq!(decoded[0].events.len(), 2);
+        assert!(was_targeted_by(&decoded[0].events, "002", &Role::Werewolf(WerewolfRole::Werewolf)));
+        assert!(decoded[0].users[0].has_effect(StatusKind::Blessed));
+
+        assert_round_trip(&FINISHED_GAMES_STORAGE, &decoded);
+    }
+
+    #[test]
+    fn test_round_trip_current_version() {
+        let history = create_history_for_test();
+        let json = GAME_STATE_STORAGE.encode(&history).unwrap();
+
+        assert!(json.contains(&format!("\"version\":{}", GAME_STATE_STORAGE.version())));
+
+        let decoded = GAME_STATE_STORAGE.decode::<GameContextHistory>(&json).unwrap();
+
+        assert_eq!(decoded.users, history.users);
+        assert_eq!(decoded.round, history.round);
+    }
+
+    #[test]
+    fn test_decode_broken_and_future_state() {
+        assert!(matches!(
+            GAME_STATE_STORAGE.decode::<GameContextHistory>("{\"users\":"),
+            Err(LoadError::Corrupted(_))
+        ));
+
+        let future = format!("{{\"version\":{},\"data\":{{}}}}", GAME_STATE_STORAGE.version() + 1);
+        assert_eq!(
+            GAME_STATE_STORAGE.decode::<GameContextHistory>(&future).err(),
+            Some(LoadError::Unmigratable(GAME_STATE_STORAGE.version() + 1))
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use leptos::*;
+use std::collections::HashMap;
+
+use crate::user::*;
+
+// Players of the table with a signal per seat, a change of one player re-renders only its row
+#[derive(Clone, Copy, Debug)]
+pub struct PlayerStore {
+    // seat order
+    ids: RwSignal<Vec<String>>,
+    players: StoredValue<HashMap<String, RwSignal<Player>>>,
+    // the whole table for the readers of every player, cloned once per change
+    table: Memo<Vec<Player>>,
+    // the player signals live as long as the store, not the screen that added them
+    owner: Option<Owner>,
+}
+
+impl PlayerStore {
+    pub fn new(users: Vec<Player>) -> Self {
+        let ids = create_rw_signal(Vec::<String>::new());
+        let players = store_value(HashMap::<String, RwSignal<Player>>::new());
+        let table = create_memo(move |_| {
+            ids.with(|ids| {
+                ids.iter()
+                    .filter_map(|id| players.with_value(|players| players.get(id).copied()))
+                    .map(|player| player.get())
+                    .collect()
+            })
+        });
+
+        let store = Self {
+            ids,
+            players,
+            table,
+            owner: Owner::current(),
+        };
+
+        store.set(users);
+        store
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.ids.get()
+    }
+
+    pub fn player(&self, id: &str) -> Option<RwSignal<Player>> {
+        self.players.with_value(|players| players.get(id).copied())
+    }
+
+    // Subscribes to every player without cloning them
+    pub fn track(&self) {
+        self.ids.with(|ids| {
+            ids.iter()
+                .filter_map(|id| self.player(id))
+                .for_each(|player| player.track())
+        });
+    }
+
+    // Reads every player by reference, the memos of the game screens don't clone the table
+    pub fn with<T>(&self, f: impl FnOnce(&[Player]) -> T) -> T {
+        self.table.with(|users| f(users))
+    }
+
+    pub fn get(&self) -> Vec<Player> {
+        self.ids.with(|ids| {
+            ids.iter()
+                .filter_map(|id| self.player(id))
+                .map(|player| player.get())
+                .collect()
+        })
+    }
+
+    pub fn get_untracked(&self) -> Vec<Player> {
+        self.ids.with_untracked(|ids| {
+            ids.iter()
+                .filter_map(|id| self.player(id))
+                .map(|player| player.get_untracked())
+                .collect()
+        })
+    }
+
+    // Only the players that really changed notify their rows
+    pub fn set(&self, users: Vec<Player>) {
+        batch(|| {
+            let ids = users.iter().map(|user| user.id.clone()).collect::<Vec<_>>();
+
+            for user in users {
+                match self.player(&user.id) {
+                    Some(player) => {
+                        if player.with_untracked(|p| *p != user) {
+                            player.set(user);
+                        }
+                    }
+                    None => {
+                        let id = user.id.clone();
+                        let player = match self.owner {
+                            Some(owner) => with_owner(owner, || create_rw_signal(user)),
+                            None => create_rw_signal(user),
+                        };
+
+                        self.players.update_value(|players| {
+                            players.insert(id, player);
+                        });
+                    }
+                }
+            }
+
+            // a removed row may still read its player until the list drops it
+            self.players
+                .update_value(|players| players.retain(|id, _| ids.contains(id)));
+
+            if self.ids.with_untracked(|old_ids| *old_ids != ids) {
+                self.ids.set(ids);
+            }
+        });
+    }
+
+    pub fn update(&self, f: impl FnOnce(&mut Vec<Player>)) {
+        let mut users = self.get_untracked();
+        f(&mut users);
+        self.set(users);
+    }
+
+    // Keyed update, the other players are not touched
+    pub fn update_player(&self, id: &str, f: impl FnOnce(&mut Player)) {
+        if let Some(player) = self.player(id) {
+            player.update(f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attributes::RoleAttribute;
+    use crate::roles::Role;
+
+    #[test]
+    fn test_player_store_keeps_unchanged_players() {
+        let store = PlayerStore::new(test_players(&[Role::None; 2]));
+        let first = store.player("001").unwrap();
+
+        store.update(|users| {
+            users[1].is_alive = false;
+            users.push(Player::new_guest("003".to_string(), "User3".to_string()));
+        });
+
+        assert_eq!(store.player("001"), Some(first));
+        assert!(!store.player("002").unwrap().get_untracked().is_alive);
+        assert_eq!(store.ids(), vec!["001", "002", "003"]);
+        assert_eq!(store.with(|users| users.iter().filter(|u| u.is_alive).count()), 2);
+
+        // any field of the player is a change
+        store.update(|users| {
+            users[0].used_attributes.insert(RoleAttribute::Brutal);
+        });
+        assert!(first.get_untracked().used_attributes.contains(&RoleAttribute::Brutal));
+
+        store.update_player("003", |user| user.name = "Ivan".to_string());
+        store.set(store.get_untracked().into_iter().skip(1).collect());
+
+        assert_eq!(store.player("001"), None);
+        assert_eq!(
+            store.get().iter().map(|u| u.name.as_str()).collect::<Vec<_>>(),
+            vec!["User2", "Ivan"]
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use itertools::Itertools;
+use leptos::*;
+use leptos_use::*;
+use std::collections::HashMap;
+
+use crate::events::*;
+use crate::replay::*;
+use crate::roles::*;
+use crate::user::*;
+use crate::GameState;
+
+fn names(users: &[&Player]) -> String {
+    users.iter().map(|user| user.name.as_str()).join(", ")
+}
+
+fn role_label(roles: &[RoleInfo], user: &Player) -> String {
+    let label = user
+        .role
+        .iter()
+        .filter_map(|role| roles.iter().find(|r| r.get_role() == *role))
+        .map(|r| format!("{} {}", r.get_role_icon(), r.get_role_name()))
+        .join(" • ");
+
+    if label.is_empty() {
+        "Мирный".to_string()
+    } else {
+        label
+    }
+}
+
+// Who was who, in the order of the ruleset roles
+fn roles_lines(roles: &[RoleInfo], users: &[Player]) -> Vec<String> {
+    let mut lines = roles
+        .iter()
+        .filter(|r| !matches!(r, RoleInfo::Icon(_)))
+        .filter_map(|r| {
+            let holders = users
+                .iter()
+                .filter(|u| u.role.contains(&r.get_role()) || u.additional_role.contains(&r.get_role()))
+                .collect::<Vec<_>>();
+
+            (!holders.is_empty())
+                .then(|| format!("{} {}: {}", r.get_role_icon(), r.get_role_name(), names(&holders)))
+        })
+        .collect::<Vec<_>>();
+
+    let villagers = users.iter().filter(|u| u.role.is_empty()).collect::<Vec<_>>();
+    if !villagers.is_empty() {
+        lines.push(format!("✋ Мирные: {}", names(&villagers)));
+    }
+
+    lines
+}
+
+// Kills at night and lynches at day, one line per phase
+fn timeline_lines(roles: &[RoleInfo], users: &[Player], events: &[GameEvent]) -> Vec<String> {
+    build_replay(users, events)
+        .into_iter()
+        .filter_map(|phase| {
+            let deaths = phase
+                .entries
+                .iter()
+                .filter(|entry| entry.died)
+                .map(|entry| format!("{} ({})", entry.user.name, role_label(roles, &entry.user)))
+                .collect::<Vec<_>>();
+
+            if deaths.is_empty() {
+                return None;
+            }
+
+            let (icon, verb) = if phase.is_night() {
+                ("🌙", "убит")
+            } else {
+                ("☀️", "казнён")
+            };
+
+            Some(format!("{} {}: ❌ {} {}", icon, phase.title(), verb, deaths.join(", ")))
+        })
+        .collect()
+}
+
+fn best_players_line(game: &FinishedGame) -> Option<String> {
+    let best_players = game
+        .users
+        .iter()
+        .filter(|u| game.best_players.contains(&u.id))
+        .collect::<Vec<_>>();
+
+    (!best_players.is_empty()).then(|| format!("⭐ Лучшие игроки: {}", names(&best_players)))
+}
+
+// Who voted for whom as the best player, in the seat order of the voters
+fn best_player_votes_line(game: &FinishedGame, title: &str, votes: &HashMap<String, String>) -> Option<String> {
+    let name = |id: &str| game.users.iter().find(|u| u.id == id).map(|u| u.name.as_str());
+
+    let votes = game
+        .users
+        .iter()
+        .filter_map(|voter| {
+            let candidate = votes.get(&voter.id)?;
+            Some(format!("{} → {}", voter.name, name(candidate)?))
+        })
+        .collect::<Vec<_>>();
+
+    (!votes.is_empty()).then(|| format!("🗳️ {}: {}", title, votes.join(", ")))
+}
+
+fn best_player_vote_lines(game: &FinishedGame) -> Vec<String> {
+    best_player_votes_line(game, "Голоса", &game.best_player_votes)
+        .into_iter()
+        .chain(best_player_votes_line(game, "Переголосование", &game.best_player_revotes))
+        .collect()
+}
+
+// Text for the club chat, the short form keeps roles and the winner in secret
+pub fn game_summary(game: &FinishedGame, spoiler_free: bool) -> String {
+    let roles = roles_for_game(&game.game_state);
+    let title = match game.game_state {
+        GameState::Game(ruleset, _) => ruleset.title(),
+        GameState::SetupNames => "",
+    };
+
+    let mut lines = vec![format!("🎲 {} — {}", title, game.name)];
+
+    if spoiler_free {
+        let nights = build_replay(&game.users, &game.events)
+            .iter()
+            .filter(|phase| phase.is_night())
+            .count();
+
+        lines.push(format!("Игроки ({}): {}", game.users.len(), names(&game.users.iter().collect::<Vec<_>>())));
+        lines.push(format!("Ночей сыграно: {}", nights));
+        lines.extend(best_players_line(game));
+        lines.extend(best_player_vote_lines(game));
+
+        return lines.join("\n");
+    }
+
+    lines.push("".to_string());
+    lines.push("Роли:".to_string());
+    lines.extend(roles_lines(roles, &game.users));
+
+    let timeline = timeline_lines(roles, &game.users, &game.events);
+    if !timeline.is_empty() {
+        lines.push("".to_string());
+        lines.push("Ход игры:".to_string());
+        lines.extend(timeline);
+    }
+
+    let winner_roles = match game.game_state {
+        GameState::Game(ruleset, _) => ruleset.winner_roles(),
+        GameState::SetupNames => &[],
+    };
+    let winners = winner_roles
+        .iter()
+        .filter(|r| game.winners.contains(&r.get_role()))
+        .map(|r| r.get_role_name())
+        .join(", ");
+
+    lines.push("".to_string());
+    if winners.is_empty() {
+        lines.push("🏆 Победитель не выбран".to_string());
+    } else {
+        lines.push(format!("🏆 Победили: {}", winners));
+    }
+    lines.extend(best_players_line(game));
+    lines.extend(best_player_vote_lines(game));
+
+    lines.join("\n")
+}
+
+#[component]
+pub fn CopySummaryButtons(game: FinishedGame) -> impl IntoView {
+    let UseClipboardReturn { is_supported, copied, copy, .. } = use_clipboard();
+    let spoiler_free = create_rw_signal(false);
+
+    let full = game_summary(&game, false);
+    let short = game_summary(&game, true);
+
+    let copy_full = copy.clone();
+    let copy_short = copy;
+
+    view! {
+        <Show when=move || is_supported.get()>
+            <div class="flex gap-2 w-full items-center">
+                <button
+                    class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                    on:click={
+                        let copy_full = copy_full.clone();
+                        let full = full.clone();
+                        move |_| {
+                            spoiler_free.set(false);
+                            copy_full(&full);
+                        }
+                    }
+                >
+                    {move || if copied.get() && !spoiler_free.get() { "Скопировано" } else { "Копировать итоги" }}
+                </button>
+                <button
+                    class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                    on:click={
+                        let copy_short = copy_short.clone();
+                        let short = short.clone();
+                        move |_| {
+                            spoiler_free.set(true);
+                            copy_short(&short);
+                        }
+                    }
+                >
+                    {move || if copied.get() && spoiler_free.get() { "Скопировано" } else { "Без спойлеров" }}
+                </button>
+            </div>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mafia::*;
+    use crate::ruleset::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_game_summary() {
+        let mut users = vec![
+            Player::new_guest("001".to_string(), "Ivan".to_string()),
+            Player::new_guest("002".to_string(), "Olga".to_string()),
+            Player::new_guest("003".to_string(), "Petr".to_string()),
+        ];
+        users[0].role.insert(Role::Mafia(MafiaRole::Mafia));
+        let events = vec![
+            GameEvent::Targeted {
+                phase: 2,
+                actor_role: Role::Mafia(MafiaRole::Mafia),
+                actors: vec!["001".to_string()],
+                target: "002".to_string(),
+            },
+            GameEvent::Killed {
+                phase: 2,
+                target: "002".to_string(),
+                cause: Some(Role::Mafia(MafiaRole::Mafia)),
+            },
+            GameEvent::Lynched {
+                phase: 3,
+                target: "001".to_string(),
+                by: Role::Mafia(MafiaRole::Citizen),
+            },
+            GameEvent::Killed {
+                phase: 3,
+                target: "001".to_string(),
+                cause: Some(Role::Mafia(MafiaRole::Citizen)),
+            },
+        ];
+
+        let game = FinishedGame {
+            id: "game".to_string(),
+            name: "Пятница".to_string(),
+            finished_at: 0.0,
+            game_state: GameState::Game(&MafiaRuleset, GamePhase::End),
+            users,
+            events,
+            winners: HashSet::from([Role::Mafia(MafiaRole::Citizen)]),
+            best_players: HashSet::from(["003".to_string()]),
+            best_player_votes: HashMap::from([
+                ("001".to_string(), "003".to_string()),
+                ("002".to_string(), "003".to_string()),
+                ("003".to_string(), "002".to_string()),
+            ]),
+            best_player_revotes: HashMap::from([("001".to_string(), "003".to_string())]),
+        };
+
+        let full = game_summary(&game, false);
+        assert!(full.contains("Мирные: Olga, Petr"));
+        assert!(full.contains("🌙 Ночь 1: ❌ убит Olga (Мирный)"));
+        assert!(full.contains("☀️ День 2: ❌ казнён Ivan"));
+        assert!(full.contains("🏆 Победили"));
+        assert!(full.contains("⭐ Лучшие игроки: Petr"));
+
+        let short = game_summary(&game, true);
+        assert!(!short.contains("🔫") && !short.contains("🏆") && !short.contains("Мирные"));
+        assert!(short.contains("Игроки (3): Ivan, Olga, Petr"));
+        assert!(short.contains("Ночей сыграно: 1"));
+        assert!(short.contains("⭐ Лучшие игроки: Petr"));
+        assert!(short.contains("🗳️ Голоса: Ivan → Petr, Olga → Petr, Petr → Olga"));
+        assert!(short.contains("🗳️ Переголосование: Ivan → Petr"));
+    }
+}
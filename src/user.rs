@@ -3,10 +3,13 @@ use std::collections::HashSet;
 use serde::{Deserialize, Serialize};
 use web_sys::js_sys::*;
 
+use crate::attributes::*;
+use crate::effects::*;
+use crate::investigation::*;
 use crate::roles::*;
 use leptos::*;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Player {
     pub id: String,
     pub name: String,
@@ -14,10 +17,50 @@ pub struct Player {
     pub is_guest: bool,
     pub role: HashSet<Role>,
     pub additional_role: HashSet<Role>,
-    pub choosed_by: HashSet<Role>,
-    pub history_by: Vec<(usize, HashSet<Role>)>,
     pub is_alive: bool,
     pub was_killed: bool,
+    #[serde(default)]
+    pub previous_holders: Vec<SeatHolder>,
+    #[serde(default)]
+    pub notes: Vec<PlayerNote>,
+    #[serde(default)]
+    pub alignment_changes: Vec<AlignmentChange>,
+    #[serde(default)]
+    pub check_results: Vec<CheckRecord>,
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
+    #[serde(default)]
+    pub attributes: HashSet<RoleAttribute>,
+    // attributes that act once and have already acted
+    #[serde(default)]
+    pub used_attributes: HashSet<RoleAttribute>,
+}
+
+// Team change during the game, cause is the role that converted the player
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AlignmentChange {
+    pub round: usize,
+    pub from: Alignment,
+    pub to: Alignment,
+    pub cause: Role,
+}
+
+// Moderator note about the player, round is the index of the night or day it was written in
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerNote {
+    pub round: usize,
+    pub text: String,
+    pub created_at: f64,
+}
+
+// Someone who left the seat during the game, the next holder plays from until_round
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeatHolder {
+    pub id: String,
+    pub name: String,
+    pub comment: String,
+    pub is_guest: bool,
+    pub until_round: usize,
 }
 
 impl Player {
@@ -29,10 +72,15 @@ impl Player {
             is_guest,
             role: HashSet::new(),
             additional_role: HashSet::new(),
-            choosed_by: HashSet::new(),
-            history_by: Vec::new(),
             is_alive: true,
             was_killed: false,
+            previous_holders: Vec::new(),
+            notes: Vec::new(),
+            alignment_changes: Vec::new(),
+            check_results: Vec::new(),
+            effects: Vec::new(),
+            attributes: HashSet::new(),
+            used_attributes: HashSet::new(),
         }
     }
 
@@ -47,11 +95,31 @@ impl Player {
     pub fn get_key(&self) -> String {
         self.id.clone()
     }
-}
 
-impl PartialEq for Player {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id && self.role == other.role
+    pub fn alignment(&self) -> Alignment {
+        roles_alignment(&self.role)
+    }
+
+    // Team the player was dealt into, before any conversion
+    pub fn original_alignment(&self) -> Alignment {
+        self.alignment_changes
+            .first()
+            .map_or_else(|| self.alignment(), |change| change.from)
+    }
+
+    // The player joins the team of `role`, the old roles stay for the logs
+    pub fn convert(&mut self, role: Role, round: usize, cause: Role) {
+        let from = self.alignment();
+        self.role.insert(role);
+        self.record_alignment_change(from, round, cause);
+    }
+
+    pub fn record_alignment_change(&mut self, from: Alignment, round: usize, cause: Role) {
+        let to = self.alignment();
+
+        if from != to {
+            self.alignment_changes.push(AlignmentChange { round, from, to, cause });
+        }
     }
 }
 
@@ -61,11 +129,35 @@ pub fn reset_user_roles(users: &mut Vec<Player>) {
         user.is_alive = true;
         user.was_killed = false;
         user.additional_role.clear();
-        user.choosed_by.clear();
-        user.history_by.clear();
+        user.previous_holders.clear();
+        user.notes.clear();
+        user.alignment_changes.clear();
+        user.check_results.clear();
+        user.effects.clear();
+        user.attributes.clear();
+        user.used_attributes.clear();
     }
 }
 
+// Undo restores an older state, the notes written since then are kept.
+// Seats don't move during the game, so the players are matched by position.
+pub fn carry_notes(from: &[Player], to: &mut [Player]) {
+    for (user, current) in to.iter_mut().zip(from) {
+        user.notes = current.notes.clone();
+    }
+}
+
+// Hand the seat over to another player, the role, status and history stay with the seat
+pub fn substitute_player(user: &mut Player, replacement: &UserSheetInfo, first_round: usize) {
+    user.previous_holders.push(SeatHolder {
+        id: std::mem::replace(&mut user.id, replacement.id()),
+        name: std::mem::replace(&mut user.name, replacement.name()),
+        comment: std::mem::replace(&mut user.comment, replacement.comment()),
+        is_guest: std::mem::replace(&mut user.is_guest, replacement.is_guest()),
+        until_round: first_round,
+    });
+}
+
 #[derive(Clone, Debug)]
 pub struct UserSheetInfo {
     id: String,
@@ -160,3 +252,19 @@ impl From<Array> for UserSheetInfo {
         }
     }
 }
+
+// Guests "001", "002", ... with the given roles, Role::None leaves the player without a role
+#[cfg(test)]
+pub fn test_players(roles: &[Role]) -> Vec<Player> {
+    roles
+        .iter()
+        .enumerate()
+        .map(|(index, role)| {
+            let mut user = Player::new_guest(format!("00{}", index + 1), format!("User{}", index + 1));
+            if *role != Role::None {
+                user.role.insert(*role);
+            }
+            user
+        })
+        .collect()
+}
@@ -0,0 +1,225 @@
+use leptos::*;
+
+use crate::components::*;
+use crate::roles::*;
+use crate::ruleset::*;
+use crate::user::*;
+use crate::GameContext;
+use crate::GameContextHistory;
+use crate::GameState;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IssueLevel {
+    // the game can't start until it is fixed
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug)]
+pub struct SetupIssue {
+    pub level: IssueLevel,
+    pub message: String,
+    pub fix: &'static str,
+    // setup step where the issue can be fixed
+    pub phase: Option<GamePhase<'static>>,
+}
+
+impl SetupIssue {
+    pub fn error(message: String, fix: &'static str, phase: Option<GamePhase<'static>>) -> Self {
+        Self {
+            level: IssueLevel::Error,
+            message,
+            fix,
+            phase,
+        }
+    }
+
+    pub fn warning(message: String, fix: &'static str, phase: Option<GamePhase<'static>>) -> Self {
+        Self {
+            level: IssueLevel::Warning,
+            message,
+            fix,
+            phase,
+        }
+    }
+}
+
+pub fn has_errors(issues: &[SetupIssue]) -> bool {
+    issues.iter().any(|issue| issue.level == IssueLevel::Error)
+}
+
+pub fn count_role(users: &[Player], role: Role) -> usize {
+    users
+        .iter()
+        .filter(|u| u.role.contains(&role) || u.additional_role.contains(&role))
+        .count()
+}
+
+pub fn setup_phase(roles: &'static [RoleInfo], role: Role) -> Option<GamePhase<'static>> {
+    roles
+        .iter()
+        .find(|r| r.get_role() == role)
+        .map(GamePhase::SetupRoles)
+}
+
+// Players holding more than one of the given roles
+pub fn players_with_several_roles(
+    roles: &'static [RoleInfo],
+    users: &[Player],
+    checked: impl Fn(&RoleInfo) -> bool,
+    level: IssueLevel,
+) -> Vec<SetupIssue> {
+    users
+        .iter()
+        .filter_map(|user| {
+            let user_roles = roles
+                .iter()
+                .filter(|r| checked(r) && user.role.contains(&r.get_role()))
+                .collect::<Vec<_>>();
+
+            (user_roles.len() > 1).then(|| SetupIssue {
+                level,
+                message: format!(
+                    "{} получил несколько ролей: {}",
+                    user.name,
+                    user_roles
+                        .iter()
+                        .map(|r| r.get_role_name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                fix: "Оставьте игроку одну роль",
+                phase: Some(GamePhase::SetupRoles(user_roles[0])),
+            })
+        })
+        .collect()
+}
+
+// Issues found before the first night, the game continues only without errors
+#[component]
+pub fn SetupCheck(
+    issues: Vec<SetupIssue>,
+    on_continue: impl Fn() + Copy + 'static,
+    on_close: impl Fn() + Copy + 'static,
+) -> impl IntoView {
+    let ruleset = use_ruleset();
+    let can_continue = !has_errors(&issues);
+
+    let onclick_fix = move |phase: GamePhase<'static>| {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
+
+        set_context_history.update(|history| history.push(game_ctx.get_history()));
+        game_ctx.game_state.set(GameState::Game(ruleset, phase));
+        on_close();
+    };
+
+    view! {
+        <div class="absolute -bottom-1 -left-1 -right-1 rounded-2xl p-3 bg-red-100 flex flex-col gap-2 max-h-full overflow-auto">
+            <h2>"Проверьте расстановку"</h2>
+            {issues.into_iter().map(|issue| view! {
+                <div class="flex gap-2 items-center text-sm">
+                    <div class="flex-1">
+                        <div>{if issue.level == IssueLevel::Error { "⛔ " } else { "⚠️ " }}{issue.message}</div>
+                        <div class="text-xs opacity-60">{issue.fix}</div>
+                    </div>
+                    {issue.phase.map(|phase| view! {
+                        <button
+                            class="px-3 py-1 text-xs bg-white rounded-full"
+                            on:click=move |_| onclick_fix(phase)
+                        >
+                            "Исправить"
+                        </button>
+                    })}
+                </div>
+            }).collect::<Vec<_>>()}
+            <div class="flex gap-2 w-full items-center">
+                <button
+                    class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                    on:click=move |_| on_close()
+                >
+                    "Назад"
+                </button>
+                {can_continue.then(|| view! {
+                    <button
+                        class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                        on:click=move |_| on_continue()
+                    >
+                        "Продолжить"
+                    </button>
+                })}
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mafia::*;
+    use crate::one_night::*;
+    use crate::werewolf::*;
+    use std::collections::HashSet;
+
+    fn messages(issues: &[SetupIssue]) -> Vec<String> {
+        issues.iter().map(|issue| issue.message.clone()).collect()
+    }
+
+    #[test]
+    fn test_mafia_setup_without_mafia() {
+        let mut users = test_players(&[
+            Role::Mafia(MafiaRole::Doctor),
+            Role::None,
+            Role::None,
+        ]);
+        users[0].role.insert(Role::Mafia(MafiaRole::Detective));
+
+        let issues = validate_mafia_setup(&users);
+
+        assert!(has_errors(&issues));
+        assert!(messages(&issues).contains(&"В игре нет Мафии".to_string()));
+        assert!(messages(&issues).iter().any(|m| m.starts_with("User1 получил несколько ролей")));
+
+        let users = test_players(&[Role::Mafia(MafiaRole::Mafia), Role::None, Role::None]);
+        assert!(validate_mafia_setup(&users).is_empty());
+    }
+
+    #[test]
+    fn test_werewolf_setup_issues() {
+        let lovers = Role::Werewolf(WerewolfRole::Lovers);
+        let users = test_players(&[lovers, Role::Werewolf(WerewolfRole::Seer)]);
+        let active_roles = HashSet::from([
+            lovers,
+            Role::Werewolf(WerewolfRole::Seer),
+            Role::Werewolf(WerewolfRole::WitchHeal),
+        ]);
+
+        let issues = validate_werewolf_setup(&users, &active_roles);
+        let messages = messages(&issues);
+
+        assert!(messages.contains(&"Нет убивающей стороны".to_string()));
+        assert!(messages.contains(&"Lovers должно быть ровно двое".to_string()));
+        assert!(messages.contains(&"Ролей больше, чем игроков".to_string()));
+        assert!(issues
+            .iter()
+            .any(|issue| issue.level == IssueLevel::Warning && issue.message.contains("Witch")));
+    }
+
+    #[test]
+    fn test_one_night_setup_issues() {
+        let users = test_players(&[
+            Role::OneNight(OneNightRole::Robber),
+            Role::OneNight(OneNightRole::Robber),
+            Role::None,
+        ]);
+        let cards = OneNightCards::default();
+
+        let messages = messages(&validate_one_night_setup(&users, &cards));
+
+        assert!(messages.contains(&"У игрока User3 нет карты".to_string()));
+        assert!(messages.contains(&"В центре не хватает карт".to_string()));
+        assert!(messages.contains(&"Карта Robber выдана нескольким игрокам".to_string()));
+        assert!(messages.contains(&"В колоде нет Werewolf".to_string()));
+    }
+}
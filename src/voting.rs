@@ -0,0 +1,266 @@
+// Counting the day vote voter by voter, the ruleset decides how much every vote weighs
+use leptos::*;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::components::*;
+use crate::ruleset::*;
+use crate::user::*;
+
+// Weighted votes of every candidate, the leaders first
+pub fn tally_votes(
+    users: &[Player],
+    votes: &HashMap<String, String>,
+    weight: impl Fn(&Player) -> u32,
+) -> Vec<(Player, u32)> {
+    let mut tally = HashMap::<&str, u32>::new();
+
+    for (voter, candidate) in votes {
+        let Some(voter) = users.iter().find(|u| u.id == *voter) else {
+            continue;
+        };
+        *tally.entry(candidate.as_str()).or_default() += weight(voter);
+    }
+
+    let mut tally = users
+        .iter()
+        .filter_map(|u| tally.get(u.id.as_str()).map(|count| (u.clone(), *count)))
+        .filter(|(_, count)| *count > 0)
+        .collect::<Vec<_>>();
+    tally.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    tally
+}
+
+// Candidates with the most votes, a tie goes to the pick of the tie breaker if it is among them
+pub fn vote_leaders(
+    users: &[Player],
+    votes: &HashMap<String, String>,
+    weight: impl Fn(&Player) -> u32,
+    breaks_ties: impl Fn(&Player) -> bool,
+) -> HashSet<String> {
+    let tally = tally_votes(users, votes, weight);
+    let Some(max) = tally.first().map(|(_, count)| *count) else {
+        return HashSet::new();
+    };
+
+    let leaders = tally
+        .into_iter()
+        .filter(|(_, count)| *count == max)
+        .map(|(user, _)| user.id)
+        .collect::<HashSet<_>>();
+
+    if leaders.len() > 1 {
+        let tie_break = users
+            .iter()
+            .filter(|u| breaks_ties(u))
+            .find_map(|u| votes.get(&u.id).filter(|candidate| leaders.contains(*candidate)));
+
+        if let Some(candidate) = tie_break {
+            return HashSet::from([candidate.clone()]);
+        }
+    }
+
+    leaders
+}
+
+// The day vote, weights and the tie breaker come from the ruleset
+pub fn day_vote_leaders(ruleset: &dyn Ruleset, users: &[Player], votes: &HashMap<String, String>) -> HashSet<String> {
+    vote_leaders(
+        users,
+        votes,
+        |u| ruleset.vote_weight(u),
+        |u| ruleset.breaks_vote_ties(u),
+    )
+}
+
+// Voters of the day vote with their weights, the ones who can't vote are left out
+pub fn day_voters(ruleset: &dyn Ruleset, users: Vec<Player>) -> Vec<(Player, u32)> {
+    users
+        .into_iter()
+        .map(|u| {
+            let weight = ruleset.vote_weight(&u);
+            (u, weight)
+        })
+        .filter(|(_, weight)| *weight > 0)
+        .collect()
+}
+
+// Every player has one vote for the best player, a tie goes to the re-vote among the leaders.
+// A tie in the re-vote is left to the moderator, only the votes for the leaders count there.
+pub fn best_player_leaders(
+    users: &[Player],
+    votes: &HashMap<String, String>,
+    revotes: &HashMap<String, String>,
+) -> HashSet<String> {
+    let leaders = vote_leaders(users, votes, |_| 1, |_| false);
+    if leaders.len() < 2 {
+        return leaders;
+    }
+
+    let revotes = revotes
+        .iter()
+        .filter(|(_, candidate)| leaders.contains(*candidate))
+        .map(|(voter, candidate)| (voter.clone(), candidate.clone()))
+        .collect::<HashMap<_, _>>();
+
+    match vote_leaders(users, &revotes, |_| 1, |_| false) {
+        revote_leaders if revote_leaders.is_empty() => leaders,
+        revote_leaders => revote_leaders,
+    }
+}
+
+// Voters go around the table, every one picks a candidate or abstains
+#[component]
+pub fn VoteCount(
+    title: &'static str,
+    voters: Vec<(Player, u32)>,
+    // the voter and the candidate
+    is_disabled: impl Fn(&Player, &Player) -> bool + Copy + 'static,
+    on_done: impl Fn(HashMap<String, String>) + Copy + 'static,
+    on_close: impl Fn() + Copy + 'static,
+) -> impl IntoView {
+    let voters = store_value(voters);
+    let voter_index = create_rw_signal(0usize);
+    let votes = create_rw_signal(HashMap::<String, String>::new());
+    let (selected_users, set_selected_users) = create_signal::<HashSet<String>>(HashSet::new());
+
+    let voter = move || voters.with_value(|voters| voters.get(voter_index.get()).cloned());
+    let voter_label = move || {
+        voter()
+            .map(|(voter, weight)| match weight {
+                1 => voter.name,
+                weight => format!("{} (×{})", voter.name, weight),
+            })
+            .unwrap_or_default()
+    };
+
+    let show_voter = move |index: usize| {
+        voter_index.set(index);
+        let vote = voter().and_then(|(voter, _)| votes.get_untracked().get(&voter.id).cloned());
+        set_selected_users.set(vote.into_iter().collect());
+    };
+
+    let onclick_next = move |_| {
+        let Some((voter, _)) = voter() else {
+            return;
+        };
+
+        votes.update(|votes| match selected_users.get_untracked().into_iter().next() {
+            Some(candidate) => {
+                votes.insert(voter.id, candidate);
+            }
+            None => {
+                votes.remove(&voter.id);
+            }
+        });
+
+        let next = voter_index.get_untracked() + 1;
+        if next < voters.with_value(|voters| voters.len()) {
+            show_voter(next);
+        } else {
+            on_done(votes.get_untracked());
+        }
+    };
+
+    let onclick_prev = move |_| match voter_index.get_untracked() {
+        0 => on_close(),
+        index => show_voter(index - 1),
+    };
+
+    view! {
+        <h2>{title}</h2>
+        <div class="text-sm">"Голосует: "{voter_label}</div>
+        <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+            <div class="flex-1"></div>
+            // the list is built again for every voter, the disabled candidates depend on the voter
+            {move || voter().map(|(voter, _)| view! {
+                <div class="flex flex-col gap-1 w-full pb-0.5">
+                    <SelectUsersForVote
+                        selected_users
+                        set_selected_users
+                        is_killed=move |_| false
+                        is_disabled=move |user| is_disabled(&voter, user)
+                        is_highlighted=move |_| false
+                        is_single_select=true
+                    />
+                </div>
+            })}
+        </div>
+        <div class="flex gap-2 w-full items-center">
+            <button
+                class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full flex items-center justify-center"
+                on:click=onclick_prev
+            >
+                "←"
+            </button>
+            <button
+                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                on:click=onclick_next
+            >
+                {move || if selected_users.get().is_empty() { "Воздержался" } else { "Далее" }}
+            </button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roles::Role;
+    use crate::WerewolfRole;
+    use crate::WerewolfRuleset;
+
+    fn votes(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(voter, candidate)| (voter.to_string(), candidate.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_mayor_vote_weighs_double_and_breaks_ties() {
+        let mut users = test_players(&[Role::None; 5]);
+        users[0].additional_role.insert(Role::Werewolf(WerewolfRole::Mayor));
+        users[4].additional_role.insert(Role::Werewolf(WerewolfRole::Pacifist));
+
+        // the Mayor alone outweighs one vote
+        let leaders = day_vote_leaders(&WerewolfRuleset, &users, &votes(&[("001", "003"), ("002", "004")]));
+        assert_eq!(leaders, HashSet::from(["003".to_string()]));
+
+        // two votes against the Mayor's two is a tie the Mayor breaks
+        let all = votes(&[("001", "003"), ("002", "004"), ("003", "004"), ("005", "003")]);
+        let tally = tally_votes(&users, &all, |u| WerewolfRuleset.vote_weight(u));
+        assert_eq!(tally.iter().map(|(u, count)| (u.id.as_str(), *count)).collect::<Vec<_>>(), [("003", 2), ("004", 2)]);
+        assert_eq!(day_vote_leaders(&WerewolfRuleset, &users, &all), HashSet::from(["003".to_string()]));
+
+        // without a Mayor the tie stays for the moderator
+        users[0].additional_role.clear();
+        let leaders = day_vote_leaders(&WerewolfRuleset, &users, &votes(&[("001", "003"), ("002", "004")]));
+        assert_eq!(leaders.len(), 2);
+    }
+
+    #[test]
+    fn test_best_player_tie_goes_to_the_revote() {
+        let mut users = test_players(&[Role::None; 4]);
+        users[0].additional_role.insert(Role::Werewolf(WerewolfRole::Mayor));
+        let no_revote = HashMap::new();
+
+        let all = votes(&[("001", "002"), ("002", "003"), ("003", "002")]);
+        assert_eq!(best_player_leaders(&users, &all, &no_revote), HashSet::from(["002".to_string()]));
+        assert!(best_player_leaders(&users, &HashMap::new(), &no_revote).is_empty());
+
+        // the Mayor has no extra weight after the game, the tie waits for the re-vote
+        let all = votes(&[("001", "002"), ("002", "003"), ("003", "002"), ("004", "003")]);
+        let tie = HashSet::from(["002".to_string(), "003".to_string()]);
+        assert_eq!(best_player_leaders(&users, &all, &no_revote), tie);
+
+        // a vote for someone out of the tie doesn't count in the re-vote
+        let revotes = votes(&[("001", "003"), ("002", "003"), ("003", "002"), ("004", "001")]);
+        assert_eq!(best_player_leaders(&users, &all, &revotes), HashSet::from(["003".to_string()]));
+
+        // the re-vote ends in a tie too, the moderator picks among the leaders
+        let revotes = votes(&[("001", "003"), ("004", "002")]);
+        assert_eq!(best_player_leaders(&users, &all, &revotes), tie);
+    }
+}
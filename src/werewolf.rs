@@ -1,20 +1,25 @@
-use itertools::Itertools;
-use leptos_use::utils::*;
-use leptos_use::*;
 use serde::Deserialize;
 use serde::Serialize;
-use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::hash::Hash;
 
+use crate::attributes::*;
+use crate::components::*;
+use crate::effects::*;
+use crate::events::*;
+use crate::invariants::*;
+use crate::investigation::*;
+use crate::narration::*;
 use crate::roles::*;
-use crate::rust_create_new_game_log;
+use crate::ruleset::*;
+use crate::storage::*;
 use crate::user::*;
+use crate::validation::*;
+use crate::voting::*;
 use crate::GameContext;
 use crate::GameContextHistory;
 use crate::GameState;
-use crate::UserLogs;
 use leptos::*;
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
@@ -45,6 +50,7 @@ pub enum WerewolfRole {
     Diseased,
     Mason,
     Lovers,
+    Cupid,
     Doppelganger,
     Mentalist,
     // AlphaWolf,
@@ -77,7 +83,7 @@ const _WEREWOLF_COLORS: [&str; 13] = [
     "ring-blue-600/50",
 ];
 
-pub const WEREWOLF_ROLES: [RoleInfo; 32] = [
+pub const WEREWOLF_ROLES: [RoleInfo; 34] = [
     RoleInfo::Icon(IconRoleInfo {
         role: Role::WasKilled,
         role_name: "Killed",
@@ -168,18 +174,15 @@ pub const WEREWOLF_ROLES: [RoleInfo; 32] = [
         role_name: "Witch",
         role_name_color: "green-950",
         prepare_description: "Выберите игрока Witch",
-        night_description: "Кого вылечит Witch?",
+        night_description: "Кого вылечит или отравит Witch?",
         targeting_rules: NightTargetingRules::OnlyOne,
     }),
-    RoleInfo::Night(NightRoleInfo {
+    // the poison is given on the Witch step together with the heal
+    RoleInfo::Icon(IconRoleInfo {
         role: Role::Werewolf(WerewolfRole::WitchPoison),
-        check_role: Some(Role::Werewolf(WerewolfRole::WitchHeal)),
-        role_icon: "☠️",
         role_name: "Witch",
         role_name_color: "green-950",
-        prepare_description: "",
-        night_description: "Кого отравит Witch?",
-        targeting_rules: NightTargetingRules::OnlyOne,
+        role_icon: "☠️",
     }),
     RoleInfo::Night(NightRoleInfo {
         role: Role::Werewolf(WerewolfRole::Seer),
@@ -241,6 +244,22 @@ pub const WEREWOLF_ROLES: [RoleInfo; 32] = [
         night_description: "Кого проверит Revealer?",
         targeting_rules: NightTargetingRules::NotTheSame,
     }),
+    RoleInfo::Passive(PassiveRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Cupid),
+        role_name: "Cupid",
+        role_icon: "💘",
+        additional_role: None,
+        role_name_color: "blue-950",
+        prepare_description: "Выберите игрока Cupid",
+    }),
+    RoleInfo::Passive(PassiveRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Doppelganger),
+        role_name: "Doppelganger",
+        role_icon: "🎭",
+        additional_role: None,
+        role_name_color: "blue-950",
+        prepare_description: "Выберите игрока Doppelganger",
+    }),
     RoleInfo::Passive(PassiveRoleInfo {
         role: Role::Werewolf(WerewolfRole::Lovers),
         role_name: "Mason",
@@ -253,7 +272,7 @@ pub const WEREWOLF_ROLES: [RoleInfo; 32] = [
         role: Role::Werewolf(WerewolfRole::ToughGuy),
         role_name: "ToughGuy",
         role_icon: "💛",
-        additional_role: Some(Role::Werewolf(WerewolfRole::ToughGuy)),
+        additional_role: None,
         role_name_color: "blue-950",
         prepare_description: "Выберите игрока ToughGuy",
     }),
@@ -355,24 +374,74 @@ pub const WEREWOLF_ROLES: [RoleInfo; 32] = [
     }),
 ];
 
+// Wake ups of the first night only, played between the setup and the first day
+pub const WEREWOLF_FIRST_NIGHT_ROLES: [RoleInfo; 4] = [
+    RoleInfo::Night(NightRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Cupid),
+        check_role: None,
+        role_name: "Cupid",
+        role_name_color: "blue-950",
+        role_icon: "💘",
+        prepare_description: "",
+        night_description: "Кого Cupid сделает влюблёнными?",
+        targeting_rules: NightTargetingRules::Anyone,
+    }),
+    RoleInfo::Night(NightRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Doppelganger),
+        check_role: None,
+        role_name: "Doppelganger",
+        role_name_color: "blue-950",
+        role_icon: "🎭",
+        prepare_description: "",
+        night_description: "Чью роль Doppelganger получит после смерти игрока?",
+        targeting_rules: NightTargetingRules::Anyone,
+    }),
+    RoleInfo::Night(NightRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Minion),
+        check_role: None,
+        role_name: "Werewolf и Minion",
+        role_name_color: "red-950",
+        role_icon: "🐺",
+        prepare_description: "",
+        night_description: "Оборотни и Minion открывают глаза и узнают друг друга.",
+        targeting_rules: NightTargetingRules::No,
+    }),
+    RoleInfo::Night(NightRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Lovers),
+        check_role: None,
+        role_name: "Mason",
+        role_name_color: "blue-950",
+        role_icon: "❤️",
+        prepare_description: "",
+        night_description: "Mason (Lovers) открывают глаза и узнают друг друга.",
+        targeting_rules: NightTargetingRules::No,
+    }),
+];
+
 #[derive(Clone, Debug, PartialEq)]
-enum WerewolfHint {
+pub enum WerewolfHint {
     Mayor(Player),
     Cursed(Player),
     LostHeart(Player),
-    Spellcaster(Player),
+    // day effects of the player, see `effects.rs`
+    Effect(StatusKind, Player),
     Killed(Player, HashSet<Role>),
-    Seer(Vec<Player>),
+    Suspects(Role, Vec<Player>),
+    // players who open their eyes together on the first night
+    Meeting(Role, Vec<Player>),
+    // players who die if the night ends now
+    Victim(Player),
     //Vampire(Vec<Player>),
 }
 
-#[derive(Clone, Copy, Debug)]
-pub enum WerewolfGameState<'a> {
-    SelectActiveRoles,
-    SetupRoles(&'a RoleInfo),
-    Day,
-    Night(&'a RoleInfo),
-    End,
+// Roles given on the setup screens, the Lovers are picked by Cupid on the first night
+fn is_setup_role(role_info: &RoleInfo, active_roles: &HashSet<Role>) -> bool {
+    let picked_by_cupid = role_info.get_role() == Role::Werewolf(WerewolfRole::Lovers)
+        && active_roles.contains(&Role::Werewolf(WerewolfRole::Cupid));
+
+    active_roles.contains(&role_info.get_role())
+        && !role_info.get_prepare_description().is_empty()
+        && !picked_by_cupid
 }
 
 fn get_next_prepare_role(active_roles: HashSet<Role>, role: Role) -> Option<&'static RoleInfo> {
@@ -384,24 +453,15 @@ fn get_next_prepare_role(active_roles: HashSet<Role>, role: Role) -> Option<&'st
     let next_role = WEREWOLF_ROLES
         .iter()
         .skip(role_index.wrapping_add(1))
-        .find(|r| active_roles.contains(&r.get_role()) && !r.get_prepare_description().is_empty());
-
-    next_role
-}
-
-fn get_next_night_role(role: &RoleInfo) -> Option<&'static RoleInfo> {
-    let role_index = WEREWOLF_ROLES.iter().position(|r| r == role).unwrap();
-
-    // Find next night role
-    let next_role = WEREWOLF_ROLES
-        .iter()
-        .skip(role_index.wrapping_add(1))
-        .find(|r| matches!(r, RoleInfo::Night(_)));
+        .find(|r| is_setup_role(r, &active_roles));
 
     next_role
 }
 
-const ROLES_STORAGE_KEY: &str = "werewolf_active_roles";
+const ROLES_STORAGE: VersionedStorage = VersionedStorage {
+    key: "werewolf_active_roles",
+    migrations: &ACTIVE_ROLES_MIGRATIONS,
+};
 
 #[derive(Clone, Debug)]
 struct WerewolfActiveRoles {
@@ -410,16 +470,15 @@ struct WerewolfActiveRoles {
 
 impl Default for WerewolfActiveRoles {
     fn default() -> Self {
-        let starting_roles = window()
-            .local_storage()
-            .ok()
-            .flatten()
-            .and_then(|storage| {
-                storage
-                    .get_item(ROLES_STORAGE_KEY)
-                    .ok()
-                    .flatten()
-                    .and_then(|value| serde_json::from_str::<HashSet<Role>>(&value).ok())
+        let starting_roles = ROLES_STORAGE
+            .load_or_recover(|value| {
+                // keep the known roles from a broken list
+                value.as_array().map(|roles| {
+                    roles
+                        .iter()
+                        .filter_map(|role| serde_json::from_value::<Role>(role.clone()).ok())
+                        .collect::<HashSet<_>>()
+                })
             })
             .unwrap_or_else(|| {
                 let mut roles = HashSet::new();
@@ -438,380 +497,254 @@ impl Default for WerewolfActiveRoles {
     }
 }
 
-#[derive(Debug, Clone)]
-struct OpenFinishGameDialogue(bool);
+const WEREWOLF_WINNER_ROLES: [RoleInfo; 5] = [
+    RoleInfo::Icon(IconRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Tanner),
+        role_name: "Таннер",
+        role_name_color: "gray-950",
+        role_icon: "🧵",
+    }),
+    RoleInfo::Icon(IconRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Mason),
+        role_name: "Масоны (Любовники)",
+        role_name_color: "blue-950",
+        role_icon: "❤️",
+    }),
+    RoleInfo::Icon(IconRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Vampire),
+        role_name: "Вампиры",
+        role_name_color: "purple-950",
+        role_icon: "🩸",
+    }),
+    RoleInfo::Icon(IconRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Werewolf),
+        role_name: "Оборотни",
+        role_name_color: "red-950",
+        role_icon: "🔫",
+    }),
+    RoleInfo::Icon(IconRoleInfo {
+        role: Role::Werewolf(WerewolfRole::Villager),
+        role_name: "Жители деревни",
+        role_name_color: "green-950",
+        role_icon: "✋",
+    }),
+];
+
+impl From<WerewolfHint> for HintLine {
+    fn from(hint: WerewolfHint) -> Self {
+        match hint {
+            WerewolfHint::Killed(user, killed_by) => HintLine::Killed(user, killed_by),
+            WerewolfHint::Mayor(user) => HintLine::Player("🎖️", user, "мэр."),
+            WerewolfHint::Effect(kind, user) => HintLine::Player(kind.icon(), user, kind.description()),
+            WerewolfHint::Cursed(user) => {
+                HintLine::Player("😈", user, "станет оборотнем если его съедят.")
+            }
+            WerewolfHint::LostHeart(user) => HintLine::Player("💔", user, "потерял жизнь."),
+            WerewolfHint::Suspects(Role::Werewolf(WerewolfRole::Mentalist), users) => {
+                HintLine::Players("🔍", users, "не из деревни.")
+            }
+            WerewolfHint::Suspects(Role::Werewolf(WerewolfRole::ParanormalInvestigator), users) => {
+                HintLine::Players("🔍", users, "оборотни или их соседи.")
+            }
+            WerewolfHint::Suspects(_, users) => HintLine::Players("🔍", users, "оборотни."),
+            WerewolfHint::Meeting(Role::Werewolf(WerewolfRole::Werewolf), users) => {
+                HintLine::Players("🐺", users, "оборотни.")
+            }
+            WerewolfHint::Meeting(Role::Werewolf(WerewolfRole::Minion), users) => {
+                HintLine::Players("👺", users, "Minion.")
+            }
+            WerewolfHint::Meeting(_, users) => HintLine::Players("❤️", users, "Mason (Lovers)."),
+            WerewolfHint::Victim(user) => HintLine::Player("🩸", user, "погибнет этой ночью."),
+        }
+    }
+}
+
+pub struct WerewolfRuleset;
 
-#[component]
-pub fn WerewolfGameView() -> impl IntoView {
-    let open_finish_game_dialogue = create_rw_signal(OpenFinishGameDialogue(false));
-    let mafia_context = use_context::<GameContext>().expect("MafiaContext not found");
+impl Ruleset for WerewolfRuleset {
+    fn name(&self) -> &'static str {
+        "Werewolf"
+    }
 
-    let (active_werewolf_roles, set_active_werewolf_roles) =
-        create_signal::<WerewolfActiveRoles>(WerewolfActiveRoles::default());
+    fn title(&self) -> &'static str {
+        "Werewolf"
+    }
 
-    provide_context(active_werewolf_roles);
-    provide_context(open_finish_game_dialogue);
+    fn start_label(&self) -> &'static str {
+        "Начать в Werewolf"
+    }
 
-    let game_state_view = move || {
-        if open_finish_game_dialogue.get().0 {
-            return view! {
-                <SelectWinners
-                    on_close=move || open_finish_game_dialogue.set(OpenFinishGameDialogue(false))
-                    on_finish=move || mafia_context.game_state.set(GameState::SetupNames)
-                />
-            }
-            .into_view();
-        }
+    fn roles(&self) -> &'static [RoleInfo] {
+        &WEREWOLF_ROLES
+    }
 
-        match mafia_context.game_state.get() {
-            GameState::Werewolf(game_state) => match game_state {
-                WerewolfGameState::SelectActiveRoles => view! {
-                    <SelectActiveRoles active_werewolf_roles set_active_werewolf_roles />
-                }
-                .into_view(),
+    fn first_night_roles(&self) -> &'static [RoleInfo] {
+        &WEREWOLF_FIRST_NIGHT_ROLES
+    }
 
-                WerewolfGameState::SetupRoles(role) => view! {
-                    <SetupRolesView role={role} />
-                }
-                .into_view(),
+    fn start_phase(&self) -> GamePhase<'static> {
+        GamePhase::SelectActiveRoles
+    }
 
-                WerewolfGameState::Day => view! {
-                    <DayVote />
-                }
-                .into_view(),
-                WerewolfGameState::Night(role) => view! {
-                    <NightTurn role_info={role} />
-                }
-                .into_view(),
-                WerewolfGameState::End => view! {
-                    <div>"Конец игры"</div>
-                }
-                .into_view(),
-            },
-            _ => view! {
-                <div>"Ошибка"</div>
+    fn phase_view(&'static self, phase: GamePhase<'static>) -> View {
+        match phase {
+            GamePhase::SelectActiveRoles => view! {
+                <SelectActiveRoles />
             }
             .into_view(),
-        }
-    };
-
-    view! {
-        <div class="relative flex flex-col gap-4 w-full h-full">
-            <h1 class="text-lg relative w-full text-left">
-                "Werewolf"
-                {move || if open_finish_game_dialogue.get().0 {
-                    view!{
-                        <button
-                            class="absolute text-sm right-0 top-0 px-2 py-1 bg-gray-200 rounded-full"
-                            on:click=move |_| {
-                                open_finish_game_dialogue.set(OpenFinishGameDialogue(false));
-                            }>
-                            "Отмена"
-                        </button>
-                        }
-                    }else{
-                        view!{
-                        <button
-                            class="absolute text-sm right-0 top-0 px-2 py-1 bg-gray-200 rounded-full"
-                            on:click=move |_| {
-                                open_finish_game_dialogue.set(OpenFinishGameDialogue(true));
-                            }>
-                            "Завершить игру"
-                        </button>
-                        }
-                    }
-                }
-            </h1>
-            {game_state_view}
-        </div>
-    }
-}
 
-fn calculate_user_logs(
-    users: Vec<Player>,
-    best_players: HashSet<String>,
-    selected_winners: HashSet<Role>,
-) -> Vec<UserLogs> {
-    let mut logs = Vec::new();
-    let mut last_round = 0;
+            GamePhase::SetupRoles(role) => view! {
+                <SetupRolesView role={role} />
+            }
+            .into_view(),
 
-    // Calculate last round safely
-    for user in users.iter() {
-        for (index, _) in &user.history_by {
-            last_round = last_round.max(*index);
+            GamePhase::Day => view! {
+                <DayVote />
+                <MayorElection />
+                <BrutalRevenge />
+            }
+            .into_view(),
+            GamePhase::Night(role) if role.get_role() == Role::Werewolf(WerewolfRole::WitchHeal) => view! {
+                <WitchTurn role_info={role} />
+                <MayorElection />
+                <BrutalRevenge />
+            }
+            .into_view(),
+            GamePhase::Night(role) => view! {
+                <NightTurn role_info={role} />
+                <MayorElection />
+                <BrutalRevenge />
+            }
+            .into_view(),
+            GamePhase::FirstNight(role) => view! {
+                <FirstNightTurn role_info={role} />
+            }
+            .into_view(),
+            GamePhase::End => view! {
+                <div>"Конец игры"</div>
+            }
+            .into_view(),
         }
     }
 
-    // Prepare user history with fallback for missing data
-    let mut user_history = HashMap::new();
-    for user in users.iter() {
-        let mut rounds = user.history_by.clone();
-        rounds.push((last_round + 1, user.choosed_by.clone()));
+    fn provide_game_context(&self) {
+        let (active_werewolf_roles, set_active_werewolf_roles) =
+            create_signal::<WerewolfActiveRoles>(WerewolfActiveRoles::default());
 
-        user_history.insert(user.id.clone(), rounds);
+        provide_context(active_werewolf_roles);
+        provide_context(set_active_werewolf_roles);
     }
 
-    // Process each user with error resilience
-    let empty_history = vec![(0, HashSet::new())];
-    for user in users.iter() {
-        let mut rounds = vec!["".to_string(); last_round + 1];
-
-        let current_user_history = user_history.get(&user.id).unwrap_or_else(|| &empty_history);
+    fn role_color(&self, user: &Player) -> &'static str {
+        user_background_role_color(user)
+    }
 
-        for (index, roles) in current_user_history {
-            // Safe index calculation
-            let adjusted_index = index.saturating_sub(1).clamp(0, rounds.len() - 1);
+    fn alive_counters(&self) -> &'static [(&'static str, Role)] {
+        &[
+            ("оборотней", Role::Werewolf(WerewolfRole::Werewolf)),
+            ("вампиров", Role::Werewolf(WerewolfRole::Vampire)),
+        ]
+    }
 
-            // Safe role processing with default icon
-            let role_icons = roles
-                .iter()
-                //sort WasKilled role
-                .sorted_by(|a, b| {
-                    if **a == Role::WasKilled {
-                        Ordering::Greater
-                    } else if **b == Role::WasKilled {
-                        Ordering::Less
-                    } else {
-                        Ordering::Equal
-                    }
-                })
-                .map(|role| {
-                    WEREWOLF_ROLES
-                        .iter()
-                        .find(|r| r.get_role() == *role)
-                        .map(|r| r.get_role_icon())
-                        .unwrap_or("❓") // Fallback icon
-                })
-                .collect::<Vec<_>>()
-                .join(" ");
+    fn killer_roles(&self) -> &'static [Role] {
+        &[
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::Werewolf(WerewolfRole::WitchPoison),
+            Role::Werewolf(WerewolfRole::Revealer),
+            Role::Werewolf(WerewolfRole::Huntress),
+        ]
+    }
 
-            rounds[adjusted_index] = role_icons;
+    // The Mayor's vote counts twice, and of the tied candidates the Mayor's pick goes through
+    // (see breaks_vote_ties). The Pacifist's and the banished player's votes don't count.
+    fn vote_weight(&self, user: &Player) -> u32 {
+        if !user.is_alive
+            || user.has_effect(StatusKind::Banished)
+            || user
+                .additional_role
+                .contains(&Role::Werewolf(WerewolfRole::Pacifist))
+        {
+            0
+        } else if is_mayor(user) {
+            2
+        } else {
+            1
         }
+    }
 
-        // Winner calculation without implicit assumptions
-        let winner = user.role.iter().any(|role| selected_winners.contains(role))
-            || (selected_winners.contains(&Role::Werewolf(WerewolfRole::Villager))
-                && user.role.is_empty());
+    fn breaks_vote_ties(&self, user: &Player) -> bool {
+        user.is_alive && is_mayor(user)
+    }
 
-        // Safe role processing with fallbacks
-        let role_str = if user.role.is_empty() {
-            "Мирный".to_string()
-        } else {
-            user.role
-                .iter()
-                .filter_map(|role| {
-                    WEREWOLF_ROLES
-                        .iter()
-                        .find(|r| r.get_role() == *role)
-                        .map(|r| r.get_role_name())
-                })
-                .collect::<Vec<_>>()
-                .join(" • ")
-        };
+    fn apply_day_vote(
+        &self,
+        users: &mut [Player],
+        events: &mut Vec<GameEvent>,
+        round: usize,
+        selected_users: &HashSet<String>,
+    ) {
+        apply_day_vote(users, events, round, selected_users);
+    }
 
-        let role_index = if user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf)) {
-            "Werewolf"
-        } else if user.role.contains(&Role::Werewolf(WerewolfRole::Minion)) {
-            "Werewolf"
-        } else if user.role.contains(&Role::Werewolf(WerewolfRole::Tanner)) {
-            "Tanner"
-        } else if user.role.contains(&Role::Werewolf(WerewolfRole::Vampire)) {
-            "Vampire"
-        } else {
-            "Villager"
-        }
-        .to_string();
-
-        let role_score = if winner { 1 } else { 0 };
-
-        let best_player = best_players.contains(&user.id);
-
-        logs.push(UserLogs {
-            id: user.id.clone(),
-            name: user.name.clone(),
-            is_guest: user.is_guest,
-            role: role_str,
-            role_index,
-            role_score,
-            best_player,
-            score: 0,
-            winner,
-            rounds,
+    fn kill_at_day_start(
+        &self,
+        users: &mut [Player],
+        events: &mut Vec<GameEvent>,
+        round: usize,
+        selected_users: &HashSet<String>,
+    ) {
+        users.iter_mut().for_each(|u| {
+            if selected_users.contains(&u.id) {
+                day_kill_user(u, events, round);
+            }
         });
+
+        calculate_after_kills(users, events, round + 1);
     }
 
-    logs
-}
+    fn day_hints(&self, users: &[Player], events: &[GameEvent]) -> Vec<HintLine> {
+        day_hints(users, events).into_iter().map(HintLine::from).collect()
+    }
 
-#[component]
-fn SelectWinners(
-    on_close: impl Fn() -> () + Clone + 'static,
-    on_finish: impl Fn() -> () + Clone + 'static,
-) -> impl IntoView {
-    let selected_winners = create_rw_signal(HashSet::<Role>::new());
-    let selected_users = create_rw_signal(HashSet::<String>::new());
-
-    let roles = [
-        RoleInfo::Icon(IconRoleInfo {
-            role: Role::Werewolf(WerewolfRole::Tanner),
-            role_name: "Таннер",
-            role_name_color: "gray-950",
-            role_icon: "🧵",
-        }),
-        RoleInfo::Icon(IconRoleInfo {
-            role: Role::Werewolf(WerewolfRole::Mason),
-            role_name: "Масоны (Любовники)",
-            role_name_color: "blue-950",
-            role_icon: "❤️",
-        }),
-        RoleInfo::Icon(IconRoleInfo {
-            role: Role::Werewolf(WerewolfRole::Vampire),
-            role_name: "Вампиры",
-            role_name_color: "purple-950",
-            role_icon: "🩸",
-        }),
-        RoleInfo::Icon(IconRoleInfo {
-            role: Role::Werewolf(WerewolfRole::Werewolf),
-            role_name: "Оборотни",
-            role_name_color: "red-950",
-            role_icon: "🔫",
-        }),
-        RoleInfo::Icon(IconRoleInfo {
-            role: Role::Werewolf(WerewolfRole::Villager),
-            role_name: "Жители деревни",
-            role_name_color: "green-950",
-            role_icon: "✋",
-        }),
-    ];
-
-    let is_selected = move |role: &Role| selected_winners.get().contains(role);
-
-    let calculate_user_logs = move || {
-        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
-        let users = game_ctx.users.get();
-        let best_players = selected_users.get();
-        let selected_winners = selected_winners.get();
+    fn after_kills(&self, users: &mut [Player], events: &mut Vec<GameEvent>, phase: usize) {
+        calculate_after_kills(users, events, phase);
+    }
 
-        calculate_user_logs(users, best_players, selected_winners)
-    };
+    fn winner_roles(&self) -> &'static [RoleInfo] {
+        &WEREWOLF_WINNER_ROLES
+    }
 
-    let mafia_context = use_context::<GameContext>().expect("MafiaContext not found");
+    fn is_winner(&self, user: &Player, winners: &HashSet<Role>) -> bool {
+        let masons_won = winners.contains(&Role::Werewolf(WerewolfRole::Mason))
+            && user.role.contains(&Role::Werewolf(WerewolfRole::Lovers));
 
-    let users = move || mafia_context.users.get();
+        masons_won
+            || winners
+                .iter()
+                .any(|winner| winner.alignment() == Some(user.alignment()))
+    }
 
-    view! {
-        <div class="flex-1 flex flex-col gap-1 relative overflow-auto px-4 -mx-4">
-            <h2>"⭐ Выберите лучших игроков"</h2>
-            <div class="grid grid-cols-3 gap-1">
-                <For
-                    each=users
-                    key=|user| format!("{}_{}", user.id.clone(), user.role.len())
-                    children=move |user| {
-                        let user_clone = user.clone();
-
-                        view!{
-                            <UserSelectRole
-                                user=user_clone
-                                disabled=false
-                                is_selected=move |u| selected_users.get().contains(&u.id)
-                                highlighted=false
-                                killed=false
-                                on:click=move |_| {
-                                    selected_users.update(|users| {
-                                        if users.contains(&user.id) {
-                                            users.remove(&user.id);
-                                        } else {
-                                            users.insert(user.id.clone());
-                                        }
-                                    });
-                                }
-                            />
-                        }
-                    }
-                />
-            </div>
-        </div>
-        <div class="flex flex-col gap-1 relative px-4 -mx-4">
-            <h2>"🏆 Выберите кто победил"</h2>
-            <button class=move ||
-                format!("rounded-xl px-3 py-2 text-sm {}", if selected_winners.get().is_empty() {
-                    "text-white bg-red-800/80".to_string()
-                } else {
-                    "bg-gray-200".to_string()
-                })
-                on:click=move|_|{
-                    selected_winners.update(|selected_winners|{
-                        selected_winners.clear();
-                    });
-                }
-            >
-                "Не сохранять результаты"
-            </button>
-            <div class="grid grid-cols-3 gap-1 justify-stretch w-full">
-            <div></div>
-            {roles.map(|role| {
-                let role_clone = role.get_role().clone();
-                view!{
-                    <button class=move ||
-                        format!("flex-1 rounded-xl px-3 py-5 text-sm {}", if is_selected(
-                            &role.get_role()
-                        ) {
-                            format!("text-white {}", role.get_role_bg_color())
-                        } else {
-                            "bg-gray-200".to_string()
-                        })
-                        on:click=move|_|{
-                            selected_winners.update(|selected_winners|{
-                                if selected_winners.contains(&role_clone) {
-                                    selected_winners.remove(&role_clone);
-                                }else{
-                                    selected_winners.insert(role_clone);
-                                }
-                            });
-                        }
-                    >
-                        {role.get_role_name()}
-                    </button>
-                }
-            })}
-            </div>
-        </div>
-        <div class="flex gap-2 w-full items-center">
-            <button
-                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
-                on:click={
-                    move |_| on_close()
-                }
-            >
-                "Назад"
-            </button>
-            <button
-                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
-                on:click={
-                    move |_| {
-                        if selected_winners.get().is_empty() {
-                            if window().confirm_with_message("Вернуться в главное меню без победителей?").expect("REASON") {
-                                on_finish();
-                            }
-                        }else{
-                            rust_create_new_game_log(calculate_user_logs(), false);
+    fn role_index(&self, user: &Player) -> &'static str {
+        match user.alignment() {
+            Alignment::Werewolf => "Werewolf",
+            Alignment::Tanner => "Tanner",
+            Alignment::Vampire => "Vampire",
+            _ => "Villager",
+        }
+    }
 
-                            on_finish();
-                        }
-                    }
-                }
-            >
-                "Закончить игру"
-            </button>
-        </div>
+    fn is_mafia_log(&self) -> bool {
+        false
     }
 }
 
 #[component]
-fn SelectActiveRoles(
-    active_werewolf_roles: ReadSignal<WerewolfActiveRoles>,
-    set_active_werewolf_roles: WriteSignal<WerewolfActiveRoles>,
-) -> impl IntoView {
+fn SelectActiveRoles() -> impl IntoView {
+    let active_werewolf_roles =
+        use_context::<ReadSignal<WerewolfActiveRoles>>().expect("WerewolfActiveRoles not found");
+    let set_active_werewolf_roles =
+        use_context::<WriteSignal<WerewolfActiveRoles>>().expect("WerewolfActiveRoles not found");
+
     let roles = WEREWOLF_ROLES.iter().filter(|r| match r {
         RoleInfo::Night(_) => r.get_check_role() == r.get_role(),
         RoleInfo::Passive(_) => true,
@@ -822,13 +755,7 @@ fn SelectActiveRoles(
     let is_selected = move |role: &Role| active_werewolf_roles.get().roles.contains(role);
 
     create_effect(move |_| {
-        if let Ok(Some(storage)) = window().local_storage() {
-            let user_names = &active_werewolf_roles.get().roles;
-            let json = serde_json::to_string(user_names).expect("couldn't serialize Users");
-            if storage.set_item(ROLES_STORAGE_KEY, &json).is_err() {
-                //log::error!("error while trying to set item in localStorage");
-            }
-        }
+        ROLES_STORAGE.store(&active_werewolf_roles.get().roles);
     });
 
     let onclick_next_role = move |_| {
@@ -846,9 +773,7 @@ fn SelectActiveRoles(
         if let Some(role_info) = role_info {
             game_ctx
                 .game_state
-                .set(GameState::Werewolf(WerewolfGameState::SetupRoles(
-                    role_info,
-                )));
+                .set(GameState::Game(&WerewolfRuleset, GamePhase::SetupRoles(role_info)));
         }
     };
 
@@ -859,7 +784,7 @@ fn SelectActiveRoles(
 
         set_context_history.update(|history| {
             if let Some(prev_ctx) = history.pop() {
-                game_ctx.set_history(prev_ctx);
+                game_ctx.undo_to(prev_ctx);
             }
         });
     };
@@ -959,10 +884,9 @@ fn SetupRolesHeader<'a>(role: &'a RoleInfo) -> impl IntoView {
         use_context::<ReadSignal<WerewolfActiveRoles>>().expect("WerewolfActiveRoles exists");
 
     let roles_iter = move || {
-        WEREWOLF_ROLES.iter().filter(move |r| {
-            active_roles.get().roles.contains(&r.get_role())
-                && !r.get_prepare_description().is_empty()
-        })
+        WEREWOLF_ROLES
+            .iter()
+            .filter(move |r| is_setup_role(r, &active_roles.get().roles))
     };
 
     let len = move || roles_iter().count();
@@ -984,19 +908,21 @@ fn SelectUserForRole<'a>(role_info: &'a RoleInfo) -> impl IntoView {
 
     let role = role_info.get_role();
     let role_info = role_info.clone();
-    let users_sorted = move || users_sorted(mafia_context.users.get());
+    let ids_sorted = move || users_sorted(mafia_context.users.ids());
 
     view! {
         <div class="grid grid-cols-2 gap-y-1 gap-x-3">
             <For
-                each=users_sorted
-                key=|user| format!("{}-{}-{}-{}", user.id, user.role.len(), user.additional_role.len(), user.choosed_by.len())
-                children=move |user| {
-                    let user_clone = user.clone();
+                each=ids_sorted
+                key=|id| id.clone()
+                children=move |id| {
+                    let Some(user) = mafia_context.users.player(&id) else {
+                        return ().into_view();
+                    };
 
                     view!{
                         <UserSelectRole
-                            user=user_clone
+                            user
                             disabled=false
                             highlighted=false
                             killed=false
@@ -1007,50 +933,45 @@ fn SelectUserForRole<'a>(role_info: &'a RoleInfo) -> impl IntoView {
                                 }
                             }
                             on:click=move |_| {
-                                mafia_context.users.update(|users| {
-                                    if let Some(user) = users.iter_mut().find(|u| **u == user) {
-                                        match role_info{
-                                            RoleInfo::Additional(_) => {
-                                                if user.additional_role.contains(&role){
-                                                    user.additional_role.remove(&role);
-                                                }else{
-                                                    user.additional_role.insert(role);
-                                                }
+                                mafia_context.users.update_player(&id, |user| {
+                                    match role_info{
+                                        RoleInfo::Additional(_) => {
+                                            if user.additional_role.contains(&role){
+                                                user.additional_role.remove(&role);
+                                            }else{
+                                                user.additional_role.insert(role);
                                             }
-                                            RoleInfo::Night(_) => {
-                                                if user.role.contains(&role){
-                                                    user.role.remove(&role);
-                                                }else{
-                                                    user.role.insert(role);
-                                                }
+                                        }
+                                        RoleInfo::Night(_) => {
+                                            if user.role.contains(&role){
+                                                user.role.remove(&role);
+                                            }else{
+                                                user.role.insert(role);
                                             }
-                                            RoleInfo::Passive(passive_role_info) => {
-                                                if user.role.contains(&role){
-                                                    user.role.remove(&role);
-                                                    if let Some(additional_role) = passive_role_info.additional_role {
-                                                        user.additional_role.remove(&additional_role);
-                                                    }
-                                                }else{
-                                                    user.role.insert(role);
-                                                    if let Some(additional_role) = passive_role_info.additional_role {
-                                                        user.additional_role.insert(additional_role);
-                                                    }
+                                        }
+                                        RoleInfo::Passive(passive_role_info) => {
+                                            if user.role.contains(&role){
+                                                user.role.remove(&role);
+                                                if let Some(additional_role) = passive_role_info.additional_role {
+                                                    user.additional_role.remove(&additional_role);
                                                 }
+                                            }else{
+                                                insert_user_role(user, role);
                                             }
-                                            _ => {}
                                         }
+                                        _ => {}
                                     }
                                 });
                             }
                         />
-                    }
+                    }.into_view()
                 }
             />
         </div>
     }
 }
 
-fn user_background_role_color(user: &Player) -> &str {
+fn user_background_role_color(user: &Player) -> &'static str {
     if user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf)) {
         "bg-red-100"
     } else if user.role.contains(&Role::Werewolf(WerewolfRole::Cursed)) {
@@ -1077,262 +998,63 @@ fn user_background_role_color(user: &Player) -> &str {
 }
 
 #[component]
-fn UserSelectRole(
-    user: Player,
-    is_selected: impl Fn(&Player) -> bool + 'static,
-    disabled: bool,
-    killed: bool,
-    highlighted: bool,
-    #[prop(default = "ring-red-600/50".to_string())] highlight_color: String,
-) -> impl IntoView {
-    let history = user.history_by.clone();
-    let choosed = user.choosed_by.clone();
-    let user_c1 = user.clone();
+fn TurnButtons<'a>(role_info: &'a RoleInfo) -> impl IntoView {
+    let active_werewolf_roles =
+        use_context::<ReadSignal<WerewolfActiveRoles>>().expect("WerewolfActiveRoles not found");
+    let setup_issues = create_rw_signal::<Vec<SetupIssue>>(vec![]);
+    let show_attributes = create_rw_signal(false);
 
-    view! {
-        <button
-            disabled=disabled
-            class=move || {
-                let is_selected = is_selected(&user_c1);
-
-                format!("relative overflow-hidden
-                    flex-1 px-3 py-1 text-sm rounded-2xl 
-                    flex gap-1.5 items-center justify-center 
-                    min-h-8 {} {}", 
-                if killed {
-                    "opacity-20 bg-white hover:opacity-90"
-                } else if disabled {
-                    "opacity-60 bg-gray-100 hover:opacity-90"
-                } else if is_selected {
-                    "bg-blue-300"
-                } else {
-                    user_background_role_color(&user_c1)
-                },
-                if highlighted {
-                    format!("ring-1 {}", highlight_color)
-                } else {
-                    "".to_string()
-                })}
-        >
-            <div class="flex-grow">
-                <div class="text-left">{user.name} <UserAdditionalRoles roles=user.additional_role /></div>
-                <UserRoleNames role=user.role />
-            </div>
-            <UserHistory hystory=history current=choosed />
-            {move || if user.was_killed && !user.is_alive {
-                view! {
-                    <div class="text-[0.5rem]">"❌"</div>
-                }.into_view()
-            }else if user.was_killed && user.is_alive {
-                view! {
-                    <div class="relative text-[0.5rem]">"💛"
-                        <div class="absolute text-[0.3rem] left-[0.15rem] top-[0.04rem]">"❌"</div>
-                    </div>
-                }.into_view()
-            }else{
-                "".into_view()
-            }}
-        </button>
-    }
-}
+    let role = role_info.get_role();
 
-#[component]
-fn UserRoleNames(role: HashSet<Role>) -> impl IntoView {
-    view! {
-        <div class="flex flex-wrap gap-x-1 items-start justify-start">
-            {move || {
-                if role.is_empty() {
-                    view!{
-                        <UserRoleName role=Role::Werewolf(WerewolfRole::None) />
-                    }.into_view()
-                }else{
-                    role.iter().map(|role| {
-                        let role = *role;
-
-                        view!{
-                            <UserRoleName role=role />
-                        }
-                    }).intersperse_with(|| {
-                        // Add separator between the roles
-                        view! { <Separator /> } // Assuming there's a Separator component; alternatively, use raw HTML
-                    })
-                    .collect::<Vec<_>>().into_view()
-                }
-            }}
-        </div>
-    }
-}
+    let start_game = move || {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+            .expect("MafiaContext history not found");
 
-#[component]
-fn UserKilledBy(killed_by: HashSet<Role>) -> impl IntoView {
-    view! {
-        {move || {
-            let killed_by = killed_by.clone().into_iter().filter(|role| {
-                // werewolf or witch or revealer or hunteress
-                matches!(
-                    role,
-                    Role::Werewolf(WerewolfRole::Werewolf)
-                        | Role::Werewolf(WerewolfRole::WitchPoison)
-                        | Role::Werewolf(WerewolfRole::Revealer)
-                        | Role::Werewolf(WerewolfRole::Huntress)
-                )
-            }).collect::<Vec<_>>();
-
-            if killed_by.is_empty() {
-                view!{
-                    ""
-                }.into_view()
-            }else{
-                view!{
-                    " (by "
-                    {move || killed_by.iter().map(|role| {
-                        let role = *role;
-
-                        view!{
-                            <UserRoleName role=role />
-                        }.into_view()
-                    }).intersperse_with(|| {
-                        // Add separator between the roles
-                        view! { ", " }.into_view() // Assuming there's a Separator component; alternatively, use raw HTML
-                    })
-                    .collect::<Vec<_>>().into_view()}
-                    ")"
-                }.into_view()
-            }
-        }}
-    }
-}
+        set_context_history.update(|history| history.push(game_ctx.get_history()));
 
-#[component]
-fn Separator() -> impl IntoView {
-    view! {
-        <div class="text-xs opacity-50">"•"</div>
-    }
-}
+        game_ctx.users.update(|users| {
+            initialize_user_roles(users);
+        });
+        game_ctx.round.set(0);
 
-#[component]
-fn UserHistory(hystory: Vec<(usize, HashSet<Role>)>, current: HashSet<Role>) -> impl IntoView {
-    view! {
-        <div class="flex gap-0.5 flex-wrap min-h-4">
-            {
-                current.iter().map(|role| {
-                    let role = *role;
-
-                    if role != Role::WasKilled{
-                        view!{
-                            <UserRoleIcon role=role is_history=UserRoleIconType::Current />
-                        }
-                    }else{
-                        "".into_view()
-                    }
-                }).collect::<Vec<_>>().into_view()
-            }
-        </div>
-    }
-}
-
-enum UserRoleIconType {
-    History,
-    Current,
-    Additional,
-}
-
-#[component]
-fn UserRoleIcon(role: Role, is_history: UserRoleIconType) -> impl IntoView {
-    WEREWOLF_ROLES
-        .iter()
-        .find(|r| r.get_role() == role)
-        .map(|role_info| match is_history {
-            UserRoleIconType::Additional => view! {
-                " "{role_info.get_role_icon()}
-            }
-            .into_view(),
-            _ => view! {
-                <div
-                    class=move || match is_history {
-                        UserRoleIconType::History => "text-xs opacity-80 w-4 h-4",
-                        UserRoleIconType::Current => "text-xs rounded-md bg-white w-4 h-4",
-                        UserRoleIconType::Additional => "text-xs w-4 h-4",
-                    }
-                >
-                    {role_info.get_role_icon()}
-                </div>
-            }
-            .into_view(),
-        })
-}
-
-#[component]
-fn UserRoleName(role: Role) -> impl IntoView {
-    WEREWOLF_ROLES
-        .iter()
-        .find(|r| r.get_role() == role)
-        .map_or_else(
-            move || {
-                view! {
-                    <div
-                        class="text-xs opacity-20"
-                    >
-                        "Не выбрано"
-                    </div>
-                }
-            },
-            move |role_info| {
-                view! {
-                    <div
-                        class=move || format!("text-xs opacity-50 {}", role_info.get_role_name_color())
-                    >
-                        {role_info.get_role_name()}
-                    </div>
-                }
-            },
-        )
-        .into_view()
-}
-
-#[component]
-fn UserAdditionalRoles(roles: HashSet<Role>) -> impl IntoView {
-    view! {
-        {
-            roles.iter().map(|role| {
-                view!{
-                    <UserRoleIcon role=*role is_history=UserRoleIconType::Additional />
-                }
-            }).collect::<Vec<_>>().into_view()
-        }
-    }
-}
-
-#[component]
-fn TurnButtons<'a>(role_info: &'a RoleInfo) -> impl IntoView {
-    let active_werewolf_roles =
-        use_context::<ReadSignal<WerewolfActiveRoles>>().expect("WerewolfActiveRoles not found");
+        let first_night_role = WerewolfRuleset.next_first_night_role(None, &game_ctx.users.get());
+        game_ctx.game_state.set(GameState::Game(
+            &WerewolfRuleset,
+            first_night_role.map_or(GamePhase::Day, GamePhase::FirstNight),
+        ));
+    };
 
-    let role = role_info.get_role();
     let onclick_next_role = move |_| {
         let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
-        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
-            .expect("MafiaContext history not found");
-
-        set_context_history.update(|history| history.push(game_ctx.get_history()));
 
         match get_next_prepare_role(active_werewolf_roles.get().roles, role) {
             Some(role_info) => {
+                let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
+                    .expect("MafiaContext history not found");
+
+                set_context_history.update(|history| history.push(game_ctx.get_history()));
                 game_ctx
                     .game_state
-                    .set(GameState::Werewolf(WerewolfGameState::SetupRoles(
-                        role_info,
-                    )))
-            }
-            None => {
-                game_ctx.users.update(|users| {
-                    initialize_user_roles(users);
-                });
-                game_ctx.round.set(0);
-                game_ctx
-                    .game_state
-                    .set(GameState::Werewolf(WerewolfGameState::Day));
+                    .set(GameState::Game(&WerewolfRuleset, GamePhase::SetupRoles(role_info)))
             }
+            // the attributes are given after the last role
+            None => show_attributes.set(true),
+        }
+    };
+
+    let finish_setup = move || {
+        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+        let issues = validate_werewolf_setup(
+            &game_ctx.users.get(),
+            &active_werewolf_roles.get().roles,
+        );
+
+        show_attributes.set(false);
+        if issues.is_empty() {
+            start_game();
+        } else {
+            setup_issues.set(issues);
         }
     };
 
@@ -1343,7 +1065,7 @@ fn TurnButtons<'a>(role_info: &'a RoleInfo) -> impl IntoView {
 
         set_context_history.update(|history| {
             if let Some(prev_ctx) = history.pop() {
-                game_ctx.set_history(prev_ctx);
+                game_ctx.undo_to(prev_ctx);
             }
         });
     };
@@ -1363,525 +1085,264 @@ fn TurnButtons<'a>(role_info: &'a RoleInfo) -> impl IntoView {
                 "Далее"
             </button>
         </div>
+        {move || show_attributes.get().then(|| view! {
+            <SetupAttributes on_next=finish_setup on_close=move || show_attributes.set(false) />
+        })}
+        {move || (!setup_issues.get().is_empty()).then(|| view! {
+            <SetupCheck
+                issues=setup_issues.get()
+                on_continue=move || {
+                    setup_issues.set(vec![]);
+                    start_game();
+                }
+                on_close=move || setup_issues.set(vec![])
+            />
+        })}
     }
 }
 
-fn users_sorted(users: Vec<Player>) -> Vec<Player> {
-    // Clone and sort the users by a desired attribute if needed. Here, sorting by ID as an example.
-    let len = users.len();
-    let mut rearranged_users = Vec::with_capacity(len);
+// Blocking errors and warnings for the roles given before the first day
+pub fn validate_werewolf_setup(users: &[Player], active_roles: &HashSet<Role>) -> Vec<SetupIssue> {
+    let mut issues = vec![];
+    let werewolf = Role::Werewolf(WerewolfRole::Werewolf);
+    let lovers = Role::Werewolf(WerewolfRole::Lovers);
 
-    let is_odd = len % 2 != 0;
-    // If the length is odd, add the last middle element.
-    if is_odd {
-        rearranged_users.push(users[len / 2].clone());
-    }
+    let setup_roles = WEREWOLF_ROLES
+        .iter()
+        .filter(|r| is_setup_role(r, active_roles))
+        .collect::<Vec<_>>();
 
-    for i in 0..len / 2 {
-        let i = len / 2 - i - 1;
-        // Add the i-th from the end and the i-th from the start in pairs.
-        if is_odd {
-            rearranged_users.push(users[len - i - 1].clone());
-            rearranged_users.push(users[i].clone());
+    if count_role(users, werewolf) + count_role(users, Role::Werewolf(WerewolfRole::Vampire)) == 0 {
+        let phase = if active_roles.contains(&werewolf) {
+            setup_phase(&WEREWOLF_ROLES, werewolf)
         } else {
-            rearranged_users.push(users[i].clone());
-            rearranged_users.push(users[len - i - 1].clone());
-        }
-    }
-
-    rearranged_users
-}
-
-#[component]
-fn SelectUsersForVote(
-    selected_users: ReadSignal<HashSet<String>>,
-    set_selected_users: WriteSignal<HashSet<String>>,
-    is_killed: impl Fn(&Player) -> bool + 'static,
-    is_disabled: impl Fn(&Player) -> bool + 'static,
-    is_highlighted: impl Fn(&Player) -> bool + 'static,
-    #[prop(default = "ring-red-600/50".to_string())] highlight_color: String,
-) -> impl IntoView {
-    let mafia_context = use_context::<GameContext>().expect("MafiaContext not found");
-
-    let users = move || mafia_context.users.get();
-    let users_alive_len = move || users().iter().filter(|u| u.is_alive).count();
-    let werewolf_alive_len = move || {
-        users()
-            .iter()
-            .filter(|u| u.is_alive && u.role.contains(&Role::Werewolf(WerewolfRole::Werewolf)))
-            .count()
-    };
-    let vampire_alive_len = move || {
-        users()
-            .iter()
-            .filter(|u| u.is_alive && u.role.contains(&Role::Werewolf(WerewolfRole::Vampire)))
-            .count()
-    };
-    let is_selected = move |user: &Player| selected_users.get().contains(&user.id);
-    let users_sorted = move || users_sorted(mafia_context.users.get());
-
-    view! {
-        <div class="text-sm">"Осталось игроков: "{users_alive_len}", оборотней: "{werewolf_alive_len}", вампиров: "{vampire_alive_len}</div>
-        <div class="grid grid-cols-2 gap-y-1 gap-x-3">
-            <For
-                each=users_sorted
-                key=|user| user.id.clone()
-                children=move |user| {
-                    let disabled = is_disabled(&user);
-                    let highlighted = is_highlighted(&user);
-                    let killed = is_killed(&user);
+            Some(GamePhase::SelectActiveRoles)
+        };
 
-                    view!{
-                        <UserSelectRole
-                            user=user.clone()
-                            disabled=disabled
-                            highlighted=highlighted
-                            highlight_color=highlight_color.clone()
-                            killed=killed
-                            is_selected=is_selected
-                            on:click=move |_| {
-                                set_selected_users.update(|selected_users| {
-                                    if selected_users.contains(&user.id) {
-                                        selected_users.remove(&user.id);
-                                    } else {
-                                        selected_users.insert(user.id.clone());
-                                    }
-                                });
-                            }
-                        />
-                    }
-                }
-            />
-        </div>
+        issues.push(SetupIssue::error(
+            "Нет убивающей стороны".to_string(),
+            "Назначьте игроков Werewolf или Vampire",
+            phase,
+        ));
     }
-}
 
-fn day_kill_user(user: &mut Player, round: usize) {
-    if !user.is_alive {
-        return;
+    // Lovers always come in pairs
+    let needed_players = setup_roles
+        .iter()
+        .map(|r| if r.get_role() == lovers { 2 } else { 1 })
+        .sum::<usize>();
+
+    if needed_players > users.len() {
+        issues.push(SetupIssue::error(
+            "Ролей больше, чем игроков".to_string(),
+            "Отключите лишние роли",
+            Some(GamePhase::SelectActiveRoles),
+        ));
     }
 
-    if user
-        .additional_role
-        .contains(&Role::Werewolf(WerewolfRole::Vampire))
-    {
-        user.choosed_by
-            .insert(Role::Werewolf(WerewolfRole::Vampire));
-    } else {
-        user.choosed_by
-            .insert(Role::Werewolf(WerewolfRole::Villager));
-    }
-    user.was_killed = true;
+    for role_info in setup_roles {
+        let count = count_role(users, role_info.get_role());
 
-    if user
-        .additional_role
-        .contains(&Role::Werewolf(WerewolfRole::ToughGuy))
-    {
-        user.additional_role
-            .remove(&Role::Werewolf(WerewolfRole::ToughGuy));
-        return;
+        if count == 0 {
+            issues.push(SetupIssue::warning(
+                format!("Роль {} не назначена ни одному игроку", role_info.get_role_name()),
+                "Назначьте игрока или отключите роль",
+                Some(GamePhase::SetupRoles(role_info)),
+            ));
+        }
     }
 
-    user.choosed_by.insert(Role::WasKilled);
-    user.is_alive = false;
-}
-
-#[component]
-fn DayVote() -> impl IntoView {
-    let clock_choose = create_rw_signal(true);
-    let kill_player_choose = create_rw_signal(false);
-    let start_player_choose = create_rw_signal(false);
-
-    let (highlighted_player, set_highlighted_player) =
-        create_signal::<HashSet<String>>(HashSet::new());
-
-    let open_dialogue =
-        use_context::<RwSignal<OpenFinishGameDialogue>>().expect("MafiaContext not found");
-    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
-
-    let (selected_users, set_selected_users) = create_signal::<HashSet<String>>(HashSet::new());
+    // Lovers always come in pairs, the count is checked with Cupid too
+    let lovers_count = count_role(users, lovers);
+    if active_roles.contains(&lovers) && lovers_count != 0 && lovers_count != 2 {
+        issues.push(SetupIssue::error(
+            "Lovers должно быть ровно двое".to_string(),
+            "Выберите двух игроков Mason (Lovers)",
+            setup_phase(&WEREWOLF_ROLES, lovers),
+        ));
+    }
 
-    let onclick_next_role = move || {
-        let selected_users = selected_users.get();
-        let round = game_ctx.round.get();
+    issues.extend(players_with_several_roles(
+        &WEREWOLF_ROLES,
+        users,
+        |r| matches!(r, RoleInfo::Night(_)) && r.get_check_role() == r.get_role(),
+        IssueLevel::Warning,
+    ));
 
-        game_ctx.users.update(|users| {
-            clear_choosed_by(users, round);
-            clear_was_killed(users);
+    issues
+}
 
-            users.iter_mut().for_each(|u| {
-                if selected_users.contains(&u.id) {
-                    day_kill_user(u, round);
-                }
-            });
+// Day vote results: the selected players are executed
+pub fn apply_day_vote(
+    users: &mut [Player],
+    events: &mut Vec<GameEvent>,
+    round: usize,
+    selected_users: &HashSet<String>,
+) {
+    expire_day_effects(users);
+    clear_was_killed(users);
 
-            calculate_after_kills(users);
-            clear_choosed_by(users, round + 1);
-        });
-        game_ctx.round.set(round + 2);
-
-        let users = game_ctx.users.get();
-        let mut next_role = Some(WEREWOLF_ROLES.first().unwrap());
-        loop {
-            if let Some(check_role) = next_role {
-                if is_role_alive(check_role.get_role(), &users) {
-                    game_ctx
-                        .game_state
-                        .set(GameState::Werewolf(WerewolfGameState::Night(check_role)));
-                    break;
-                }
-                next_role = get_next_night_role(check_role);
-            } else {
-                open_dialogue.set(OpenFinishGameDialogue(true));
-                break;
-            }
+    users.iter_mut().for_each(|u| {
+        if selected_users.contains(&u.id) {
+            day_kill_user(u, events, round);
         }
-    };
-
-    let onclick_kill_users = move || {
-        let selected_users = selected_users.get();
-        let round = game_ctx.round.get();
-
-        game_ctx.users.update(|users| {
-            users.iter_mut().for_each(|u| {
-                if selected_users.contains(&u.id) {
-                    day_kill_user(u, round);
-                }
-            });
+    });
 
-            calculate_after_kills(users);
-        });
+    calculate_after_kills(users, events, round + 1);
 
-        kill_player_choose.set(false);
-        clock_choose.set(true);
-    };
+    debug_check_invariants(users, events, "apply_day_vote");
+}
 
-    create_effect(move |_| {
-        kill_player_choose.get();
-        set_selected_users.update(|selected_users| {
-            selected_users.clear();
-        });
-    });
+// Hints shown to the moderator in the morning
+pub fn day_hints(users: &[Player], events: &[GameEvent]) -> Vec<WerewolfHint> {
+    let mut log = Vec::<WerewolfHint>::new();
 
-    let onclick_start_player = move || {
-        if !start_player_choose.get() {
-            set_highlighted_player.update(|highlighted_player| {
-                highlighted_player.clear();
-            });
-            start_player_choose.set(true);
-        } else {
-            start_player_choose.set(false);
+    users.iter().for_each(|user| {
+        if user.was_killed && !user.is_alive {
+            log.push(WerewolfHint::Killed(user.clone(), kill_causes(events, &user.id)));
         }
-    };
+    });
 
-    create_effect(move |_| {
-        if start_player_choose.get() && !highlighted_player.get().is_empty() {
-            start_player_choose.set(false);
-            clock_choose.set(true);
+    users.iter().for_each(|user| {
+        if user.role.contains(&Role::Werewolf(WerewolfRole::ToughGuy))
+            && user.is_alive
+            && user.was_killed
+        {
+            log.push(WerewolfHint::LostHeart(user.clone()));
         }
     });
 
-    let game_log = create_memo(move |_| {
-        let mut log = Vec::<WerewolfHint>::new();
-
-        let users = game_ctx.users.get();
-
-        users.iter().for_each(|user| {
-            if user.was_killed && !user.is_alive {
-                log.push(WerewolfHint::Killed(user.clone(), user.choosed_by.clone()));
-            }
-        });
-
-        users.iter().for_each(|user| {
-            if user.role.contains(&Role::Werewolf(WerewolfRole::ToughGuy))
-                && user.is_alive
-                && user.was_killed
-            {
-                log.push(WerewolfHint::LostHeart(user.clone()));
-            }
-        });
-
-        users.iter().for_each(|user| {
-            if user
-                .choosed_by
-                .contains(&Role::Werewolf(WerewolfRole::Spellcaster))
-                && user.is_alive
-            {
-                log.push(WerewolfHint::Spellcaster(user.clone()));
-            }
-        });
-
-        users.iter().for_each(|user| {
-            if user
-                .additional_role
-                .contains(&Role::Werewolf(WerewolfRole::Mayor))
-                && user.is_alive
-            {
-                log.push(WerewolfHint::Mayor(user.clone()));
-            }
-        });
-
-        // let v_users = users
-        //     .iter()
-        //     .filter(|user| {
-        //         return user
-        //             .additional_role
-        //             .contains(&Role::Werewolf(WerewolfRole::Vampire))
-        //             && user.is_alive;
-        //     })
-        //     .cloned()
-        //     .collect::<Vec<_>>();
-
-        // if !v_users.is_empty() {
-        //     log.push(WerewolfHint::Vampire(v_users));
-        // }
-
-        log
+    users.iter().filter(|user| user.is_alive).for_each(|user| {
+        user.effects
+            .iter()
+            .filter(|effect| matches!(effect.kind, StatusKind::Silenced | StatusKind::Banished))
+            .for_each(|effect| log.push(WerewolfHint::Effect(effect.kind, user.clone())));
     });
 
-    let is_highlighted = move |user: &Player| highlighted_player.get().contains(&user.id);
-
-    view! {
-        {move ||
-            if start_player_choose.get() {
-                view!{
-                    <h2>"Кто начал этот раунд?"</h2>
-                    <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
-                        <div class="flex-1"></div>
-                        <div class="flex flex-col gap-1 w-full pb-0.5">
-                            <SelectUsersForVote
-                                selected_users=highlighted_player
-                                set_selected_users=set_highlighted_player
-                                is_killed=move |user: &Player| !user.is_alive && !user.was_killed
-                                is_disabled=move |user| !user.is_alive
-                                is_highlighted
-                                highlight_color="ring-blue-600/80".to_string()
-                            />
-                        </div>
-                    </div>
-                    <DayTurnButtons onclick_next_role clock_choose start_player_choose kill_player_choose onclick_start_player />
-                }.into_view()
-            }else if kill_player_choose.get() {
-                view!{
-                    <h2>"Выберите убитых в начале дня:"</h2>
-                    <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
-                        <div class="flex-1"></div>
-                        <div class="flex flex-col gap-1 w-full pb-0.5">
-                            <SelectUsersForVote
-                                selected_users
-                                set_selected_users
-                                is_killed=move |user: &Player| !user.is_alive && !user.was_killed
-                                is_disabled=move |user| !user.is_alive
-                                is_highlighted
-                                highlight_color="ring-blue-600/80".to_string()
-                            />
-                        </div>
-                    </div>
-                    <DayTurnButtons onclick_next_role=onclick_kill_users clock_choose start_player_choose kill_player_choose onclick_start_player />
-                }.into_view()
-            }else{
-                view! {
-                    <h2>"Кого мирные жители убъют этим Днем?"</h2>
-                    <DisplayLogs logs=game_log />
-                    <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
-                        <div class="flex-1"></div>
-                        <div class="flex flex-col gap-1 w-full pb-0.5">
-                            <SelectUsersForVote selected_users set_selected_users
-                                is_killed=move |user: &Player| !user.is_alive && !user.was_killed
-                                is_disabled=move |user| !user.is_alive
-                                is_highlighted
-                                highlight_color="ring-blue-600/80".to_string()
-                            />
-                        </div>
-                    </div>
-                    <Show when=move || clock_choose.get()>
-                        <Timer />
-                    </Show>
-                    <DayTurnButtons onclick_next_role clock_choose start_player_choose kill_player_choose onclick_start_player />
-                }.into_view()
-            }
+    users.iter().for_each(|user| {
+        if user
+            .additional_role
+            .contains(&Role::Werewolf(WerewolfRole::Mayor))
+            && user.is_alive
+        {
+            log.push(WerewolfHint::Mayor(user.clone()));
         }
-    }
+    });
+
+    // let v_users = users
+    //     .iter()
+    //     .filter(|user| {
+    //         return user
+    //             .additional_role
+    //             .contains(&Role::Werewolf(WerewolfRole::Vampire))
+    //             && user.is_alive;
+    //     })
+    //     .cloned()
+    //     .collect::<Vec<_>>();
+
+    // if !v_users.is_empty() {
+    //     log.push(WerewolfHint::Vampire(v_users));
+    // }
+
+    log
 }
 
-#[component]
-fn DisplayLogs(logs: Memo<Vec<WerewolfHint>>) -> impl IntoView {
-    view! {
-        <div class="flex flex-col gap-1 relative text-xs">
-            {move || logs.get().iter().map(
-                |log| {
-                    match log {
-                        WerewolfHint::Killed(user, killed_by) => {
-                            let user = user.clone();
-                            view!{
-                                <div class="w-full flex items-center justify-start gap-1 text-gray-500">
-                                    "❌"<span class="bg-gray-100 text-gray-900 px-1 rounded-md">{user.name}</span><UserRoleNames role=user.role />" убит"<UserKilledBy killed_by=killed_by.clone() />"."
-                                </div>
-                            }.into_view()
-                        },
-                        WerewolfHint::Mayor(user) => {
-                            let user = user.clone();
-                            view!{
-                                <div class="w-full flex items-center justify-start gap-1 text-gray-500">
-                                    "🎖️"<span class="bg-gray-100 text-gray-900 px-1 rounded-md">{user.name}</span>"мэр."
-                                </div>
-                            }.into_view()
-                        },
-                        WerewolfHint::Spellcaster(user) => {
-                            let user = user.clone();
-                            view!{
-                                <div class="w-full flex items-center justify-start gap-1 text-gray-500">
-                                    "🤐"<span class="bg-gray-100 text-gray-900 px-1 rounded-md">{user.name}</span>"не может говорить."
-                                </div>
-                            }.into_view()
-                        },
-                        WerewolfHint::Cursed(user) => {
-                            let user = user.clone();
-                            view!{
-                                <div class="w-full flex items-center justify-start gap-1 text-gray-500">
-                                    "😈"<span class="bg-gray-100 text-gray-900 px-1 rounded-md">{user.name}</span>"станет оборотнем если его съедят."
-                                </div>
-                            }.into_view()
-                        },
-                        WerewolfHint::LostHeart(user) => {
-                            let user = user.clone();
-                            view!{
-                                <div class="w-full flex items-center justify-start gap-1 text-gray-500">
-                                    "💔"<span class="bg-gray-100 text-gray-900 px-1 rounded-md">{user.name}</span>"потерял жизнь."
-                                </div>
-                            }.into_view()
-                        }
-                        WerewolfHint::Seer(users) => {
-                            view!{
-                                <div class="w-full flex-wrap flex items-center justify-start gap-1.5 text-gray-500">
-                                    "🔍"{users.iter().map(|user| {
-                                        let user = user.clone();
-                                        view!{
-                                            <span class="bg-gray-100 text-gray-900 px-1 rounded-md whitespace-nowrap">{user.name}</span>
-                                        }.into_view()
-                                    }).collect::<Vec<_>>().into_view()}"оборотни."
-                                </div>
-                            }.into_view()
-                        }
-                        // WerewolfHint::Vampire(users) => {
-                        //     view!{
-                        //         <div class="w-full flex-wrap flex items-center justify-start gap-1.5 text-gray-500">
-                        //             "🩸"{users.iter().map(|user| {
-                        //                 let user = user.clone();
-                        //                 view!{
-                        //                     <span class="bg-gray-100 text-gray-900 px-1 rounded-md whitespace-nowrap">{user.name}</span>
-                        //                 }.into_view()
-                        //             }).collect::<Vec<_>>().into_view()}"укушены вампирами."
-                        //         </div>
-                        //     }.into_view()
-                        // }
-                    }
-                }
-            ).collect::<Vec<_>>().into_view()}
-        </div>
+// The village executes the player in the day after the night of `round`
+pub fn day_kill_user(user: &mut Player, events: &mut Vec<GameEvent>, round: usize) {
+    if !user.is_alive {
+        return;
     }
-}
 
-#[component]
-fn Timer() -> impl IntoView {
-    let (time, set_time) = create_signal(0);
-
-    let Pausable {
-        pause,
-        resume,
-        is_active,
-    } = use_interval_fn(
-        move || {
-            // do something
-            set_time.update(|time| {
-                if *time > 0 {
-                    *time -= 1;
-                }
-            });
-        },
-        1000,
-    );
+    let by = if user
+        .additional_role
+        .contains(&Role::Werewolf(WerewolfRole::Vampire))
+    {
+        Role::Werewolf(WerewolfRole::Vampire)
+    } else {
+        Role::Werewolf(WerewolfRole::Villager)
+    };
+    events.push(GameEvent::Lynched {
+        phase: round + 1,
+        target: user.id.clone(),
+        by,
+    });
+    user.was_killed = true;
 
-    let get_time = move || {
-        let time = time.get();
-        let minutes = time / 60;
-        let seconds = time % 60;
+    if user.consume_effect(&[Role::Werewolf(WerewolfRole::ToughGuy)]).is_some() {
+        return;
+    }
 
-        format!("{:01}:{:02}", minutes, seconds)
-    };
+    // Village Idiot survives the first lynch, but is out of the discussion for the rest of the game
+    if user
+        .additional_role
+        .contains(&Role::Werewolf(WerewolfRole::VillageIdiot))
+        && !user.has_effect(StatusKind::Banished)
+    {
+        user.apply_effect(
+            StatusKind::Banished,
+            Role::Werewolf(WerewolfRole::VillageIdiot),
+            round,
+            Expiry::Permanent,
+        );
+        return;
+    }
 
-    pause();
+    user.is_alive = false;
+    events.push(GameEvent::Killed {
+        phase: round + 1,
+        target: user.id.clone(),
+        cause: Some(by),
+    });
+}
 
-    create_effect(move |_| {
-        if is_active.get() && time.get() == 0 {
-            pause();
+pub fn is_mayor(user: &Player) -> bool {
+    user.additional_role
+        .contains(&Role::Werewolf(WerewolfRole::Mayor))
+}
 
-            // play alarm sound
-            let audio = web_sys::HtmlAudioElement::new_with_src("assets/alarm.mp3");
-            if let Ok(audio) = audio {
-                let _ = audio.play().unwrap();
-            }
+// The Mayor badge goes to `id`, nobody else keeps it
+pub fn elect_mayor(users: &mut [Player], id: Option<&str>) {
+    users.iter_mut().for_each(|user| {
+        if Some(user.id.as_str()) == id {
+            user.additional_role
+                .insert(Role::Werewolf(WerewolfRole::Mayor));
+        } else {
+            user.additional_role
+                .remove(&Role::Werewolf(WerewolfRole::Mayor));
         }
     });
+}
 
-    let start_timer = move |start_time: i32| {
-        set_time.update(|time| {
-            *time = start_time;
-        });
-        resume();
-    };
-
-    view! {
-        <div class="flex items-stretch justify-center gap-1">
-            <div class="text-4xl">"⏰"</div>
-            <div class="text-4xl">{get_time}</div>
-            <button class="flex-1 px-1 py-1 text-sm bg-gray-200 rounded-full" on:click={
-                let start_timer = start_timer.clone();
-
-                move|_|{
-                    start_timer(60);
-                }
-            }>"1 мин"</button>
-            <button class="flex-1 px-1 py-1 text-sm bg-gray-200 rounded-full" on:click={
-                let start_timer = start_timer.clone();
+// A dead Mayor still holding the badge has to name the successor
+pub fn dead_mayor(users: &[Player]) -> Option<&Player> {
+    users.iter().find(|user| is_mayor(user) && !user.is_alive)
+}
 
-                move|_|{
-                    start_timer(30);
-                }
-            }>"30 сек"</button>
-            <button class="flex-1 px-1 py-1 text-sm bg-gray-200 rounded-full" on:click={
-                let start_timer = start_timer.clone();
+// The election is optional and held on the first day only, when nobody got the Mayor at the setup.
+// Once the village has elected the Mayor or decided to play without one, the election is over.
+pub fn is_mayor_election_open(users: &[Player], events: &[GameEvent], round: usize) -> bool {
+    round == 0
+        && !users.iter().any(is_mayor)
+        && !events
+            .iter()
+            .any(|event| matches!(event, GameEvent::MayorChosen { .. }))
+}
 
-                move|_|{
-                    start_timer(15);
-                }
-            }>"15 сек"</button>
-        </div>
-    }
+// The overlays come one at a time: the dying Brutal player picks the victim first, then the badge
+// is passed on, also when the revenge has taken the Mayor
+pub fn is_mayor_choice_pending(users: &[Player], events: &[GameEvent], round: usize, is_day: bool) -> bool {
+    pending_revenge(users).is_none()
+        && (dead_mayor(users).is_some() || is_day && is_mayor_election_open(users, events, round))
 }
 
 #[component]
-fn DayTurnButtons<F, F2>(
-    onclick_next_role: F,
-    onclick_start_player: F2,
-    clock_choose: RwSignal<bool>,
-    start_player_choose: RwSignal<bool>,
-    kill_player_choose: RwSignal<bool>,
-) -> impl IntoView
+fn KillDayButtons<F, F2>(onclick_kill_users: F, onclick_cancel: F2) -> impl IntoView
 where
     F: Fn() + 'static,
     F2: Fn() + 'static,
 {
     let onclick_prev_role = move |_| {
-        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
-        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
-            .expect("MafiaContext history not found");
-
-        set_context_history.update(|history| {
-            if let Some(prev_ctx) = history.pop() {
-                game_ctx.set_history(prev_ctx);
-            }
-        });
+        onclick_cancel();
     };
 
     let onclick_next = move |_| {
@@ -1891,97 +1352,7 @@ where
 
         set_context_history.update(|history| history.push(game_ctx.get_history()));
 
-        onclick_next_role();
-    };
-
-    view! {
-        <div class="flex gap-2 w-full items-center">
-        <button
-            class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full flex items-center justify-center"
-            on:click=onclick_prev_role
-        >
-            "←"
-        </button>
-        <button
-            class=move || {
-                format!("flex-1 px-2 py-2 text-sm rounded-full flex items-center justify-center {}", if kill_player_choose.get() {
-                    "bg-blue-500"
-                }else{
-                    "bg-gray-200"
-                })
-            }
-            on:click=move |_| {
-                kill_player_choose.update(|kill_player_choose| {
-                    *kill_player_choose = !*kill_player_choose;
-                });
-                clock_choose.set(false);
-                start_player_choose.set(false);
-            }
-        >
-            "❌"
-        </button>
-        <button
-            class=move || {
-                format!("flex-1 px-2 py-2 text-sm rounded-full flex items-center justify-center {}", if start_player_choose.get() {
-                    "bg-blue-500"
-                }else{
-                    "bg-gray-200"
-                })
-            }
-            on:click=move |_| {
-                onclick_start_player();
-                clock_choose.set(false);
-                kill_player_choose.set(false);
-            }
-        >
-            "🏁"
-        </button>
-        <button
-            class=move || {
-                format!("flex-1 px-2 py-2 text-sm rounded-full flex items-center justify-center {}", if clock_choose.get() {
-                    "bg-blue-500"
-                }else{
-                    "bg-gray-200"
-                })
-            }
-            on:click=move |_| {
-                start_player_choose.set(false);
-                clock_choose.update(|clock_choose| {
-                    *clock_choose = !*clock_choose;
-                });
-                kill_player_choose.set(false);
-            }
-        >
-            "⏰"
-        </button>
-        <button
-                class="flex-grow-[2] px-4 py-2 text-sm bg-gray-200 rounded-full"
-                on:click=onclick_next
-            >
-                "Далее"
-            </button>
-        </div>
-    }
-}
-
-#[component]
-fn KillDayButtons<F, F2>(onclick_kill_users: F, onclick_cancel: F2) -> impl IntoView
-where
-    F: Fn() + 'static,
-    F2: Fn() + 'static,
-{
-    let onclick_prev_role = move |_| {
-        onclick_cancel();
-    };
-
-    let onclick_next = move |_| {
-        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
-        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
-            .expect("MafiaContext history not found");
-
-        set_context_history.update(|history| history.push(game_ctx.get_history()));
-
-        onclick_kill_users();
+        onclick_kill_users();
     };
 
     view! {
@@ -2002,252 +1373,212 @@ where
     }
 }
 
-fn is_role_alive(role: Role, users: &[Player]) -> bool {
-    users.iter().any(|u| u.role.contains(&role) && u.is_alive)
-}
-
-fn get_next_night_alive_role(role_info: &RoleInfo, users: &[Player]) -> Option<&'static RoleInfo> {
-    let mut next_role = role_info;
-    loop {
-        let check_role = get_next_night_role(next_role);
-        if let Some(check_role) = check_role {
-            if is_role_alive(check_role.get_check_role(), users) {
-                return Some(check_role);
-            }
-            next_role = check_role;
-        } else {
-            return None;
-        }
-    }
-}
-
-fn clear_choosed_by(users: &mut [Player], round: usize) {
-    for user in users.iter_mut() {
-        if !user.choosed_by.is_empty() {
-            user.history_by.push((round, user.choosed_by.clone()));
-        }
-        user.choosed_by.clear();
-    }
-
-    for user in users.iter_mut() {
-        // clear spellcaster silence
-        user.additional_role
-            .remove(&Role::Werewolf(WerewolfRole::Spellcaster));
-    }
-}
-
-fn clear_was_killed(users: &mut [Player]) {
+pub fn clear_was_killed(users: &mut [Player]) {
     for user in users.iter_mut() {
         user.was_killed = false;
     }
 }
 
-fn calculate_night_kills(users: &mut [Player]) {
+// Night choices of `round` from the event log are resolved into the kills, saves and conversions
+fn calculate_night_kills(users: &mut [Player], events: &[GameEvent], round: usize) -> PhaseOutcome {
     clear_was_killed(users);
-    // Mafia killed choosed user if he is not protected by doctor or prostitute
-    let mut alive_users = users.iter_mut().filter(|u| u.is_alive).collect::<Vec<_>>();
 
-    fn is_user_protected(user: &Player, check_protection: &[Role]) -> bool {
-        for role in check_protection {
-            if user.choosed_by.contains(role) {
-                return true;
-            }
+    // the attack fails when a role is with the player, an extra life or the blessing is lost instead of the life
+    fn attack(outcome: &mut PhaseOutcome, user: &mut Player, protected_by: Option<Role>, cause: Role, lives: &[Role]) {
+        if !user.is_alive {
+            return;
         }
-        return false;
-    }
 
-    fn kill_user(user: &mut Player, check_protection: &[Role]) {
-        if !user.is_alive {
+        if let Some(role) = protected_by {
+            outcome.protect(user, role);
             return;
         }
 
-        for role in check_protection {
-            if user.additional_role.contains(role) {
-                user.additional_role.remove(role);
+        match user.consume_effect(lives) {
+            Some(source) => {
                 user.was_killed = true;
-                return;
+                outcome.protect(user, source);
             }
+            None => outcome.kill(user, cause),
         }
-
-        user.choosed_by.insert(Role::WasKilled);
-        user.is_alive = false;
-        user.was_killed = true;
     }
 
+    let werewolf = Role::Werewolf(WerewolfRole::Werewolf);
+    let vampire = Role::Werewolf(WerewolfRole::Vampire);
+    let priest = Role::Werewolf(WerewolfRole::Priest);
+    let tough_guy = Role::Werewolf(WerewolfRole::ToughGuy);
+
+    let choices = users
+        .iter()
+        .map(|u| phase_choices(events, round, &u.id))
+        .collect::<Vec<_>>();
+    let mut outcome = PhaseOutcome::new(round);
     let mut kill_roles = HashSet::<Role>::new();
 
-    for user in alive_users.iter_mut() {
+    for (user, choices) in users.iter_mut().zip(choices.iter()).filter(|(u, _)| u.is_alive) {
+        let protector = |roles: &[WerewolfRole]| {
+            roles
+                .iter()
+                .map(|role| Role::Werewolf(*role))
+                .find(|role| choices.contains(role))
+        };
+        let is_night_killer = user.role.contains(&werewolf) || user.role.contains(&vampire);
+
         // Priest check
-        if user
-            .choosed_by
-            .contains(&Role::Werewolf(WerewolfRole::Priest))
-        {
-            if user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf))
-                || user.role.contains(&Role::Werewolf(WerewolfRole::Vampire))
-            {
-                if !is_user_protected(user, &[Role::Werewolf(WerewolfRole::Bodyguard)]) {
-                    kill_user(user, &[Role::Werewolf(WerewolfRole::ToughGuy)]);
-                }
+        if choices.contains(&priest) {
+            if is_night_killer {
+                attack(&mut outcome, user, protector(&[WerewolfRole::Bodyguard]), priest, &[tough_guy]);
             } else {
-                user.additional_role
-                    .insert(Role::Werewolf(WerewolfRole::Priest));
+                user.apply_effect(StatusKind::Blessed, priest, round, Expiry::Triggered);
             }
         }
 
         // Vampire check
-        if user
-            .choosed_by
-            .contains(&Role::Werewolf(WerewolfRole::Vampire))
-        {
-            if !is_user_protected(user, &[Role::Werewolf(WerewolfRole::Bodyguard)]) {
-                if user
-                    .additional_role
-                    .contains(&Role::Werewolf(WerewolfRole::Priest))
-                {
-                    user.additional_role
-                        .remove(&Role::Werewolf(WerewolfRole::Priest));
-                } else if user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf)) {
-                    if !is_user_protected(
-                        user,
-                        &[
-                            Role::Werewolf(WerewolfRole::Bodyguard),
-                            Role::Werewolf(WerewolfRole::WitchHeal),
-                        ],
-                    ) {
-                        kill_user(user, &[Role::Werewolf(WerewolfRole::ToughGuy)]);
-                    }
-                } else {
-                    user.role.insert(Role::Werewolf(WerewolfRole::Vampire));
-                }
+        if choices.contains(&vampire) {
+            // the blessing is lost instead of the bite
+            match protector(&[WerewolfRole::Bodyguard]).or_else(|| user.consume_effect(&[priest])) {
+                Some(role) => outcome.protect(user, role),
+                None if user.role.contains(&werewolf) => attack(
+                    &mut outcome,
+                    user,
+                    protector(&[WerewolfRole::Bodyguard, WerewolfRole::WitchHeal]),
+                    vampire,
+                    &[tough_guy],
+                ),
+                None => outcome.convert(user, vampire, vampire),
             }
         }
 
         // Werewolf check
-        if user
-            .choosed_by
-            .contains(&Role::Werewolf(WerewolfRole::Werewolf))
-        {
+        if choices.contains(&werewolf) {
             if user.role.contains(&Role::Werewolf(WerewolfRole::Cursed)) {
-                if !is_user_protected(user, &[Role::Werewolf(WerewolfRole::Bodyguard)]) {
-                    // Priest check
-                    if user
-                        .additional_role
-                        .contains(&Role::Werewolf(WerewolfRole::Priest))
-                    {
-                        user.additional_role
-                            .remove(&Role::Werewolf(WerewolfRole::Priest));
-                    } else {
-                        user.role.insert(Role::Werewolf(WerewolfRole::Werewolf));
-                    }
+                match protector(&[WerewolfRole::Bodyguard]).or_else(|| user.consume_effect(&[priest])) {
+                    Some(role) => outcome.protect(user, role),
+                    None => outcome.convert(user, werewolf, Role::Werewolf(WerewolfRole::Cursed)),
                 }
             } else {
-                if !is_user_protected(
+                attack(
+                    &mut outcome,
                     user,
-                    &[
-                        Role::Werewolf(WerewolfRole::Bodyguard),
-                        Role::Werewolf(WerewolfRole::WitchHeal),
-                    ],
-                ) {
-                    kill_user(
-                        user,
-                        &[
-                            Role::Werewolf(WerewolfRole::Priest),
-                            Role::Werewolf(WerewolfRole::ToughGuy),
-                        ],
-                    );
-                }
+                    protector(&[WerewolfRole::Bodyguard, WerewolfRole::WitchHeal]),
+                    werewolf,
+                    &[priest, tough_guy],
+                );
             }
         }
 
         // Witch check
-        if user
-            .choosed_by
-            .contains(&Role::Werewolf(WerewolfRole::WitchPoison))
-        {
-            if !is_user_protected(user, &[Role::Werewolf(WerewolfRole::Bodyguard)]) {
-                kill_user(
-                    user,
-                    &[
-                        Role::Werewolf(WerewolfRole::ToughGuy),
-                        Role::Werewolf(WerewolfRole::Priest),
-                    ],
-                );
-            }
+        if choices.contains(&Role::Werewolf(WerewolfRole::WitchPoison)) {
+            attack(
+                &mut outcome,
+                user,
+                protector(&[WerewolfRole::Bodyguard]),
+                Role::Werewolf(WerewolfRole::WitchPoison),
+                &[tough_guy, priest],
+            );
         }
 
         // Huntress check
-        if user
-            .choosed_by
-            .contains(&Role::Werewolf(WerewolfRole::Huntress))
-        {
-            if !is_user_protected(
+        if choices.contains(&Role::Werewolf(WerewolfRole::Huntress)) {
+            attack(
+                &mut outcome,
                 user,
-                &[
-                    Role::Werewolf(WerewolfRole::Bodyguard),
-                    Role::Werewolf(WerewolfRole::WitchHeal),
-                ],
-            ) {
-                kill_user(
-                    user,
-                    &[
-                        Role::Werewolf(WerewolfRole::ToughGuy),
-                        Role::Werewolf(WerewolfRole::Priest),
-                    ],
-                );
-            }
+                protector(&[WerewolfRole::Bodyguard, WerewolfRole::WitchHeal]),
+                Role::Werewolf(WerewolfRole::Huntress),
+                &[tough_guy, priest],
+            );
         }
 
         // Revealer check
-        if user
-            .choosed_by
-            .contains(&Role::Werewolf(WerewolfRole::Revealer))
-        {
-            if user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf))
-                || user.role.contains(&Role::Werewolf(WerewolfRole::Vampire))
-            {
-                if !is_user_protected(
+        if choices.contains(&Role::Werewolf(WerewolfRole::Revealer)) {
+            if is_night_killer {
+                attack(
+                    &mut outcome,
                     user,
-                    &[
-                        Role::Werewolf(WerewolfRole::Bodyguard),
-                        Role::Werewolf(WerewolfRole::WitchHeal),
-                    ],
-                ) {
-                    kill_user(
-                        user,
-                        &[
-                            Role::Werewolf(WerewolfRole::ToughGuy),
-                            Role::Werewolf(WerewolfRole::Priest),
-                        ],
-                    );
-                }
+                    protector(&[WerewolfRole::Bodyguard, WerewolfRole::WitchHeal]),
+                    Role::Werewolf(WerewolfRole::Revealer),
+                    &[tough_guy, priest],
+                );
             } else {
                 kill_roles.insert(Role::Werewolf(WerewolfRole::Revealer));
             }
         }
 
         // Spellcaster check
-        if user
-            .choosed_by
-            .contains(&Role::Werewolf(WerewolfRole::Spellcaster))
-        {
-            user.additional_role
-                .insert(Role::Werewolf(WerewolfRole::Spellcaster));
+        if choices.contains(&Role::Werewolf(WerewolfRole::Spellcaster)) {
+            user.apply_effect(
+                StatusKind::Silenced,
+                Role::Werewolf(WerewolfRole::Spellcaster),
+                round,
+                Expiry::EndOfDay,
+            );
         }
     }
 
     // kill rest roles
     for kill_role in kill_roles {
-        for user in alive_users.iter_mut() {
-            if user.role.contains(&kill_role) {
-                kill_user(user, &[]);
-            }
+        for user in users.iter_mut().filter(|u| u.role.contains(&kill_role)) {
+            attack(&mut outcome, user, None, kill_role, &[]);
         }
     }
+
+    outcome
 }
 
-fn initialize_user_roles(users: &mut [Player]) {
+// Role given on the setup screen, passive roles bring their additional role with them
+pub fn insert_user_role(user: &mut Player, role: Role) {
+    user.role.insert(role);
+
+    if let Some(RoleInfo::Passive(PassiveRoleInfo {
+        additional_role: Some(additional_role),
+        ..
+    })) = WEREWOLF_ROLES.iter().find(|r| r.get_role() == role)
+    {
+        user.additional_role.insert(*additional_role);
+    }
+}
+
+// Night choices are resolved after the last night role, a player saved from the attack can still
+// die with the dead ones
+pub fn resolve_night(users: &mut [Player], events: &mut Vec<GameEvent>, round: usize) {
+    let mut outcome = calculate_night_kills(users, events, round);
+    after_kills(users, &mut outcome);
+    outcome.commit(users, events);
+
+    inherit_doppelganger_roles(users);
+
+    debug_check_invariants(users, events, "resolve_night");
+}
+
+// Players who die if the night of `round` is resolved with the choices made so far
+pub fn pending_victims(users: &[Player], events: &[GameEvent], round: usize) -> Vec<Player> {
+    let mut resolved = users.to_vec();
+    resolve_night(&mut resolved, &mut events.to_vec(), round);
+
+    users
+        .iter()
+        .zip(resolved.iter())
+        .filter(|(before, after)| before.is_alive && !after.is_alive)
+        .map(|(before, _)| before.clone())
+        .collect()
+}
+
+pub fn initialize_user_roles(users: &mut [Player]) {
     // init additional roles
 
+    // tough guy extra life
+    users
+        .iter_mut()
+        .filter(|u| u.role.contains(&Role::Werewolf(WerewolfRole::ToughGuy)))
+        .for_each(|u| {
+            u.apply_effect(
+                StatusKind::Protected,
+                Role::Werewolf(WerewolfRole::ToughGuy),
+                0,
+                Expiry::Triggered,
+            );
+        });
+
     // vampire
     users
         .iter_mut()
@@ -2258,7 +1589,18 @@ fn initialize_user_roles(users: &mut [Player]) {
         });
 }
 
-fn calculate_after_kills(users: &mut [Player]) {
+// Players who die with the dead ones of the phase: the other Lover, the Vampires and the Dire Wolf
+pub fn calculate_after_kills(users: &mut [Player], events: &mut Vec<GameEvent>, phase: usize) {
+    let mut outcome = PhaseOutcome::new(phase);
+    after_kills(users, &mut outcome);
+    outcome.commit(users, events);
+
+    inherit_doppelganger_roles(users);
+
+    debug_check_invariants(users, events, "calculate_after_kills");
+}
+
+fn after_kills(users: &mut [Player], outcome: &mut PhaseOutcome) {
     let mut kill_indices: Vec<(usize, Role)> = Vec::new();
 
     for user in users.iter() {
@@ -2302,62 +1644,227 @@ fn calculate_after_kills(users: &mut [Player]) {
         }
     }
 
-    // Set is_alive to false for all Lovers
     for (index, role) in kill_indices {
         if let Some(u) = users.get_mut(index) {
-            if u.is_alive {
-                u.is_alive = false;
-                u.was_killed = true;
-                u.choosed_by.insert(Role::WasKilled);
-                u.choosed_by.insert(role);
+            outcome.kill(u, role);
+        }
+    }
+}
+
+// The Doppelganger takes the role of the player chosen on the first night when that player dies
+fn inherit_doppelganger_roles(users: &mut [Player]) {
+    let doppelganger = Role::Werewolf(WerewolfRole::Doppelganger);
+
+    let Some(dead_index) = users
+        .iter()
+        .position(|u| !u.is_alive && u.additional_role.contains(&doppelganger))
+    else {
+        return;
+    };
+
+    // the pair of Lovers stays with the dead player
+    let roles = users[dead_index]
+        .role
+        .iter()
+        .copied()
+        .filter(|role| *role != Role::Werewolf(WerewolfRole::Lovers))
+        .collect::<Vec<_>>();
+    users[dead_index].additional_role.remove(&doppelganger);
+
+    if let Some(user) = users
+        .iter_mut()
+        .find(|u| u.is_alive && u.role.contains(&doppelganger))
+    {
+        user.role.remove(&doppelganger);
+        roles.into_iter().for_each(|role| insert_user_role(user, role));
+        initialize_user_roles(std::slice::from_mut(user));
+    }
+}
+
+// Choice of the role woken on the first night, the meetings only show the players to each other
+pub fn apply_first_night_choice(users: &mut [Player], role: Role, selected_users: &HashSet<String>) {
+    // the pair of Cupid replaces the Lovers left from the setup
+    if role == Role::Werewolf(WerewolfRole::Cupid) {
+        for user in users.iter_mut() {
+            user.role.remove(&Role::Werewolf(WerewolfRole::Lovers));
+        }
+    }
+
+    for user in users.iter_mut().filter(|u| selected_users.contains(&u.id)) {
+        match role {
+            Role::Werewolf(WerewolfRole::Cupid) => insert_user_role(user, Role::Werewolf(WerewolfRole::Lovers)),
+            Role::Werewolf(WerewolfRole::Doppelganger) => {
+                user.additional_role.insert(role);
             }
+            _ => {}
+        }
+    }
+}
+
+// Players the moderator points at when a team wakes up to meet
+pub fn first_night_hints(role: Role, users: &[Player]) -> Vec<WerewolfHint> {
+    let holders = |role: WerewolfRole| {
+        users
+            .iter()
+            .filter(|u| u.is_alive && u.role.contains(&Role::Werewolf(role)))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    match role {
+        Role::Werewolf(WerewolfRole::Minion) => vec![
+            WerewolfHint::Meeting(Role::Werewolf(WerewolfRole::Werewolf), holders(WerewolfRole::Werewolf)),
+            WerewolfHint::Meeting(role, holders(WerewolfRole::Minion)),
+        ],
+        Role::Werewolf(WerewolfRole::Lovers) => {
+            vec![WerewolfHint::Meeting(role, holders(WerewolfRole::Lovers))]
         }
+        _ => vec![],
     }
 }
 
-// check is user history contains role
-fn check_user_history_for_role(user: &Player, role: &Role) -> bool {
-    user.history_by
+// Faction that has won with the current alive players, None while the game goes on.
+// Only the simulator checks it, the moderator picks the winners at the end of the game.
+#[cfg(test)]
+pub fn calculate_winner(users: &[Player], events: &[GameEvent]) -> Option<Role> {
+    let was_lynched = |user: &Player| {
+        marked_roles(events, &user.id).iter().any(|(_, roles)| {
+            roles.contains(&Role::Werewolf(WerewolfRole::Villager)) && roles.contains(&Role::WasKilled)
+        })
+    };
+
+    if users
+        .iter()
+        .any(|u| u.role.contains(&Role::Werewolf(WerewolfRole::Tanner)) && was_lynched(u))
+    {
+        return Some(Role::Werewolf(WerewolfRole::Tanner));
+    }
+
+    let alive = users.iter().filter(|u| u.is_alive).count();
+    let werewolves = users
+        .iter()
+        .filter(|u| u.is_alive && u.role.contains(&Role::Werewolf(WerewolfRole::Werewolf)))
+        .count();
+    let vampires = users
         .iter()
-        .any(|(_, roles)| roles.contains(role))
+        .filter(|u| {
+            u.is_alive
+                && u.role.contains(&Role::Werewolf(WerewolfRole::Vampire))
+                && !u.role.contains(&Role::Werewolf(WerewolfRole::Werewolf))
+        })
+        .count();
+
+    if werewolves == 0 && vampires == 0 {
+        return Some(Role::Werewolf(WerewolfRole::Villager));
+    }
+
+    if werewolves > 0 && werewolves * 2 >= alive {
+        return Some(Role::Werewolf(WerewolfRole::Werewolf));
+    }
+
+    if werewolves == 0 && vampires * 2 >= alive {
+        return Some(Role::Werewolf(WerewolfRole::Vampire));
+    }
+
+    None
+}
+
+// The Cursed player turns into a werewolf if eaten in the night of `round`
+fn is_cursed_exposed(user: &Player, events: &[GameEvent], round: usize) -> bool {
+    let choices = phase_choices(events, round, &user.id);
+
+    user.role.contains(&Role::Werewolf(WerewolfRole::Cursed))
+        && !user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf))
+        && user.is_alive
+        && !choices.contains(&Role::Werewolf(WerewolfRole::Bodyguard))
+        && !choices.contains(&Role::Werewolf(WerewolfRole::Priest))
+        && !user.has_effect(StatusKind::Blessed)
+}
+
+// Hints shown to the moderator during the night step of the role
+pub fn night_hints(role: Role, users: &[Player], events: &[GameEvent], round: usize) -> Vec<WerewolfHint> {
+    let mut log = Vec::<WerewolfHint>::new();
+
+    if role == Role::Werewolf(WerewolfRole::Werewolf) {
+        users.iter().for_each(|user| {
+            if is_cursed_exposed(user, events, round) {
+                log.push(WerewolfHint::Cursed(user.clone()));
+            }
+        });
+    }
+
+    if role == Role::Werewolf(WerewolfRole::WitchHeal) {
+        log.extend(pending_victims(users, events, round).into_iter().map(WerewolfHint::Victim));
+    }
+
+    if let Some(investigation) = Investigation::of(role) {
+        let suspects = investigation
+            .suspects(users)
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !suspects.is_empty() {
+            log.push(WerewolfHint::Suspects(role, suspects));
+        }
+    }
+
+    log
+}
+
+// Wakes the next night role, the night is resolved after the last one
+fn next_night_step(game_ctx: GameContext, role_info: &'static RoleInfo) {
+    let users = game_ctx.users.get();
+    let next_role = WerewolfRuleset.next_night_role(Some(role_info), &users);
+
+    match next_role {
+        Some(next_role) => {
+            game_ctx
+                .game_state
+                .set(GameState::Game(&WerewolfRuleset, GamePhase::Night(next_role)));
+        }
+        None => {
+            let round = game_ctx.round.get();
+            game_ctx.update_game(|users, events| resolve_night(users, events, round));
+            game_ctx
+                .game_state
+                .set(GameState::Game(&WerewolfRuleset, GamePhase::Day));
+        }
+    }
 }
 
 #[component]
 fn NightTurn(role_info: &'static RoleInfo) -> impl IntoView {
-    let mafia_context = use_context::<GameContext>().expect("MafiaContext not found");
     let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
     let night_description = role_info.get_night_description();
     let show_cursed_convert = create_rw_signal(false);
 
-    let users = move || mafia_context.users.get();
-
     let (selected_users, set_selected_users) = create_signal::<HashSet<String>>(HashSet::new());
     let role = role_info.get_role();
+    let check_card = create_rw_signal(None::<CheckResult>);
 
     let onclick_next_role = move || {
         let selected_users = selected_users.get();
-        game_ctx.users.update(|users| {
-            users.iter_mut().for_each(|u| {
-                if selected_users.contains(&u.id) {
-                    u.choosed_by.insert(role);
-                }
-            })
-        });
+        let round = game_ctx.round.get();
+
+        // the closed check card and the Cursed notice come back here, the choice is already in the log
+        if check_card.get_untracked().is_none() && !show_cursed_convert.get_untracked() {
+            game_ctx.update_game(|users, events| {
+                record_choice(users, events, round, role, &role_actors(users, role), &selected_users);
+            });
+        }
+
+        if open_check_card(game_ctx, role, &selected_users, check_card) {
+            return;
+        }
 
         if show_cursed_convert.get() == false && role == Role::Werewolf(WerewolfRole::Werewolf) {
-            let users = game_ctx.users.get();
-            let is_selected = users.iter().any(|u| {
-                u.role.contains(&Role::Werewolf(WerewolfRole::Cursed))
-                    && !u.role.contains(&Role::Werewolf(WerewolfRole::Werewolf))
-                    && u.is_alive
-                    && !u
-                        .choosed_by
-                        .contains(&Role::Werewolf(WerewolfRole::Bodyguard))
-                    && !u.choosed_by.contains(&Role::Werewolf(WerewolfRole::Priest))
-                    && !u
-                        .additional_role
-                        .contains(&Role::Werewolf(WerewolfRole::Priest))
-                    && selected_users.contains(&u.id)
+            let is_selected = game_ctx.users.with(|users| {
+                game_ctx.events.with(|events| {
+                    users
+                        .iter()
+                        .any(|u| selected_users.contains(&u.id) && is_cursed_exposed(u, events, round))
+                })
             });
 
             if is_selected {
@@ -2366,115 +1873,47 @@ fn NightTurn(role_info: &'static RoleInfo) -> impl IntoView {
             }
         }
 
-        let users = game_ctx.users.get();
-        let next_role = get_next_night_alive_role(role_info, &users);
-
-        match next_role {
-            Some(next_role) => {
-                game_ctx
-                    .game_state
-                    .set(GameState::Werewolf(WerewolfGameState::Night(next_role)));
-            }
-            None => {
-                game_ctx.users.update(|users| {
-                    calculate_night_kills(users);
-                    calculate_after_kills(users);
-                });
-                game_ctx
-                    .game_state
-                    .set(GameState::Werewolf(WerewolfGameState::Day));
-            }
-        }
+        next_night_step(game_ctx, role_info);
     };
 
     let role_targeting_rules = role_info.get_targeting_rules();
     let is_fully_disabled = match role_targeting_rules {
-        NightTargetingRules::OnlyOne => users()
-            .iter()
-            .any(|u| check_user_history_for_role(u, &role)),
+        NightTargetingRules::OnlyOne => game_ctx
+            .events
+            .with(|events| game_ctx.users.with(|users| users.iter().any(|u| was_targeted_by(events, &u.id, &role)))),
         _ => false,
     };
     let is_disabled = move |user: &Player| {
         is_fully_disabled
             || !user.is_alive
             || match role_targeting_rules {
-                NightTargetingRules::NotTheSame => check_user_history_for_role(&user, &role),
+                NightTargetingRules::NotTheSame => {
+                    game_ctx.events.with(|events| was_targeted_by(events, &user.id, &role))
+                }
                 _ => false,
             }
     };
 
-    let role_info = role_info.clone();
-    let is_highlighted = move |user: &Player| {
-        if role_info.get_role() == Role::Werewolf(WerewolfRole::Seer) {
-            return user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf))
-                || user.role.contains(&Role::Werewolf(WerewolfRole::Vampire))
-                || user.role.contains(&Role::Werewolf(WerewolfRole::Lycan));
-        }
+    let narration =
+        Signal::derive(move || game_ctx.users.with(|users| night_script(&WEREWOLF_ROLES, role_info, users)));
 
-        if role_info.get_role() == Role::Werewolf(WerewolfRole::Mentalist) {
-            return user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf))
-                || user.role.contains(&Role::Werewolf(WerewolfRole::Vampire))
-                || user.role.contains(&Role::Werewolf(WerewolfRole::Minion));
-        }
-
-        if role_info.get_role() == Role::Werewolf(WerewolfRole::ParanormalInvestigator) {
-            return user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf))
-                || user.role.contains(&Role::Werewolf(WerewolfRole::Vampire))
-                || user.role.contains(&Role::Werewolf(WerewolfRole::Lycan));
-        }
-
-        false
+    let is_highlighted = move |user: &Player| {
+        Investigation::of(role_info.get_role()).is_some_and(|investigation| investigation.is_suspect(user))
     };
 
-    let game_log: Memo<Vec<WerewolfHint>> = create_memo(move |_| {
-        let mut log = Vec::<WerewolfHint>::new();
-
-        let users = game_ctx.users.get();
-
-        if role_info.get_role() == Role::Werewolf(WerewolfRole::Werewolf) {
-            users.iter().for_each(|user| {
-                if user.role.contains(&Role::Werewolf(WerewolfRole::Cursed))
-                    && !user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf))
-                    && user.is_alive
-                    && !user
-                        .choosed_by
-                        .contains(&Role::Werewolf(WerewolfRole::Bodyguard))
-                    && !user
-                        .choosed_by
-                        .contains(&Role::Werewolf(WerewolfRole::Priest))
-                    && !user
-                        .additional_role
-                        .contains(&Role::Werewolf(WerewolfRole::Priest))
-                {
-                    log.push(WerewolfHint::Cursed(user.clone()));
-                }
-            });
-        }
-
-        if role_info.get_role() == Role::Werewolf(WerewolfRole::Seer) {
-            let ww_users = users
-                .iter()
-                .filter(|user| {
-                    return user.role.contains(&Role::Werewolf(WerewolfRole::Werewolf))
-                        || user.role.contains(&Role::Werewolf(WerewolfRole::Vampire))
-                        || user.role.contains(&Role::Werewolf(WerewolfRole::Lycan))
-                            && user.is_alive;
-                })
-                .cloned()
-                .collect::<Vec<_>>();
-
-            if !ww_users.is_empty() {
-                log.push(WerewolfHint::Seer(ww_users));
-            }
-        }
-
-        log
+    let game_log = create_memo(move |_| {
+        let round = game_ctx.round.get();
+        game_ctx.users.with(|users| game_ctx.events.with(|events| night_hints(role_info.get_role(), users, events, round)))
+            .into_iter()
+            .map(HintLine::from)
+            .collect::<Vec<_>>()
     });
 
     view! {
         <h2>
             {night_description}
         </h2>
+        <NarrationScript lines=narration />
         <DisplayLogs logs=game_log />
         <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
             <div class="flex-1"></div>
@@ -2486,7 +1925,7 @@ fn NightTurn(role_info: &'static RoleInfo) -> impl IntoView {
             />
             </div>
         </div>
-        <NightTurnButtons onclick_next_role />
+        <NextTurnButtons onclick_next_role />
         {move || if show_cursed_convert.get() {
             view!{
                 <div class="absolute -bottom-1 -left-1 -right-1 rounded-2xl p-3 bg-fuchsia-300">
@@ -2502,50 +1941,492 @@ fn NightTurn(role_info: &'static RoleInfo) -> impl IntoView {
         } else {
             "".into_view()
         }}
+        {move || check_card.get().map(|result| view! {
+            <CheckResultCard role_info result on_close=onclick_next_role />
+        })}
     }
 }
 
+// Both Witch potions in one step, the heal is given to one of the pending victims
 #[component]
-fn NightTurnButtons<F>(onclick_next_role: F) -> impl IntoView
-where
-    F: Fn() + 'static,
-{
-    let onclick_prev_role = move |_| {
-        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
-        let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
-            .expect("MafiaContext history not found");
+fn WitchTurn(role_info: &'static RoleInfo) -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
 
-        set_context_history.update(|history| {
-            if let Some(prev_ctx) = history.pop() {
-                game_ctx.set_history(prev_ctx);
-            }
+    let heal = Role::Werewolf(WerewolfRole::WitchHeal);
+    let poison = Role::Werewolf(WerewolfRole::WitchPoison);
+
+    let (users, events, round) = (
+        game_ctx.users.get_untracked(),
+        game_ctx.events.get_untracked(),
+        game_ctx.round.get_untracked(),
+    );
+    let is_heal_used = is_role_used(&events, heal);
+    let is_poison_used = is_role_used(&events, poison);
+    let victims = store_value(
+        pending_victims(&users, &events, round)
+            .into_iter()
+            .map(|u| u.id)
+            .collect::<HashSet<_>>(),
+    );
+    let is_victim = move |user: &Player| victims.with_value(|victims| victims.contains(&user.id));
+
+    let potion = create_rw_signal(if is_heal_used { poison } else { heal });
+    let (healed_users, set_healed_users) = create_signal::<HashSet<String>>(HashSet::new());
+    let (poisoned_users, set_poisoned_users) = create_signal::<HashSet<String>>(HashSet::new());
+
+    let onclick_next_role = move || {
+        let (healed_users, poisoned_users) = (healed_users.get(), poisoned_users.get());
+        game_ctx.update_game(|users, events| {
+            // the Witch holds the heal, both potions are hers
+            let witches = role_actors(users, heal);
+            record_choice(users, events, round, heal, &witches, &healed_users);
+            record_choice(users, events, round, poison, &witches, &poisoned_users);
         });
+
+        next_night_step(game_ctx, role_info);
     };
 
-    let onclick_next = move |_| {
-        let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+    let narration =
+        Signal::derive(move || game_ctx.users.with(|users| night_script(&WEREWOLF_ROLES, role_info, users)));
+
+    let game_log = create_memo(move |_| {
+        game_ctx.users.with(|users| game_ctx.events.with(|events| night_hints(heal, users, events, round)))
+            .into_iter()
+            .map(HintLine::from)
+            .collect::<Vec<_>>()
+    });
+
+    let potion_button = move |role: Role, label: &'static str, is_used: bool| {
+        let class = move || {
+            if is_used {
+                "flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full opacity-40 line-through"
+            } else if potion.get() == role {
+                "flex-1 px-4 py-2 text-sm bg-green-300 rounded-full"
+            } else {
+                "flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+            }
+        };
+
+        view! {
+            <button class=class disabled=is_used on:click=move |_| potion.set(role)>
+                {label}
+            </button>
+        }
+    };
+
+    view! {
+        <h2>
+            {role_info.get_night_description()}
+        </h2>
+        <NarrationScript lines=narration />
+        <DisplayLogs logs=game_log />
+        <div class="flex gap-2 w-full">
+            {potion_button(heal, "🌿 Вылечить", is_heal_used)}
+            {potion_button(poison, "☠️ Отравить", is_poison_used)}
+        </div>
+        <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+            <div class="flex-1"></div>
+            <div class="flex flex-col gap-1 w-full">
+            {move || if potion.get() == heal {
+                view! {
+                    <SelectUsersForVote
+                        is_killed=move |user: &Player| !user.is_alive && !user.was_killed
+                        selected_users=healed_users set_selected_users=set_healed_users
+                        is_disabled=move |user: &Player| is_heal_used || !is_victim(user)
+                        is_highlighted=is_victim
+                        is_single_select=true
+                        highlight_color="ring-red-600/50".to_string()
+                    />
+                }
+            } else {
+                view! {
+                    <SelectUsersForVote
+                        is_killed=move |user: &Player| !user.is_alive && !user.was_killed
+                        selected_users=poisoned_users set_selected_users=set_poisoned_users
+                        is_disabled=move |user: &Player| is_poison_used || !user.is_alive
+                        is_highlighted=is_victim
+                        is_single_select=true
+                        highlight_color="ring-red-600/50".to_string()
+                    />
+                }
+            }}
+            </div>
+        </div>
+        <NextTurnButtons onclick_next_role />
+    }
+}
+
+#[component]
+fn FirstNightTurn(role_info: &'static RoleInfo) -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+    let (selected_users, set_selected_users) = create_signal::<HashSet<String>>(HashSet::new());
+    let role = role_info.get_role();
+    let is_choice = role_info.get_targeting_rules() != NightTargetingRules::No;
+
+    let onclick_next_role = move || {
+        let selected_users = selected_users.get();
+        game_ctx
+            .users
+            .update(|users| apply_first_night_choice(users, role, &selected_users));
+
+        let next_role = WerewolfRuleset.next_first_night_role(Some(role_info), &game_ctx.users.get());
+        game_ctx.game_state.set(GameState::Game(
+            &WerewolfRuleset,
+            next_role.map_or(GamePhase::Day, GamePhase::FirstNight),
+        ));
+    };
+
+    // Cupid picks a pair, Doppelganger picks somebody else
+    let is_disabled = move |user: &Player| {
+        !is_choice
+            || !user.is_alive
+            || match role {
+                Role::Werewolf(WerewolfRole::Cupid) => selected_users.with(|selected| {
+                    selected.len() >= 2 && !selected.contains(&user.id)
+                }),
+                _ => user.role.contains(&role),
+            }
+    };
+
+    // Cupid has to pick the pair before going on
+    let is_next_disabled = Signal::derive(move || {
+        role == Role::Werewolf(WerewolfRole::Cupid) && selected_users.with(|selected| selected.len() != 2)
+    });
+
+    let narration = Signal::derive(move || {
+        game_ctx.users.with(|users| night_script(&WEREWOLF_FIRST_NIGHT_ROLES, role_info, users))
+    });
+
+    let game_log = create_memo(move |_| {
+        game_ctx.users.with(|users| first_night_hints(role, users))
+            .into_iter()
+            .map(HintLine::from)
+            .collect::<Vec<_>>()
+    });
+
+    view! {
+        <h2>
+            {role_info.get_night_description()}
+        </h2>
+        <NarrationScript lines=narration />
+        <DisplayLogs logs=game_log />
+        <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+            <div class="flex-1"></div>
+            <div class="flex flex-col gap-1 w-full">
+            <SelectUsersForVote
+                is_killed=move |user: &Player| !user.is_alive
+                selected_users set_selected_users is_disabled
+                is_highlighted=move |_| false
+                is_single_select={role == Role::Werewolf(WerewolfRole::Doppelganger)}
+            />
+            </div>
+        </div>
+        <NextTurnButtons onclick_next_role is_next_disabled />
+    }
+}
+
+
+// The first day election of the Mayor and the succession when the Mayor dies
+#[component]
+fn MayorElection() -> impl IntoView {
+    let game_ctx = use_context::<GameContext>().expect("MafiaContext not found");
+
+    let is_counting = create_rw_signal(false);
+    let (selected_users, set_selected_users) = create_signal::<HashSet<String>>(HashSet::new());
+
+    let dead_mayor_name = move || {
+        game_ctx.users.with(|users| dead_mayor(users).map(|user| user.name.clone()))
+    };
+    let is_open = move || {
+        let is_day = matches!(game_ctx.game_state.get(), GameState::Game(_, GamePhase::Day));
+
+        game_ctx.users.with(|users| {
+            game_ctx
+                .events
+                .with(|events| is_mayor_choice_pending(users, events, game_ctx.round.get(), is_day))
+        })
+    };
+
+    let set_mayor = move |id: Option<String>| {
         let set_context_history = use_context::<WriteSignal<Vec<GameContextHistory>>>()
             .expect("MafiaContext history not found");
 
         set_context_history.update(|history| history.push(game_ctx.get_history()));
+        let phase = phase_round(game_ctx.game_state.get(), game_ctx.round.get());
+        game_ctx.update_game(|users, events| {
+            elect_mayor(users, id.as_deref());
+            events.push(GameEvent::MayorChosen { phase, mayor: id });
+        });
+        set_selected_users.set(HashSet::new());
+    };
+
+    let onclick_elect = move |_| {
+        if let [id] = selected_users.get().into_iter().collect::<Vec<_>>().as_slice() {
+            set_mayor(Some(id.clone()));
+        }
+    };
+
+    // nobody gets the badge, the game goes on without a Mayor
+    let onclick_skip = move |_| set_mayor(None);
 
-        onclick_next_role();
+    let on_votes_counted = move |votes: HashMap<String, String>| {
+        let leaders = day_vote_leaders(&WerewolfRuleset, &game_ctx.users.get(), &votes);
+        set_selected_users.set(leaders);
+        is_counting.set(false);
     };
 
     view! {
-        <div class="flex gap-2 w-full items-center">
-            <button
-                class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full flex items-center justify-center"
-                on:click=onclick_prev_role
-            >
-                "←"
-            </button>
-            <button
-                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
-                on:click=onclick_next
-            >
-                "Далее"
-            </button>
-        </div>
+        <Show when=is_open>
+            <div class="fixed inset-0 z-40 bg-white flex flex-col gap-3 p-3">
+                {move || if is_counting.get() {
+                    view! {
+                        <VoteCount
+                            title="Выборы мэра"
+                            voters=day_voters(&WerewolfRuleset, game_ctx.users.get_untracked())
+                            is_disabled=|_, user| !user.is_alive
+                            on_done=on_votes_counted
+                            on_close=move || is_counting.set(false)
+                        />
+                    }.into_view()
+                } else {
+                    view! {
+                        <h2>
+                            {move || match dead_mayor_name() {
+                                Some(name) => format!("Мэр {} погиб. Кому он передаёт значок?", name),
+                                None => "Выборы мэра".to_string(),
+                            }}
+                        </h2>
+                        <Show when=move || selected_users.with(|selected| selected.len() > 1)>
+                            <div class="text-xs text-gray-500">"Ничья, выберите мэра среди лидеров."</div>
+                        </Show>
+                        <div class="flex-1 flex flex-col relative overflow-auto px-4 -mx-4">
+                            <div class="flex-1"></div>
+                            <div class="flex flex-col gap-1 w-full pb-0.5">
+                                <SelectUsersForVote
+                                    selected_users
+                                    set_selected_users
+                                    is_killed=move |user: &Player| !user.is_alive
+                                    is_disabled=move |user| !user.is_alive
+                                    is_highlighted=move |_| false
+                                    is_single_select=true
+                                />
+                            </div>
+                        </div>
+                        <div class="flex gap-2 w-full items-center">
+                            <button
+                                class="flex-1 px-4 py-2 text-sm bg-gray-200 rounded-full"
+                                on:click=onclick_skip
+                            >
+                                {move || if dead_mayor_name().is_some() { "Никому" } else { "Без мэра" }}
+                            </button>
+                            <Show when=move || dead_mayor_name().is_none()>
+                                <button
+                                    class="w-9 px-2 py-2 text-sm bg-gray-200 rounded-full flex items-center justify-center"
+                                    on:click=move |_| is_counting.set(true)
+                                >
+                                    "🗳️"
+                                </button>
+                            </Show>
+                            <button
+                                class="flex-grow-[2] px-4 py-2 text-sm bg-gray-200 rounded-full"
+                                on:click=onclick_elect
+                            >
+                                {move || if dead_mayor_name().is_some() { "Передать" } else { "Выбрать" }}
+                            </button>
+                        </div>
+                    }.into_view()
+                }}
+            </div>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_night_roles(users: &[Player]) -> Vec<Role> {
+        let mut roles = vec![];
+        let mut role = WerewolfRuleset.next_first_night_role(None, users);
+
+        while let Some(role_info) = role {
+            roles.push(role_info.get_role());
+            role = WerewolfRuleset.next_first_night_role(Some(role_info), users);
+        }
+
+        roles
+    }
+
+    #[test]
+    fn test_first_night_plays_only_active_roles() {
+        let mut users = test_players(&[
+            Role::Werewolf(WerewolfRole::Cupid),
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::None,
+            Role::None,
+        ]);
+
+        assert_eq!(first_night_roles(&users), vec![Role::Werewolf(WerewolfRole::Cupid)]);
+
+        // the Lovers picked by Cupid wake up right after
+        apply_first_night_choice(
+            &mut users,
+            Role::Werewolf(WerewolfRole::Cupid),
+            &HashSet::from(["003".to_string(), "004".to_string()]),
+        );
+        assert_eq!(
+            first_night_roles(&users),
+            vec![Role::Werewolf(WerewolfRole::Cupid), Role::Werewolf(WerewolfRole::Lovers)]
+        );
+
+        users[0].is_alive = false;
+        users[2].role.insert(Role::Werewolf(WerewolfRole::Minion));
+        assert_eq!(
+            first_night_roles(&users),
+            vec![Role::Werewolf(WerewolfRole::Minion), Role::Werewolf(WerewolfRole::Lovers)]
+        );
+        assert!(matches!(
+            first_night_hints(Role::Werewolf(WerewolfRole::Minion), &users).as_slice(),
+            [WerewolfHint::Meeting(_, wolves), WerewolfHint::Meeting(_, minions)]
+                if wolves[0].id == "002" && minions[0].id == "003"
+        ));
+    }
+
+    #[test]
+    fn test_cupid_picks_exactly_one_pair() {
+        let cupid = Role::Werewolf(WerewolfRole::Cupid);
+        let lovers = Role::Werewolf(WerewolfRole::Lovers);
+        let mut users = test_players(&[cupid, lovers, Role::None, Role::None]);
+        let active_roles = HashSet::from([cupid, lovers, Role::Werewolf(WerewolfRole::Werewolf)]);
+
+        // a single Lover left from the setup is an error even when Cupid picks the pair
+        let issues = validate_werewolf_setup(&users, &active_roles);
+        assert!(issues.iter().any(|issue| issue.message == "Lovers должно быть ровно двое"));
+
+        users[1].role.remove(&lovers);
+        let issues = validate_werewolf_setup(&users, &active_roles);
+        assert!(!issues.iter().any(|issue| issue.message == "Lovers должно быть ровно двое"));
+
+        // the pair of Cupid replaces the Lovers given before
+        users[1].role.insert(lovers);
+        apply_first_night_choice(&mut users, cupid, &HashSet::from(["003".to_string(), "004".to_string()]));
+        let lovers_ids = users
+            .iter()
+            .filter(|u| u.role.contains(&lovers))
+            .map(|u| u.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(lovers_ids, vec!["003", "004"]);
+    }
+
+    #[test]
+    fn test_doppelganger_inherits_role_on_death() {
+        let mut users = test_players(&[
+            Role::Werewolf(WerewolfRole::Doppelganger),
+            Role::Werewolf(WerewolfRole::Seer),
+            Role::Werewolf(WerewolfRole::Werewolf),
+        ]);
+
+        apply_first_night_choice(
+            &mut users,
+            Role::Werewolf(WerewolfRole::Doppelganger),
+            &HashSet::from(["002".to_string()]),
+        );
+        calculate_after_kills(&mut users, &mut vec![], 0);
+        assert!(users[0].role.contains(&Role::Werewolf(WerewolfRole::Doppelganger)));
+
+        users[1].is_alive = false;
+        calculate_after_kills(&mut users, &mut vec![], 0);
+
+        assert_eq!(users[0].role, HashSet::from([Role::Werewolf(WerewolfRole::Seer)]));
+        assert!(users[1].additional_role.is_empty());
+    }
+
+    #[test]
+    fn test_witch_sees_pending_victims() {
+        let mut users = test_players(&[
+            Role::Werewolf(WerewolfRole::Werewolf),
+            Role::Werewolf(WerewolfRole::WitchHeal),
+            Role::None,
+            Role::None,
+        ]);
+        let heal = Role::Werewolf(WerewolfRole::WitchHeal);
+        let poison = Role::Werewolf(WerewolfRole::WitchPoison);
+
+        let mut events = vec![];
+        test_choice(&users, &mut events, 0, Role::Werewolf(WerewolfRole::Werewolf), &["003"]);
+        let victims = pending_victims(&users, &events, 0);
+        assert_eq!(victims.iter().map(|u| u.id.as_str()).collect::<Vec<_>>(), ["003"]);
+        assert!(users.iter().all(|u| u.is_alive));
+
+        // the witch step is the last one, the poison has no step of its own
+        let witch_step = WerewolfRuleset.next_night_role(None, &users).and_then(|r| {
+            WerewolfRuleset.next_night_role(Some(r), &users)
+        });
+        assert_eq!(witch_step.map(|r| r.get_role()), Some(heal));
+        assert!(WerewolfRuleset.next_night_role(witch_step, &users).is_none());
+
+        assert!(!is_role_used(&events, heal));
+        let witches = role_actors(&users, heal);
+        record_choice(&users, &mut events, 0, heal, &witches, &HashSet::from(["003".to_string()]));
+        record_choice(&users, &mut events, 0, poison, &witches, &HashSet::from(["004".to_string()]));
+        assert!(is_role_used(&events, heal));
+        assert!(is_role_used(&events, poison));
+
+        resolve_night(&mut users, &mut events, 0);
+        assert!(users[2].is_alive);
+        assert!(!users[3].is_alive);
+
+        // the poison is the Witch's choice, the heal saved the victim of the werewolves
+        assert!(events.contains(&GameEvent::Targeted {
+            phase: 0,
+            actor_role: poison,
+            actors: vec!["002".to_string()],
+            target: "004".to_string(),
+        }));
+        assert!(events.contains(&GameEvent::Protected {
+            phase: 0,
+            actor_role: heal,
+            target: "003".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_mayor_election_and_succession() {
+        let mut users = test_players(&[Role::Werewolf(WerewolfRole::Werewolf), Role::None, Role::None]);
+        assert!(is_mayor_election_open(&users, &[], 0));
+        assert!(!is_mayor_election_open(&users, &[], 2));
+
+        // the village decided to play without a Mayor
+        let skipped = [GameEvent::MayorChosen { phase: 1, mayor: None }];
+        assert!(!is_mayor_election_open(&users, &skipped, 0));
+
+        elect_mayor(&mut users, Some("002"));
+        assert!(!is_mayor_election_open(&users, &[], 0));
+        assert_eq!(WerewolfRuleset.vote_weight(&users[1]), 2);
+
+        day_kill_user(&mut users[1], &mut vec![], 0);
+        assert_eq!(dead_mayor(&users).map(|u| u.id.as_str()), Some("002"));
+
+        // the dying Mayor names the successor
+        elect_mayor(&mut users, Some("003"));
+        assert!(dead_mayor(&users).is_none());
+        assert!(is_mayor(&users[2]));
+        assert_eq!(WerewolfRuleset.vote_weight(&users[1]), 0);
+    }
+
+    #[test]
+    fn test_brutal_mayor_takes_the_victim_before_the_succession() {
+        let mut users = test_players(&[Role::Werewolf(WerewolfRole::Werewolf), Role::None, Role::None]);
+        let mut events = vec![];
+        users[1].attributes.insert(RoleAttribute::Brutal);
+        elect_mayor(&mut users, Some("002"));
+
+        apply_day_vote(&mut users, &mut events, 2, &HashSet::from(["002".to_string()]));
+        assert!(dead_mayor(&users).is_some());
+        assert!(!is_mayor_choice_pending(&users, &events, 4, false));
+
+        take_revenge(&mut users, &mut events, 4, "002", Some("001"));
+        assert!(is_mayor_choice_pending(&users, &events, 4, false));
     }
 }